@@ -0,0 +1,1454 @@
+use crate::keys::{chord_key_code, hotkey_chords, same_hotkey, variant_modifiers, HotkeyAction};
+use crate::terminal::Terminal;
+use anyhow::{anyhow, bail, Context, Result};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    pub hotkey: HotkeyConfig,
+    /// Optional second hotkey that edits the clipboard instead of the selection
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clipboard_hotkey: Option<HotkeyConfig>,
+    /// More hotkeys, each opening the selection or the clipboard its own way
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub actions: Vec<NamedAction>,
+    pub terminal: TerminalConfig,
+    #[serde(default)]
+    pub editor: EditorConfig,
+    #[serde(default)]
+    pub session: SessionConfig,
+    #[serde(default)]
+    pub menu: MenuConfig,
+    #[serde(default)]
+    pub timing: TimingConfig,
+    #[serde(default)]
+    pub shortcuts: ShortcutsConfig,
+    #[serde(default)]
+    pub notifications: NotificationsConfig,
+    #[serde(default)]
+    pub automation: AutomationConfig,
+    #[serde(default)]
+    pub debug: DebugConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HotkeyConfig {
+    #[serde(default)]
+    pub modifiers: Vec<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub key: String,
+    /// Raw macOS key code, for keys that have no name (set instead of `key`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_code: Option<u16>,
+    /// Raw modifier flag bits, added to `modifiers`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modifier_flags: Option<u64>,
+    /// Chords that must follow this one (leader key), e.g. `Cmd+;` then `e`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sequence: Vec<HotkeyConfig>,
+    /// Only fire once the (last) chord has been held this long, in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub long_press_ms: Option<u64>,
+    /// Changes to the session when a modifier (by name) is held in addition
+    /// to the hotkey's own, e.g. `shift = "select_all"`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub variants: HashMap<String, ModifierVariant>,
+    /// Whether other modifiers may be held too ("at_least") or not ("exact")
+    #[serde(default, skip_serializing_if = "ModifierMatch::is_exact")]
+    pub modifier_match: ModifierMatch,
+    /// Whether the hotkey is listened for; turned off, it's kept in the config
+    /// but does nothing (not for shortcuts or sequence chords)
+    #[serde(default = "enabled_by_default", skip_serializing_if = "is_enabled")]
+    pub enabled: bool,
+}
+
+/// A hotkey of its own with what it edits and how (`[[actions]]`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NamedAction {
+    /// Shown in logs, e.g. "markdown"
+    pub name: String,
+    pub hotkey: HotkeyConfig,
+    /// Whether the selection or the clipboard's content is edited
+    #[serde(default)]
+    pub source: ActionSource,
+    /// File extension the text is edited with (e.g. "md"), instead of the
+    /// one found from the app
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extension: Option<String>,
+}
+
+/// What a named action edits
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionSource {
+    /// Copy the selection and paste the result back, like `[hotkey]`
+    #[default]
+    Selection,
+    /// Edit the clipboard's content, like `[clipboard_hotkey]`
+    Clipboard,
+}
+
+impl Default for HotkeyConfig {
+    fn default() -> Self {
+        Self {
+            modifiers: Vec::new(),
+            key: String::new(),
+            key_code: None,
+            modifier_flags: None,
+            sequence: Vec::new(),
+            long_press_ms: None,
+            variants: HashMap::new(),
+            modifier_match: ModifierMatch::Exact,
+            enabled: true,
+        }
+    }
+}
+
+impl HotkeyConfig {
+    /// A hotkey pressing `key` with the named modifiers
+    pub fn new(modifiers: &[&str], key: &str) -> Self {
+        Self {
+            modifiers: modifiers.iter().map(|m| m.to_string()).collect(),
+            key: key.to_string(),
+            ..Self::default()
+        }
+    }
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+fn is_enabled(enabled: &bool) -> bool {
+    *enabled
+}
+
+/// How the modifiers held with a key are compared to a hotkey's
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModifierMatch {
+    /// Exactly the hotkey's modifiers (Caps Lock aside)
+    #[default]
+    Exact,
+    /// The hotkey's modifiers, and any others
+    AtLeast,
+}
+
+impl ModifierMatch {
+    fn is_exact(&self) -> bool {
+        *self == ModifierMatch::Exact
+    }
+}
+
+/// What holding an extra modifier with the hotkey changes about the session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModifierVariant {
+    /// Select everything in the text field before copying
+    SelectAll,
+    /// Paste after the selection, as with `output_mode = "append"`
+    Append,
+    /// Only put the result on the clipboard
+    ClipboardOnly,
+    /// With `scratchpad_path`, paste the whole scratchpad into the frontmost
+    /// app once the editor closes
+    PasteScratchpad,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TerminalConfig {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    /// Save the size the edit window had when it closed as the new width/height
+    /// (Kitty, Alacritty and WezTerm only)
+    #[serde(default)]
+    pub remember_size: bool,
+    /// Size the window to the text being edited instead of using width/height
+    #[serde(default)]
+    pub auto_size: bool,
+    /// Smallest automatic size (default 40 × 10)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_width: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_height: Option<u32>,
+    /// Largest automatic size (default: width and height)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_width: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_height: Option<u32>,
+    /// Find out once whether the terminal can be waited on, by opening a
+    /// short-lived window, instead of relying on the built-in list
+    #[serde(default)]
+    pub probe_waiting: bool,
+    /// How the edit window opens: "normal", "maximized" or "fullscreen"
+    #[serde(default)]
+    pub window_mode: WindowMode,
+    /// Title the edit window after the app the text came from, e.g.
+    /// "helix-anywhere: Mail (14:32)"
+    #[serde(default)]
+    pub set_window_title: bool,
+}
+
+/// How the edit window opens
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowMode {
+    /// At the configured width and height
+    #[default]
+    Normal,
+    /// Filling the screen, next to other windows
+    Maximized,
+    /// In its own fullscreen space
+    Fullscreen,
+}
+
+/// Settings for the editor process launched in the terminal
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EditorConfig {
+    /// Directory used as `XDG_CONFIG_HOME` for the editor, so Helix reads
+    /// `<dir>/helix/config.toml` instead of the user's main configuration
+    pub helix_config_dir: Option<PathBuf>,
+    /// Use the file extension in the frontmost window's title (e.g. `main.rs`)
+    /// for the temp file, so the editor picks the matching syntax
+    pub extension_from_window_title: bool,
+    /// File extension to use per app bundle identifier when the window title
+    /// doesn't reveal one, e.g. `"com.apple.dt.Xcode" = "swift"`
+    pub app_extensions: HashMap<String, String>,
+    /// File extension of the temp file when neither the window title nor
+    /// `app_extensions` gives one (e.g. `"md"`); `txt` if unset
+    pub extension: Option<String>,
+    /// Extra environment variables for the editor (e.g. `RUST_LOG`, `HELIX_RUNTIME`)
+    pub env: HashMap<String, String>,
+    /// Directory the editor starts in, so its file picker and language
+    /// servers resolve paths relative to a project
+    pub working_dir: Option<PathBuf>,
+    /// Editors to use, in order of preference; the first one installed is
+    /// picked (e.g. `["hx", "nvim", "vim"]`). Empty means `hx`.
+    pub editors: Vec<String>,
+    /// Extra command line arguments for the editor, placed before the file
+    /// (e.g. `["-c", "/path/to/prose.toml"]` for Helix)
+    pub startup_args: Vec<String>,
+    /// Helix `config.toml` to use instead of the user's, passed with `-c`
+    /// (only when the editor is Helix)
+    pub config_path: Option<PathBuf>,
+    /// Soft-wrap Helix at the window width, through a copy of the Helix
+    /// config generated for each session
+    pub wrap_to_window: bool,
+}
+
+/// Behavior of the edit session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionConfig {
+    /// Treat the edit as unchanged if only trailing whitespace on lines differs
+    pub ignore_trailing_whitespace: bool,
+    /// Treat the edit as unchanged if only the final newline(s) differ
+    pub ignore_final_newline_only: bool,
+    /// Only paste if focus is back on the original window, otherwise leave
+    /// the edited text on the clipboard
+    pub verify_paste_target: bool,
+    /// Count session outcomes in a local stats file (shown under Statistics…)
+    pub record_stats: bool,
+    /// Collect selections as snippets and edit them all at once (see `collect`)
+    pub collect_snippets: bool,
+    /// What to do with the edited text
+    pub output_mode: OutputMode,
+    /// Select the pasted text afterwards, so it can be copied or edited again right away
+    pub reselect_after_paste: bool,
+    /// How trailing newlines of the edited text (Helix adds one on save) are handled
+    pub trailing_newline: TrailingNewline,
+    /// Whitespace removed from the selection before editing, and put back
+    /// around the result
+    pub trim_selection: TrimSelection,
+    /// Shell command run after a successful edit, with the edited text on
+    /// stdin; `{file}` is replaced by the path of a file holding it
+    pub post_edit_command: Option<String>,
+    /// Keep the last edit on disk so it can be reopened after a restart
+    pub remember_last_edit: bool,
+    /// When the selection is an existing file path or a URL, open the file
+    /// (or the URL's content) instead of the selected text
+    pub smart_open: bool,
+    /// Paste the text back every time it's saved, keeping the editor open;
+    /// the session ends when the editor closes
+    pub paste_on_save: bool,
+    /// How the selection is read and replaced
+    pub capture_mode: CaptureMode,
+    /// Apps (bundle identifiers) where `capture_mode = "ax"` is used;
+    /// everywhere else the selection goes through the clipboard
+    pub ax_apps: Vec<String>,
+    /// Edit rich selections (HTML on the clipboard) as Markdown, and paste
+    /// the result back as HTML
+    pub rich_text_as_markdown: bool,
+    /// Also put a simple HTML version of plain-text results on the clipboard,
+    /// so rich editors (mail, docs) keep the paragraphs and line breaks
+    pub paste_html: bool,
+    /// Open this file (e.g. `~/notes/scratch.md`) with the hotkey instead of
+    /// the selection, keeping notes between sessions; nothing is pasted back
+    pub scratchpad_path: Option<PathBuf>,
+    /// Put the clipboard's previous content back once the result is pasted
+    pub restore_clipboard_after_paste: bool,
+    /// Leave the result on the clipboard after pasting, even with
+    /// `restore_clipboard_after_paste`
+    pub keep_result_on_clipboard: bool,
+    /// Apps (bundle identifiers) where the hotkey does nothing, e.g. password
+    /// managers or games that react badly to the simulated copy
+    pub ignored_apps: Vec<String>,
+    /// How the result is put into the app: pasted, or typed key by key
+    pub paste_mode: PasteMode,
+    /// How the paste combo is pressed: "cgevent" or "applescript"
+    pub paste_method: PasteMethod,
+}
+
+/// Apps whose text views are known to work with `capture_mode = "ax"`
+pub const KNOWN_AX_APPS: &[&str] = &["com.apple.TextEdit", "com.apple.Notes"];
+
+impl SessionConfig {
+    /// Whether the selection in the app with this bundle identifier is read
+    /// and replaced through the Accessibility API
+    pub fn uses_ax(&self, bundle_id: Option<&str>) -> bool {
+        self.capture_mode == CaptureMode::Ax
+            && bundle_id.is_some_and(|id| self.ax_apps.iter().any(|app| app == id))
+    }
+
+    /// Whether the hotkey is ignored in the app with this bundle identifier
+    ///
+    /// Bundle identifiers are compared ignoring case.
+    pub fn is_app_ignored(&self, bundle_id: &str) -> bool {
+        self.ignored_apps.iter().any(|app| app.eq_ignore_ascii_case(bundle_id))
+    }
+
+    /// Whether the clipboard's previous content is put back after pasting
+    ///
+    /// `keep_result_on_clipboard` wins over `restore_clipboard_after_paste`,
+    /// and with `output_mode = "clipboard_only"` the result always stays.
+    pub fn restores_clipboard_after_paste(&self) -> bool {
+        self.restore_clipboard_after_paste
+            && !self.keep_result_on_clipboard
+            && self.output_mode != OutputMode::ClipboardOnly
+    }
+}
+
+/// How the selection is read from the app and the result put back
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureMode {
+    /// Simulate copy and paste
+    #[default]
+    Clipboard,
+    /// Read and replace the focused text field's selection through the
+    /// Accessibility API, without touching the clipboard
+    #[serde(alias = "accessibility")]
+    Ax,
+}
+
+/// How the edited text is delivered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputMode {
+    /// Paste over the selection
+    #[default]
+    Replace,
+    /// Paste after the selection, keeping the original
+    Append,
+    /// Only put the result on the clipboard
+    ClipboardOnly,
+}
+
+/// How the edited text is put into the app
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PasteMode {
+    /// Simulate the paste combo (Cmd+V)
+    #[default]
+    Clipboard,
+    /// Type the text key by key, for apps that ignore a synthetic paste;
+    /// text with characters that can't be typed is pasted instead
+    Type,
+}
+
+/// How the paste combo is pressed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PasteMethod {
+    /// Post key events (needs Accessibility access)
+    #[default]
+    CgEvent,
+    /// Have System Events press the keys (needs Automation access)
+    AppleScript,
+}
+
+/// How trailing newlines of the edited text are handled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrailingNewline {
+    /// Remove all trailing newlines
+    #[default]
+    StripAll,
+    /// Keep at most one trailing newline
+    KeepOne,
+    /// End with as many newlines as the original selection did
+    PreserveOriginal,
+}
+
+/// Which whitespace of the selection is trimmed before editing
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrimSelection {
+    /// Edit the selection as it is
+    #[default]
+    None,
+    /// Trim whitespace (including newlines) at the start and end
+    Edges,
+    /// Trim trailing spaces and tabs of each line
+    Lines,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            ignore_trailing_whitespace: false,
+            ignore_final_newline_only: false,
+            verify_paste_target: true,
+            record_stats: false,
+            collect_snippets: false,
+            output_mode: OutputMode::Replace,
+            reselect_after_paste: false,
+            trailing_newline: TrailingNewline::StripAll,
+            trim_selection: TrimSelection::None,
+            post_edit_command: None,
+            remember_last_edit: true,
+            smart_open: false,
+            paste_on_save: false,
+            capture_mode: CaptureMode::Clipboard,
+            ax_apps: KNOWN_AX_APPS.iter().map(|app| app.to_string()).collect(),
+            rich_text_as_markdown: false,
+            paste_html: false,
+            scratchpad_path: None,
+            restore_clipboard_after_paste: false,
+            keep_result_on_clipboard: false,
+            ignored_apps: Vec::new(),
+            paste_mode: PasteMode::Clipboard,
+            paste_method: PasteMethod::CgEvent,
+        }
+    }
+}
+
+/// Menu bar customization
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MenuConfig {
+    /// Show the About line at the top of the menu
+    pub show_about: bool,
+    /// Text of the About line (defaults to the app name and version)
+    pub about_label: Option<String>,
+    /// URL opened by the About panel's Open Website button
+    pub about_url: String,
+    /// Also show the app in the Dock (takes effect after a relaunch)
+    pub show_in_dock: bool,
+    /// Show "H" in the menu bar instead of the icon (takes effect after a relaunch)
+    pub text_icon: bool,
+}
+
+impl Default for MenuConfig {
+    fn default() -> Self {
+        Self {
+            show_about: true,
+            about_label: None,
+            about_url: env!("CARGO_PKG_REPOSITORY").to_string(),
+            show_in_dock: false,
+            text_icon: false,
+        }
+    }
+}
+
+/// Which events show a notification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationsConfig {
+    /// Show notifications at all; the settings below pick which ones
+    pub show: bool,
+    /// Hotkey recording started
+    pub record_started: bool,
+    /// A hotkey was recorded, picked or reset, or recording failed
+    pub record_done: bool,
+    /// The edited text was pasted back (or put on the clipboard), or a snippet was collected
+    pub edit_done: bool,
+    /// An edit failed, or its result couldn't be pasted back
+    pub edit_error: bool,
+    /// The hotkey was pressed with nothing selected
+    pub no_selection: bool,
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            show: true,
+            record_started: true,
+            record_done: true,
+            edit_done: false,
+            edit_error: true,
+            no_selection: false,
+        }
+    }
+}
+
+/// Kinds of events that can show a notification
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationEvent {
+    RecordStarted,
+    RecordDone,
+    EditDone,
+    EditError,
+    NoSelection,
+    /// All settings were reset from the menu; shown whenever notifications are on
+    SettingsReset,
+}
+
+impl NotificationsConfig {
+    /// Whether notifications are shown for this kind of event
+    pub fn enabled(&self, event: NotificationEvent) -> bool {
+        self.show && match event {
+            NotificationEvent::RecordStarted => self.record_started,
+            NotificationEvent::RecordDone => self.record_done,
+            NotificationEvent::EditDone => self.edit_done,
+            NotificationEvent::EditError => self.edit_error,
+            NotificationEvent::NoSelection => self.no_selection,
+            NotificationEvent::SettingsReset => true,
+        }
+    }
+}
+
+/// Key combos sent to the frontmost app to copy the selection and paste the result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShortcutsConfig {
+    pub copy: HotkeyConfig,
+    pub paste: HotkeyConfig,
+    /// Overrides per app bundle identifier, e.g. Ctrl+Shift+C/V for a remote desktop client
+    pub apps: HashMap<String, AppShortcuts>,
+}
+
+/// Copy/paste combos for one app; unset combos use the global ones
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AppShortcuts {
+    pub copy: Option<HotkeyConfig>,
+    pub paste: Option<HotkeyConfig>,
+    /// Pause between the app coming back to the front and pasting, instead
+    /// of `timing.paste_delay_ms`
+    pub paste_delay_ms: Option<u64>,
+}
+
+impl Default for ShortcutsConfig {
+    fn default() -> Self {
+        Self {
+            copy: HotkeyConfig::new(&["cmd"], "c"),
+            paste: HotkeyConfig::new(&["cmd"], "v"),
+            apps: HashMap::new(),
+        }
+    }
+}
+
+impl ShortcutsConfig {
+    /// Copy combo for the given app
+    pub fn copy_for(&self, bundle_id: Option<&str>) -> &HotkeyConfig {
+        bundle_id
+            .and_then(|id| self.apps.get(id))
+            .and_then(|app| app.copy.as_ref())
+            .unwrap_or(&self.copy)
+    }
+
+    /// Paste combo for the given app
+    pub fn paste_for(&self, bundle_id: Option<&str>) -> &HotkeyConfig {
+        bundle_id
+            .and_then(|id| self.apps.get(id))
+            .and_then(|app| app.paste.as_ref())
+            .unwrap_or(&self.paste)
+    }
+}
+
+/// Wakeup intervals, tunable to trade responsiveness for battery life
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimingConfig {
+    /// Longest time the hotkey listener sleeps in its run loop before waking up.
+    /// Commands wake the listener immediately, so this only bounds idle wakeups.
+    pub listener_wait_ms: u64,
+    /// Interval between temp file checks for terminals that need polling
+    pub file_poll_ms: u64,
+    /// Repeated hotkey presses within this window only trigger once
+    pub trigger_cooldown_ms: u64,
+    /// Time allowed between the chords of a hotkey sequence
+    pub sequence_timeout_ms: u64,
+    /// How long to wait for the original app to come back to the front before pasting
+    pub activation_timeout_ms: u64,
+    /// How long `open`/`osascript` may take to launch the terminal before giving up
+    pub launch_timeout_ms: u64,
+    /// Pause after the hotkey fires before copying, so the hotkey's keys can be released
+    pub focus_grace_ms: u64,
+    /// Pause between the original app coming back to the front and pasting;
+    /// apps can override it under `[shortcuts.apps]`
+    pub paste_delay_ms: u64,
+    /// Hotkey presses within this long after a session ends are ignored, so
+    /// keys still held (or the simulated paste) don't start another one
+    pub post_session_cooldown_ms: u64,
+    /// How long recording a new hotkey waits for a key press, in seconds
+    pub recording_timeout_secs: u64,
+}
+
+impl Default for TimingConfig {
+    fn default() -> Self {
+        Self {
+            listener_wait_ms: 1000,
+            file_poll_ms: 100,
+            trigger_cooldown_ms: 300,
+            sequence_timeout_ms: 1000,
+            activation_timeout_ms: 1000,
+            launch_timeout_ms: 10000,
+            focus_grace_ms: 30,
+            paste_delay_ms: 0,
+            post_session_cooldown_ms: 250,
+            recording_timeout_secs: 10,
+        }
+    }
+}
+
+/// Ways for scripts and other tools to drive the app
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutomationConfig {
+    /// Accept commands on a Unix socket in the app's data directory (see
+    /// `control`); only the current user can connect. Applied at startup.
+    pub control_socket: bool,
+}
+
+/// Troubleshooting options, not meant for everyday use
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DebugConfig {
+    /// Listen for the hotkey at the HID level instead of the login session, so
+    /// it's seen before apps that install their own session taps. Affects
+    /// keyboard events of all users and needs Input Monitoring permission.
+    /// Applied at startup.
+    pub hid_event_tap: bool,
+    /// Log every key event sent to apps (to copy, paste, select), with its key
+    /// code, flags and time, to compare with what the app received
+    pub trace_keystrokes: bool,
+}
+
+/// Top-level sections in the order they are written, with the comment above each
+///
+/// Sections missing here are written after these, so new ones stay visible
+/// until they get their place.
+const SECTIONS: &[(&str, &str)] = &[
+    ("hotkey", "Hotkey that opens the selection in the editor"),
+    ("clipboard_hotkey", "Hotkey that opens the clipboard in the editor"),
+    ("actions", "More hotkeys, each with its own source and file type"),
+    ("terminal", "Terminal the editor runs in, and its window size"),
+    ("editor", "Editor process: config directory, environment, file type"),
+    ("session", "What happens to the selection and the edited text"),
+    ("shortcuts", "Keys sent to apps to copy the selection and paste the result"),
+    ("notifications", "Which events show a notification"),
+    ("menu", "Menu bar customization"),
+    ("timing", "Timeouts and polling intervals"),
+    ("automation", "Control of the app by scripts and other tools"),
+    ("debug", "Troubleshooting options"),
+];
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            hotkey: HotkeyConfig::new(&["cmd", "shift"], "semicolon"),
+            clipboard_hotkey: None,
+            actions: Vec::new(),
+            terminal: TerminalConfig {
+                name: "ghostty".to_string(),
+                width: 100,
+                height: 30,
+                remember_size: false,
+                auto_size: false,
+                probe_waiting: false,
+                window_mode: WindowMode::Normal,
+                set_window_title: false,
+                min_width: None,
+                min_height: None,
+                max_width: None,
+                max_height: None,
+            },
+            editor: EditorConfig::default(),
+            session: SessionConfig::default(),
+            menu: MenuConfig::default(),
+            timing: TimingConfig::default(),
+            shortcuts: ShortcutsConfig::default(),
+            notifications: NotificationsConfig::default(),
+            automation: AutomationConfig::default(),
+            debug: DebugConfig::default(),
+        }
+    }
+}
+
+/// Config file given on the command line (`--config`), used instead of the default one
+static CONFIG_PATH_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+impl Config {
+    /// Use `path` as the config file for the rest of the process
+    ///
+    /// Loading, saving and reloading all go through it. Can only be set once.
+    pub fn set_config_path(path: PathBuf) -> Result<()> {
+        CONFIG_PATH_OVERRIDE
+            .set(path)
+            .map_err(|path| anyhow!("Config path already set, not using {:?}", path))
+    }
+
+    /// Get the config directory path
+    pub fn config_dir() -> Option<PathBuf> {
+        if let Some(path) = CONFIG_PATH_OVERRIDE.get() {
+            return Some(path.parent().unwrap_or(Path::new(".")).to_path_buf());
+        }
+        ProjectDirs::from("com", "helix-anywhere", "helix-anywhere")
+            .map(|dirs| dirs.config_dir().to_path_buf())
+    }
+
+    /// Get the config file path
+    pub fn config_path() -> Option<PathBuf> {
+        if let Some(path) = CONFIG_PATH_OVERRIDE.get() {
+            return Some(path.clone());
+        }
+        Self::config_dir().map(|dir| dir.join("config.toml"))
+    }
+
+    /// Load config from file, or create default if not exists
+    ///
+    /// A file that can't be parsed (e.g. empty, or cut short by a crash while
+    /// saving) is moved to `config.toml.bak` and replaced with the defaults,
+    /// and invalid settings in one that can are replaced by their defaults,
+    /// see `load_recovering`.
+    pub fn load() -> Result<Self> {
+        Self::load_recovering().map(|(config, _)| config)
+    }
+
+    /// Load config like `load`, also returning what had to be recovered
+    pub fn load_recovering() -> Result<(Self, LoadIssues)> {
+        let config_path = Self::config_path()
+            .context("Could not determine config directory")?;
+
+        if config_path.exists() {
+            Self::load_from(&config_path)
+        } else {
+            // Create default config
+            let config = Config::default();
+            config.save()?;
+            Ok((config, LoadIssues::default()))
+        }
+    }
+
+    /// Load the config file again, failing if it can't be parsed
+    ///
+    /// For reloading a file edited by hand, which shouldn't be replaced over a typo.
+    pub fn reload() -> Result<Self> {
+        let config_path = Self::config_path()
+            .context("Could not determine config directory")?;
+        let content = fs::read_to_string(&config_path)
+            .with_context(|| format!("Failed to read config file: {:?}", config_path))?;
+        toml::from_str(&content).with_context(|| "Failed to parse config file")
+    }
+
+    /// Load an existing config file, backing it up and using the defaults if it's broken
+    ///
+    /// Invalid settings of a file that parses are replaced in the loaded
+    /// config only, so the file keeps them for the user to fix.
+    fn load_from(path: &Path) -> Result<(Self, LoadIssues)> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file: {:?}", path))?;
+        let error = match toml::from_str::<Config>(&content) {
+            Ok(mut config) => {
+                let replaced = config.repair();
+                return Ok((config, LoadIssues { backup: None, replaced }));
+            }
+            Err(e) => e,
+        };
+
+        log::warn!("Failed to parse config file, using the defaults: {}", error);
+        let backup = path.with_extension("toml.bak");
+        fs::rename(path, &backup)
+            .with_context(|| format!("Failed to back up the broken config file to {:?}", backup))?;
+        let config = Config::default();
+        write_atomically(path, &config.to_toml_string()?)?;
+        Ok((config, LoadIssues { backup: Some(backup), replaced: Vec::new() }))
+    }
+
+    /// Replace the config file with the defaults, moving it to `config.toml.bak`
+    ///
+    /// Returns where the old file went, if there was one.
+    pub fn reset() -> Result<Option<PathBuf>> {
+        let config_path = Self::config_path()
+            .context("Could not determine config path")?;
+        let backup = if config_path.exists() {
+            let backup = config_path.with_extension("toml.bak");
+            fs::rename(&config_path, &backup)
+                .with_context(|| format!("Failed to back up the config file to {:?}", backup))?;
+            Some(backup)
+        } else {
+            None
+        };
+        Config::default().save()?;
+        Ok(backup)
+    }
+
+    /// Save config to file
+    pub fn save(&self) -> Result<()> {
+        let config_dir = Self::config_dir()
+            .context("Could not determine config directory")?;
+        let config_path = Self::config_path()
+            .context("Could not determine config path")?;
+
+        // Create directory if it doesn't exist
+        fs::create_dir_all(&config_dir)
+            .with_context(|| format!("Failed to create config directory: {:?}", config_dir))?;
+
+        let content = self.to_toml_string()?;
+
+        write_atomically(&config_path, &content)
+            .with_context(|| format!("Failed to write config file: {:?}", config_path))?;
+
+        log::info!("Config saved to {:?}", config_path);
+        Ok(())
+    }
+
+    /// Serialize the config with its sections in a fixed order, each preceded by a comment
+    ///
+    /// Keeps the file readable, and saving after a menu change only touches
+    /// the values that changed.
+    pub fn to_toml_string(&self) -> Result<String> {
+        let content = toml::to_string_pretty(self).context("Failed to serialize config")?;
+        let mut document: toml_edit::DocumentMut =
+            content.parse().context("Failed to format config")?;
+
+        let mut names: Vec<String> = SECTIONS.iter().map(|(name, _)| name.to_string()).collect();
+        names.extend(
+            document
+                .iter()
+                .map(|(name, _)| name.to_string())
+                .filter(|name| !SECTIONS.iter().any(|(known, _)| known == name)),
+        );
+
+        let mut position = 0;
+        for name in names {
+            // A section is a table, or an array of tables like `[[actions]]`
+            let tables: Vec<&mut toml_edit::Table> = match document.get_mut(&name) {
+                Some(toml_edit::Item::Table(table)) => vec![table],
+                Some(toml_edit::Item::ArrayOfTables(array)) => array.iter_mut().collect(),
+                _ => continue,
+            };
+            for (index, table) in tables.into_iter().enumerate() {
+                if let Some((_, comment)) = SECTIONS.iter().find(|(known, _)| *known == name) {
+                    let prefix = match (index, position) {
+                        (0, 0) => format!("# {}\n", comment),
+                        (0, _) => format!("\n# {}\n", comment),
+                        _ => "\n".to_string(),
+                    };
+                    table.decor_mut().set_prefix(prefix);
+                }
+                // Sections holding only subtables would otherwise lose their header and comment
+                table.set_implicit(false);
+                position = set_table_positions(table, position);
+            }
+        }
+
+        Ok(document.to_string())
+    }
+
+    /// Pause before pasting into the app with this bundle identifier
+    pub fn paste_delay_ms(&self, bundle_id: Option<&str>) -> u64 {
+        bundle_id
+            .and_then(|id| self.shortcuts.apps.get(id))
+            .and_then(|app| app.paste_delay_ms)
+            .unwrap_or(self.timing.paste_delay_ms)
+    }
+
+    /// The hotkey triggering `action`, if it has one
+    pub fn hotkey_for(&self, action: HotkeyAction) -> Option<&HotkeyConfig> {
+        match action {
+            HotkeyAction::EditSelection => Some(&self.hotkey),
+            HotkeyAction::EditClipboard => self.clipboard_hotkey.as_ref(),
+            HotkeyAction::Named(index) => self.actions.get(index).map(|action| &action.hotkey),
+        }
+    }
+
+    /// The hotkey triggering `action`, to change it
+    pub fn hotkey_for_mut(&mut self, action: HotkeyAction) -> Option<&mut HotkeyConfig> {
+        match action {
+            HotkeyAction::EditSelection => Some(&mut self.hotkey),
+            HotkeyAction::EditClipboard => self.clipboard_hotkey.as_mut(),
+            HotkeyAction::Named(index) => self.actions.get_mut(index).map(|action| &mut action.hotkey),
+        }
+    }
+
+    /// The config a named action's session runs with
+    ///
+    /// The action's extension wins over the ones found from the app.
+    pub fn for_action(&self, action: &NamedAction) -> Config {
+        let mut config = self.clone();
+        if let Some(ref extension) = action.extension {
+            config.editor.extension = Some(extension.clone());
+            config.editor.extension_from_window_title = false;
+            config.editor.app_extensions.clear();
+        }
+        config
+    }
+
+    /// The enabled hotkeys to listen for, each with the action it triggers
+    pub fn hotkey_bindings(&self) -> Vec<(HotkeyAction, HotkeyConfig)> {
+        let mut bindings = vec![(HotkeyAction::EditSelection, self.hotkey.clone())];
+        if let Some(ref clipboard_hotkey) = self.clipboard_hotkey {
+            bindings.push((HotkeyAction::EditClipboard, clipboard_hotkey.clone()));
+        }
+        bindings.extend(
+            self.actions
+                .iter()
+                .enumerate()
+                .map(|(index, action)| (HotkeyAction::Named(index), action.hotkey.clone())),
+        );
+        bindings.retain(|(_, hotkey)| hotkey.enabled);
+        bindings
+    }
+
+    /// Replace the settings `validate` would reject by their defaults
+    ///
+    /// Returns the names of the replaced settings. Invalid per-app shortcuts
+    /// are dropped, so the global ones are used for that app, and so are
+    /// actions whose hotkey is invalid or already taken.
+    pub fn repair(&mut self) -> Vec<&'static str> {
+        let defaults = Config::default();
+        let mut replaced = Vec::new();
+
+        if Terminal::from_name(&self.terminal.name).is_none() {
+            log::warn!("Unknown terminal {:?}, using {}", self.terminal.name, defaults.terminal.name);
+            self.terminal.name = defaults.terminal.name.clone();
+            replaced.push("terminal.name");
+        }
+        if self.terminal.width == 0 || self.terminal.height == 0 {
+            log::warn!("Terminal width and height must be greater than 0, using the defaults");
+            self.terminal.width = defaults.terminal.width;
+            self.terminal.height = defaults.terminal.height;
+            replaced.push("terminal.width/height");
+        }
+
+        let hotkey_valid = |hotkey: &HotkeyConfig| {
+            hotkey_chords(hotkey).and_then(|_| variant_modifiers(hotkey)).map(|_| ())
+        };
+        if let Err(e) = hotkey_valid(&self.hotkey) {
+            log::warn!("Invalid hotkey ({:#}), using the default", e);
+            self.hotkey = defaults.hotkey.clone();
+            replaced.push("hotkey");
+        }
+        if let Some(ref clipboard_hotkey) = self.clipboard_hotkey {
+            let error = match hotkey_valid(clipboard_hotkey) {
+                Err(e) => Some(format!("{:#}", e)),
+                Ok(()) if same_hotkey(&self.hotkey, clipboard_hotkey) => {
+                    Some("same as the hotkey".to_string())
+                }
+                Ok(()) => None,
+            };
+            if let Some(error) = error {
+                log::warn!("Invalid clipboard hotkey ({}), turning it off", error);
+                self.clipboard_hotkey = None;
+                replaced.push("clipboard_hotkey");
+            }
+        }
+
+        let count = self.actions.len();
+        let mut kept: Vec<NamedAction> = Vec::new();
+        for action in std::mem::take(&mut self.actions) {
+            let taken = std::iter::once(&self.hotkey)
+                .chain(self.clipboard_hotkey.iter())
+                .chain(kept.iter().map(|other| &other.hotkey))
+                .any(|hotkey| same_hotkey(hotkey, &action.hotkey));
+            match hotkey_valid(&action.hotkey) {
+                Err(e) => log::warn!("Invalid hotkey for action {:?} ({:#}), skipping it", action.name, e),
+                Ok(()) if taken => log::warn!("Hotkey of action {:?} is already used, skipping it", action.name),
+                Ok(()) => kept.push(action),
+            }
+        }
+        if kept.len() < count {
+            replaced.push("actions");
+        }
+        self.actions = kept;
+
+        if let Err(e) = chord_key_code(&self.shortcuts.copy) {
+            log::warn!("Invalid copy shortcut ({:#}), using the default", e);
+            self.shortcuts.copy = defaults.shortcuts.copy.clone();
+            replaced.push("shortcuts.copy");
+        }
+        if let Err(e) = chord_key_code(&self.shortcuts.paste) {
+            log::warn!("Invalid paste shortcut ({:#}), using the default", e);
+            self.shortcuts.paste = defaults.shortcuts.paste.clone();
+            replaced.push("shortcuts.paste");
+        }
+        for (bundle_id, app) in self.shortcuts.apps.iter_mut() {
+            for combo in [&mut app.copy, &mut app.paste] {
+                if let Some(Err(e)) = combo.as_ref().map(chord_key_code) {
+                    log::warn!("Invalid shortcut for {} ({:#}), using the global one", bundle_id, e);
+                    *combo = None;
+                    if !replaced.contains(&"shortcuts.apps") {
+                        replaced.push("shortcuts.apps");
+                    }
+                }
+            }
+        }
+
+        let count = self.editor.env.len();
+        self.editor.env.retain(|key, _| {
+            let valid = is_env_name(key);
+            if !valid {
+                log::warn!("Invalid environment variable name {:?} in editor.env, skipping it", key);
+            }
+            valid
+        });
+        if self.editor.env.len() < count {
+            replaced.push("editor.env");
+        }
+
+        replaced
+    }
+
+    /// Check that the config only refers to known terminals, keys and apps
+    pub fn validate(&self) -> Result<()> {
+        if Terminal::from_name(&self.terminal.name).is_none() {
+            bail!("Unknown terminal: {}", self.terminal.name);
+        }
+        if self.terminal.width == 0 || self.terminal.height == 0 {
+            bail!("Terminal width and height must be greater than 0");
+        }
+
+        hotkey_chords(&self.hotkey).context("Invalid hotkey")?;
+        variant_modifiers(&self.hotkey).context("Invalid hotkey variant")?;
+        if let Some(ref clipboard_hotkey) = self.clipboard_hotkey {
+            hotkey_chords(clipboard_hotkey).context("Invalid clipboard hotkey")?;
+            variant_modifiers(clipboard_hotkey).context("Invalid clipboard hotkey variant")?;
+            if same_hotkey(&self.hotkey, clipboard_hotkey) {
+                bail!("The clipboard hotkey must differ from the hotkey");
+            }
+        }
+        for action in &self.actions {
+            hotkey_chords(&action.hotkey)
+                .and_then(|_| variant_modifiers(&action.hotkey))
+                .with_context(|| format!("Invalid hotkey for action {:?}", action.name))?;
+            let taken = std::iter::once(&self.hotkey)
+                .chain(self.clipboard_hotkey.iter())
+                .chain(self.actions.iter().map(|other| &other.hotkey))
+                .filter(|hotkey| same_hotkey(hotkey, &action.hotkey))
+                .count();
+            if taken > 1 {
+                bail!("The hotkey of action {:?} is used twice", action.name);
+            }
+        }
+
+        let app_shortcuts = self
+            .shortcuts
+            .apps
+            .values()
+            .flat_map(|app| app.copy.iter().chain(app.paste.iter()));
+        for combo in [&self.shortcuts.copy, &self.shortcuts.paste]
+            .into_iter()
+            .chain(app_shortcuts)
+        {
+            chord_key_code(combo).context("Invalid shortcut")?;
+        }
+        if let Some(key) = self.editor.env.keys().find(|key| !is_env_name(key)) {
+            bail!("Invalid environment variable name in editor.env: {:?}", key);
+        }
+        Ok(())
+    }
+
+    /// Load and validate a config from an arbitrary file
+    pub fn import(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file: {:?}", path))?;
+        let config: Config = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse config file: {:?}", path))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Write the config to an arbitrary file
+    pub fn export(&self, path: &Path) -> Result<()> {
+        let content = self.to_toml_string()?;
+        fs::write(path, content)
+            .with_context(|| format!("Failed to write config file: {:?}", path))?;
+        Ok(())
+    }
+}
+
+/// What loading the config file had to recover from
+#[derive(Debug, Default)]
+pub struct LoadIssues {
+    /// Where a file that couldn't be parsed was moved to
+    pub backup: Option<PathBuf>,
+    /// Settings that were invalid and replaced by their defaults
+    pub replaced: Vec<&'static str>,
+}
+
+/// Whether a string is a valid environment variable name (`[A-Za-z_][A-Za-z0-9_]*`),
+/// so it can go into a shell command without quoting
+fn is_env_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Replace a file's content in one step
+///
+/// Writes a uniquely named temp file next to it and renames it over the
+/// file, so a crash, a concurrent reader (e.g. a reload) or another save at
+/// the same time never leaves a truncated file.
+fn write_atomically(path: &Path, content: &str) -> Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let mut temp = tempfile::NamedTempFile::new_in(dir)
+        .with_context(|| format!("Failed to create a temp file in {:?}", dir))?;
+    temp.write_all(content.as_bytes())
+        .and_then(|()| temp.as_file().sync_all())
+        .with_context(|| format!("Failed to write {:?}", temp.path()))?;
+    temp.persist(path)
+        .with_context(|| format!("Failed to replace {:?}", path))?;
+    Ok(())
+}
+
+/// Number a table and its subtables in order, so they're written together
+fn set_table_positions(table: &mut toml_edit::Table, mut position: usize) -> usize {
+    table.set_position(position);
+    position += 1;
+    for (_, item) in table.iter_mut() {
+        if let Some(subtable) = item.as_table_mut() {
+            position = set_table_positions(subtable, position);
+        }
+    }
+    position
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn readers_see_either_the_old_or_the_new_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let old = "a".repeat(100_000);
+        let new = "b".repeat(200_000);
+        write_atomically(&path, &old).unwrap();
+
+        // Two writers at once, like the menu and the control socket saving
+        let writers: Vec<_> = [new.clone(), old.clone()]
+            .into_iter()
+            .map(|content| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..25 {
+                        write_atomically(&path, &content).unwrap();
+                    }
+                })
+            })
+            .collect();
+        while !writers.iter().all(|writer| writer.is_finished()) {
+            let content = fs::read_to_string(&path).unwrap();
+            assert!(content == old || content == new, "read {} bytes", content.len());
+        }
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn broken_config_files_are_backed_up_and_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        for broken in ["", "[hotkey]\nmodifiers = [\"cmd\", \"sh"] {
+            fs::write(&path, broken).unwrap();
+            let (config, issues) = Config::load_from(&path).unwrap();
+            assert_eq!(config.hotkey, Config::default().hotkey);
+            assert_eq!(issues.backup, Some(dir.path().join("config.toml.bak")));
+            assert_eq!(fs::read_to_string(dir.path().join("config.toml.bak")).unwrap(), broken);
+            assert!(Config::load_from(&path).unwrap().1.backup.is_none());
+        }
+    }
+
+    #[test]
+    fn invalid_settings_are_replaced_by_their_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let content = r#"
+            [hotkey]
+            modifiers = ["cmd", "shift"]
+            key = "nope"
+
+            [clipboard_hotkey]
+            modifiers = ["cmd", "shift"]
+            key = "v"
+
+            [terminal]
+            name = "hyper"
+            width = 120
+            height = 0
+
+            [shortcuts.apps."com.example.Remote"]
+            paste = { modifiers = ["ctrl"], key = "vv" }
+        "#;
+        fs::write(&path, content).unwrap();
+
+        let (config, issues) = Config::load_from(&path).unwrap();
+        assert!(issues.backup.is_none());
+        assert_eq!(
+            issues.replaced,
+            ["terminal.name", "terminal.width/height", "hotkey", "shortcuts.apps"]
+        );
+        config.validate().unwrap();
+        assert_eq!(config.hotkey, Config::default().hotkey);
+        assert_eq!(config.terminal.name, "ghostty");
+        assert_eq!((config.terminal.width, config.terminal.height), (100, 30));
+        // Valid settings next to the invalid ones are kept
+        assert_eq!(config.clipboard_hotkey.as_ref().map(|h| h.key.as_str()), Some("v"));
+        assert!(config.shortcuts.apps["com.example.Remote"].paste.is_none());
+        // The file keeps what the user wrote
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+
+        let mut config = Config {
+            clipboard_hotkey: Some(Config::default().hotkey),
+            ..Config::default()
+        };
+        assert_eq!(config.repair(), ["clipboard_hotkey"]);
+        assert!(config.clipboard_hotkey.is_none());
+        assert!(Config::default().repair().is_empty());
+
+        // Env keys go into shell commands unquoted
+        let mut config = Config::default();
+        config.editor.env.insert("RUST_LOG".into(), "debug".into());
+        config.editor.env.insert("X;touch /tmp/pwned;Y".into(), "1".into());
+        config.editor.env.insert("1ABC".into(), "1".into());
+        assert!(config.validate().is_err());
+        assert_eq!(config.repair(), ["editor.env"]);
+        assert_eq!(config.editor.env.keys().collect::<Vec<_>>(), ["RUST_LOG"]);
+        config.validate().unwrap();
+
+        // A misspelled modifier would otherwise leave a bare key bound
+        let mut config = Config {
+            hotkey: toml::from_str("modifiers = [\"cmdd\"]\nkey = \"e\"").unwrap(),
+            ..Config::default()
+        };
+        assert_eq!(config.repair(), ["hotkey"]);
+        assert_eq!(config.hotkey, Config::default().hotkey);
+    }
+
+    #[test]
+    fn imports_with_a_misspelled_modifier_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("import.toml");
+        let terminal = "[terminal]\nname = \"ghostty\"\nwidth = 100\nheight = 30\n";
+        fs::write(&path, format!("{}[hotkey]\nmodifiers = [\"cmd\", \"shfit\"]\nkey = \"e\"\n", terminal)).unwrap();
+
+        let error = format!("{:#}", Config::import(&path).unwrap_err());
+        assert_eq!(error, "Invalid hotkey: Unknown modifier \"shfit\"");
+
+        fs::write(&path, format!("{}[hotkey]\nmodifiers = [\"cmd\", \"shift\"]\nkey = \"e\"\n", terminal)).unwrap();
+        Config::import(&path).unwrap();
+    }
+
+    #[test]
+    fn sections_are_written_in_a_fixed_order() {
+        let content = Config::default().to_toml_string().unwrap();
+        let sections: Vec<&str> = content
+            .lines()
+            .filter(|line| line.starts_with('[') && !line.contains('.'))
+            .collect();
+        assert_eq!(
+            sections,
+            [
+                "[hotkey]",
+                "[terminal]",
+                "[editor]",
+                "[session]",
+                "[shortcuts]",
+                "[notifications]",
+                "[menu]",
+                "[timing]",
+                "[automation]",
+                "[debug]",
+            ]
+        );
+    }
+
+    #[test]
+    fn sections_are_preceded_by_their_comment() {
+        let content = Config::default().to_toml_string().unwrap();
+        assert!(content.starts_with("# Hotkey that opens the selection in the editor\n[hotkey]\n"));
+        assert!(content.contains("\n\n# Menu bar customization\n[menu]\n"));
+    }
+
+    #[test]
+    fn subtables_stay_with_their_section() {
+        let mut config = Config::default();
+        config.editor.env.insert("RUST_LOG".to_string(), "info".to_string());
+        let content = config.to_toml_string().unwrap();
+        let editor = content.find("[editor]").unwrap();
+        let env = content.find("[editor.env]").unwrap();
+        let session = content.find("[session]").unwrap();
+        assert!(editor < env && env < session, "{}", content);
+    }
+
+    #[test]
+    fn saving_again_doesnt_change_the_file() {
+        let mut config = Config::default();
+        config.shortcuts.apps.insert(
+            "com.example.app".to_string(),
+            AppShortcuts {
+                copy: None,
+                paste: Some(config.shortcuts.paste.clone()),
+                paste_delay_ms: Some(150),
+            },
+        );
+        let content = config.to_toml_string().unwrap();
+        let reloaded: Config = toml::from_str(&content).unwrap();
+        assert_eq!(reloaded.to_toml_string().unwrap(), content);
+    }
+
+    #[test]
+    fn paste_delay_can_be_set_per_app() {
+        let mut config = Config {
+            shortcuts: toml::from_str("[apps.\"com.tinyspeck.slackmacgap\"]\npaste_delay_ms = 150").unwrap(),
+            ..Default::default()
+        };
+        config.timing.paste_delay_ms = 20;
+        assert_eq!(config.paste_delay_ms(Some("com.tinyspeck.slackmacgap")), 150);
+        assert_eq!(config.paste_delay_ms(Some("com.apple.TextEdit")), 20);
+        assert_eq!(config.paste_delay_ms(None), 20);
+    }
+
+    #[test]
+    fn ax_capture_only_applies_to_listed_apps() {
+        let mut session = SessionConfig::default();
+        assert!(!session.uses_ax(Some("com.apple.TextEdit")));
+
+        session.capture_mode = CaptureMode::Ax;
+        assert!(session.uses_ax(Some("com.apple.TextEdit")));
+        assert!(session.uses_ax(Some("com.apple.Notes")));
+        assert!(!session.uses_ax(Some("com.google.Chrome")));
+        assert!(!session.uses_ax(None));
+
+        let session: SessionConfig = toml::from_str(r#"capture_mode = "accessibility""#).unwrap();
+        assert_eq!(session.capture_mode, CaptureMode::Ax);
+    }
+
+    #[test]
+    fn ignored_apps_match_ignoring_case() {
+        let session = SessionConfig {
+            ignored_apps: vec!["com.1password.1Password".to_string()],
+            ..SessionConfig::default()
+        };
+        assert!(session.is_app_ignored("com.1password.1password"));
+        assert!(session.is_app_ignored("COM.1PASSWORD.1PASSWORD"));
+        assert!(!session.is_app_ignored("com.1password"));
+        assert!(!SessionConfig::default().is_app_ignored("com.apple.TextEdit"));
+    }
+
+    #[test]
+    fn keeping_the_result_wins_over_restoring_the_clipboard() {
+        let mut session = SessionConfig::default();
+        assert!(!session.restores_clipboard_after_paste());
+
+        session.restore_clipboard_after_paste = true;
+        assert!(session.restores_clipboard_after_paste());
+
+        session.keep_result_on_clipboard = true;
+        assert!(!session.restores_clipboard_after_paste());
+
+        session.keep_result_on_clipboard = false;
+        session.output_mode = OutputMode::ClipboardOnly;
+        assert!(!session.restores_clipboard_after_paste());
+    }
+
+    #[test]
+    fn named_actions_get_their_own_bindings() {
+        let content = r#"
+            [hotkey]
+            modifiers = ["cmd", "shift"]
+            key = "semicolon"
+
+            [[actions]]
+            name = "markdown"
+            extension = "md"
+            hotkey = { modifiers = ["cmd", "shift"], key = "quote" }
+
+            [[actions]]
+            name = "clipboard"
+            source = "clipboard"
+            hotkey = { modifiers = ["cmd", "alt"], key = "v" }
+
+            [terminal]
+            name = "kitty"
+            width = 100
+            height = 30
+        "#;
+        let mut config: Config = toml::from_str(content).unwrap();
+        config.validate().unwrap();
+        assert_eq!(config.actions[0].source, ActionSource::Selection);
+        assert_eq!(config.actions[1].source, ActionSource::Clipboard);
+
+        let actions: Vec<_> = config.hotkey_bindings().into_iter().map(|(action, _)| action).collect();
+        assert_eq!(
+            actions,
+            [HotkeyAction::EditSelection, HotkeyAction::Named(0), HotkeyAction::Named(1)]
+        );
+        assert_eq!(config.hotkey_for(HotkeyAction::Named(1)).unwrap().key, "v");
+        assert!(config.hotkey_for(HotkeyAction::Named(2)).is_none());
+
+        config.editor.extension_from_window_title = true;
+        let markdown = config.for_action(&config.actions[0]);
+        assert_eq!(markdown.editor.extension.as_deref(), Some("md"));
+        assert!(!markdown.editor.extension_from_window_title);
+        assert!(config.for_action(&config.actions[1]).editor.extension_from_window_title);
+
+        // Saved as an array of tables, after the hotkeys
+        let saved = config.to_toml_string().unwrap();
+        assert!(saved.find("[[actions]]").unwrap() < saved.find("[terminal]").unwrap());
+        assert_eq!(saved.matches("# More hotkeys").count(), 1);
+        let reloaded: Config = toml::from_str(&saved).unwrap();
+        assert_eq!(reloaded.actions.len(), 2);
+        assert_eq!(reloaded.actions[0].hotkey, config.actions[0].hotkey);
+
+        // A hotkey taken twice is rejected, and dropped by repair
+        config.actions[1].hotkey = config.hotkey.clone();
+        assert!(config.validate().is_err());
+        assert_eq!(config.repair(), ["actions"]);
+        assert_eq!(config.actions.len(), 1);
+        config.validate().unwrap();
+    }
+
+    #[test]
+    fn disabled_hotkeys_are_kept_but_not_bound() {
+        let mut config = Config {
+            clipboard_hotkey: Some(toml::from_str("modifiers = [\"cmd\", \"alt\"]\nkey = \"v\"").unwrap()),
+            ..Config::default()
+        };
+        assert_eq!(config.hotkey_bindings().len(), 2);
+        assert!(!config.to_toml_string().unwrap().contains("enabled"));
+
+        config.hotkey.enabled = false;
+        let bindings = config.hotkey_bindings();
+        assert_eq!(bindings.len(), 1);
+        assert_eq!(bindings[0].0, HotkeyAction::EditClipboard);
+
+        let saved: Config = toml::from_str(&config.to_toml_string().unwrap()).unwrap();
+        assert!(!saved.hotkey.enabled);
+        assert!(saved.clipboard_hotkey.unwrap().enabled);
+    }
+}
@@ -0,0 +1,121 @@
+//! Line protocol of the control socket
+//!
+//! Each request is one line of JSON with a `command`, answered by one line
+//! of JSON with `"ok": true` and the command's result, or `"ok": false` and
+//! an `error`:
+//!
+//! ```text
+//! {"command": "edit"}                          edit the selection, like the hotkey
+//! {"command": "edit_text", "text": "..."}      edit text, returns "text" and "changed"
+//! {"command": "get_hotkey"}                    returns "hotkey" and "display"
+//! {"command": "set_hotkey", "hotkey": {...}}   same fields as [hotkey] in the config
+//! {"command": "reload_config"}                 re-read the config file
+//! ```
+
+use crate::config::HotkeyConfig;
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::{Map, Value};
+
+/// A request received on the control socket
+#[derive(Debug, Clone)]
+pub enum ControlCommand {
+    /// Edit the selection of the frontmost app, as if the hotkey was pressed
+    Edit,
+    /// Edit the given text and return the result
+    EditText(String),
+    /// Return the current hotkey
+    GetHotkey,
+    /// Replace the hotkey
+    SetHotkey(HotkeyConfig),
+    /// Load the config file again
+    ReloadConfig,
+}
+
+impl ControlCommand {
+    /// Parse a request line
+    pub fn parse(line: &str) -> Result<Self> {
+        let request: Value = serde_json::from_str(line).context("Invalid JSON")?;
+        let command = request
+            .get("command")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("Missing \"command\""))?;
+
+        Ok(match command {
+            "edit" => ControlCommand::Edit,
+            "edit_text" => ControlCommand::EditText(
+                request
+                    .get("text")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| anyhow!("Missing \"text\""))?
+                    .to_string(),
+            ),
+            "get_hotkey" => ControlCommand::GetHotkey,
+            "set_hotkey" => {
+                let hotkey = request
+                    .get("hotkey")
+                    .cloned()
+                    .ok_or_else(|| anyhow!("Missing \"hotkey\""))?;
+                ControlCommand::SetHotkey(
+                    serde_json::from_value(hotkey)
+                        .map_err(|e| anyhow!("Invalid \"hotkey\": {}", e))?,
+                )
+            }
+            "reload_config" => ControlCommand::ReloadConfig,
+            other => bail!("Unknown command \"{}\"", other),
+        })
+    }
+}
+
+/// Response line for a successful command, with its result fields
+pub fn ok_response(fields: Vec<(&str, Value)>) -> String {
+    let fields: Map<String, Value> = std::iter::once(("ok".to_string(), Value::Bool(true)))
+        .chain(fields.into_iter().map(|(name, value)| (name.to_string(), value)))
+        .collect();
+    Value::Object(fields).to_string()
+}
+
+/// Response line for a failed command
+pub fn error_response(error: &anyhow::Error) -> String {
+    serde_json::json!({"ok": false, "error": format!("{:#}", error)}).to_string()
+}
+
+/// A hotkey as JSON, with the same fields as in the config
+pub fn hotkey_to_json(hotkey: &HotkeyConfig) -> Result<Value> {
+    Ok(serde_json::to_value(hotkey)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_are_parsed() {
+        assert!(matches!(ControlCommand::parse(r#"{"command":"edit"}"#), Ok(ControlCommand::Edit)));
+        assert!(matches!(
+            ControlCommand::parse(r#"{"command":"edit_text","text":"hi"}"#),
+            Ok(ControlCommand::EditText(text)) if text == "hi"
+        ));
+        assert!(ControlCommand::parse(r#"{"command":"edit_text"}"#).is_err());
+        assert!(ControlCommand::parse(r#"{"command":"format_disk"}"#).is_err());
+        assert!(ControlCommand::parse("edit").is_err());
+        // Deep nesting is refused instead of overflowing the stack
+        assert!(ControlCommand::parse(&"[".repeat(200_000)).is_err());
+    }
+
+    #[test]
+    fn hotkeys_round_trip_through_json() {
+        let line = r#"{"command":"set_hotkey","hotkey":{"modifiers":["cmd","alt"],"key":"e","long_press_ms":400}}"#;
+        let Ok(ControlCommand::SetHotkey(hotkey)) = ControlCommand::parse(line) else {
+            panic!("not parsed: {}", line);
+        };
+        assert_eq!(hotkey.modifiers, ["cmd", "alt"]);
+        assert_eq!(hotkey.long_press_ms, Some(400));
+
+        let json = hotkey_to_json(&hotkey).unwrap();
+        assert_eq!(json.get("key").and_then(Value::as_str), Some("e"));
+        assert_eq!(
+            ok_response(vec![("display", Value::String("⌥⌘E".to_string()))]),
+            r#"{"ok":true,"display":"⌥⌘E"}"#
+        );
+    }
+}
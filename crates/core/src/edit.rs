@@ -0,0 +1,222 @@
+//! Decisions about the edited text
+//!
+//! Whether an edit changed anything that matters, and how its surrounding
+//! whitespace and trailing newlines end up, independent of how the text was
+//! captured or delivered.
+
+use crate::config::{SessionConfig, TrailingNewline, TrimSelection};
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// Read an edited file as UTF-8
+///
+/// Invalid UTF-8 (e.g. after an editor crash) is replaced with U+FFFD rather
+/// than failing, so the rest of the edit isn't lost.
+pub fn read_edited_file(path: &Path) -> Result<String> {
+    let bytes = fs::read(path)
+        .with_context(|| format!("Failed to read edited file: {:?}", path))?;
+
+    match String::from_utf8(bytes) {
+        Ok(text) => Ok(text),
+        Err(e) => {
+            log::warn!("Edited file {:?} isn't valid UTF-8, replacing invalid bytes", path);
+            Ok(String::from_utf8_lossy(e.as_bytes()).into_owned())
+        }
+    }
+}
+
+/// Adjust the trailing newlines of the edited text according to `mode`
+///
+/// `original` is the text that was opened in the editor.
+pub fn apply_trailing_newline(edited: &str, original: &str, mode: TrailingNewline) -> String {
+    let trimmed = edited.trim_end_matches('\n');
+    let count = match mode {
+        TrailingNewline::StripAll => 0,
+        TrailingNewline::KeepOne => (edited.len() > trimmed.len()) as usize,
+        TrailingNewline::PreserveOriginal => {
+            original.len() - original.trim_end_matches('\n').len()
+        }
+    };
+    format!("{}{}", trimmed, "\n".repeat(count))
+}
+
+/// Whitespace trimmed from the edges of a selection, put back after editing
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrimmedEdges {
+    pub leading: String,
+    pub trailing: String,
+}
+
+impl TrimmedEdges {
+    /// Surround the edited text with the trimmed whitespace again, so the
+    /// text around the selection isn't disturbed
+    pub fn restore(&self, edited: &str) -> String {
+        format!("{}{}{}", self.leading, edited, self.trailing)
+    }
+}
+
+/// Trim the selection according to `mode`, returning the text to edit and
+/// what was trimmed from its edges
+///
+/// With `Lines`, only the last line's trailing whitespace is at an edge; what
+/// is trimmed from the other lines isn't put back.
+pub fn trim_selection(text: &str, mode: TrimSelection) -> (String, TrimmedEdges) {
+    match mode {
+        TrimSelection::None => (text.to_string(), TrimmedEdges::default()),
+        TrimSelection::Edges => {
+            let start = text.len() - text.trim_start().len();
+            let end = start + text[start..].trim_end().len();
+            let edges = TrimmedEdges {
+                leading: text[..start].to_string(),
+                trailing: text[end..].to_string(),
+            };
+            (text[start..end].to_string(), edges)
+        }
+        TrimSelection::Lines => {
+            let mut trailing = "";
+            let trimmed = text
+                .split('\n')
+                .map(|line| {
+                    let (kept, removed) = trim_line_end(line);
+                    trailing = removed;
+                    kept
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            let edges = TrimmedEdges {
+                leading: String::new(),
+                trailing: trailing.to_string(),
+            };
+            (trimmed, edges)
+        }
+    }
+}
+
+/// Split the trailing spaces and tabs off a line, keeping a CRLF's `\r`
+fn trim_line_end(line: &str) -> (String, &str) {
+    let (body, cr) = match line.strip_suffix('\r') {
+        Some(body) => (body, "\r"),
+        None => (line, ""),
+    };
+    let kept = body.trim_end_matches([' ', '\t']);
+    (format!("{}{}", kept, cr), &body[kept.len()..])
+}
+
+/// Check if the edited text differs from the original in a way that matters
+///
+/// Differences the session config marks as trivial (trailing whitespace,
+/// final newline) are ignored, so an editor normalizing them on save doesn't
+/// cause an unintended paste-back.
+pub fn content_changed(original: &str, edited: &str, session: &SessionConfig) -> bool {
+    let normalize = |text: &str| -> String {
+        let mut text = if session.ignore_trailing_whitespace {
+            text.lines().map(str::trim_end).collect::<Vec<_>>().join("\n")
+        } else {
+            text.to_string()
+        };
+        if session.ignore_final_newline_only {
+            text.truncate(text.trim_end_matches(['\r', '\n']).len());
+        }
+        text
+    };
+
+    original != edited && normalize(original) != normalize(edited)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn whitespace_only_changes_count_by_default() {
+        let session = SessionConfig::default();
+        assert!(content_changed("a b", "a b  ", &session));
+        assert!(content_changed("line\n", "line", &session));
+        assert!(!content_changed("same", "same", &session));
+    }
+
+    #[test]
+    fn trailing_whitespace_can_be_ignored() {
+        let session = SessionConfig {
+            ignore_trailing_whitespace: true,
+            ..Default::default()
+        };
+        assert!(!content_changed("one  \ntwo\t", "one\ntwo", &session));
+        assert!(content_changed("one\ntwo", "one\n two", &session));
+        assert!(content_changed("one", "one two", &session));
+    }
+
+    #[test]
+    fn final_newline_can_be_ignored() {
+        let session = SessionConfig {
+            ignore_final_newline_only: true,
+            ..Default::default()
+        };
+        assert!(!content_changed("text\n\n", "text", &session));
+        assert!(!content_changed("text\r\n", "text", &session));
+        assert!(content_changed("text \n", "text", &session));
+    }
+
+    #[test]
+    fn trailing_newlines_are_stripped_by_default() {
+        let mode = TrailingNewline::StripAll;
+        assert_eq!(apply_trailing_newline("text", "text", mode), "text");
+        assert_eq!(apply_trailing_newline("text\n", "text", mode), "text");
+        assert_eq!(apply_trailing_newline("text\n\n\n", "text\n", mode), "text");
+    }
+
+    #[test]
+    fn one_trailing_newline_can_be_kept() {
+        let mode = TrailingNewline::KeepOne;
+        assert_eq!(apply_trailing_newline("text", "text\n", mode), "text");
+        assert_eq!(apply_trailing_newline("text\n", "text", mode), "text\n");
+        assert_eq!(apply_trailing_newline("text\n\n\n", "text", mode), "text\n");
+    }
+
+    #[test]
+    fn original_trailing_newlines_can_be_preserved() {
+        let mode = TrailingNewline::PreserveOriginal;
+        assert_eq!(apply_trailing_newline("text\n", "text", mode), "text");
+        assert_eq!(apply_trailing_newline("text", "text\n", mode), "text\n");
+        assert_eq!(apply_trailing_newline("text\n", "a\n\n\n", mode), "text\n\n\n");
+    }
+
+    #[test]
+    fn selections_are_not_trimmed_by_default() {
+        let (text, edges) = trim_selection("  text \n", TrimSelection::None);
+        assert_eq!(text, "  text \n");
+        assert_eq!(edges.restore("edited"), "edited");
+    }
+
+    #[test]
+    fn selection_edges_can_be_trimmed() {
+        let (text, edges) = trim_selection(" \n one \n two\t\n", TrimSelection::Edges);
+        assert_eq!(text, "one \n two");
+        assert_eq!(edges.restore("ONE\nTWO"), " \n ONE\nTWO\t\n");
+
+        let (text, edges) = trim_selection("   ", TrimSelection::Edges);
+        assert_eq!(text, "");
+        assert_eq!(edges.restore("x"), "   x");
+    }
+
+    #[test]
+    fn selection_lines_can_be_trimmed() {
+        let (text, edges) = trim_selection("  one  \r\ntwo\t\n three ", TrimSelection::Lines);
+        assert_eq!(text, "  one\r\ntwo\n three");
+        assert_eq!(edges.restore("edited"), "edited ");
+
+        let (text, edges) = trim_selection("line \n", TrimSelection::Lines);
+        assert_eq!(text, "line\n");
+        assert_eq!(edges.restore("LINE\n"), "LINE\n");
+    }
+
+    #[test]
+    fn invalid_utf8_is_read_lossily() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(b"caf\xC3\xA9 \xFF ok").unwrap();
+        assert_eq!(read_edited_file(file.path()).unwrap(), "café \u{FFFD} ok");
+    }
+}
@@ -0,0 +1,80 @@
+//! Helix configuration generated for a single edit session
+
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use toml_edit::{value, DocumentMut};
+
+/// Columns Helix's default gutters (diagnostics, line numbers, diff) take up
+pub const GUTTER_WIDTH: u32 = 7;
+
+/// Narrowest text width that is still worth wrapping at
+const MIN_TEXT_WIDTH: u32 = 20;
+
+/// Path of the Helix `config.toml` the editor would read
+///
+/// `helix_config_dir` is the `XDG_CONFIG_HOME` override from the config;
+/// without it, `$XDG_CONFIG_HOME` and then `~/.config` are used like Helix does.
+pub fn user_config_path(helix_config_dir: Option<&Path>) -> Option<PathBuf> {
+    let base = match helix_config_dir {
+        Some(dir) => dir.to_path_buf(),
+        None => match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        },
+    };
+    Some(base.join("helix").join("config.toml"))
+}
+
+/// Text width for a window `window_width` columns wide
+pub fn text_width(window_width: u32) -> u32 {
+    window_width.saturating_sub(GUTTER_WIDTH).max(MIN_TEXT_WIDTH)
+}
+
+/// The user's Helix config with soft wrap at the window's text width
+///
+/// Everything else in `user_config` is kept, since `hx -c` replaces the
+/// user's `config.toml` instead of merging with it.
+pub fn with_soft_wrap(user_config: &str, window_width: u32) -> Result<String> {
+    let mut doc: DocumentMut = user_config
+        .parse()
+        .context("Failed to parse the Helix config")?;
+
+    let editor = doc.get("editor");
+    let soft_wrap = editor.and_then(|editor| editor.get("soft-wrap"));
+    if [editor, soft_wrap].into_iter().flatten().any(|item| !item.is_table_like()) {
+        bail!("The Helix config's [editor] or [editor.soft-wrap] is not a table");
+    }
+
+    let width = text_width(window_width);
+    doc["editor"]["text-width"] = value(i64::from(width));
+    doc["editor"]["soft-wrap"]["enable"] = value(true);
+    doc["editor"]["soft-wrap"]["wrap-at-text-width"] = value(true);
+
+    Ok(doc.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn soft_wrap_is_added_to_the_user_config() {
+        let user = "theme = \"onedark\"\n\n[editor]\nline-number = \"relative\"\n\n[keys.normal]\nC-s = \":w\"\n";
+        let generated = with_soft_wrap(user, 100).unwrap();
+        let doc: toml::Value = toml::from_str(&generated).unwrap();
+
+        assert_eq!(doc["theme"].as_str(), Some("onedark"));
+        assert_eq!(doc["keys"]["normal"]["C-s"].as_str(), Some(":w"));
+        assert_eq!(doc["editor"]["line-number"].as_str(), Some("relative"));
+        assert_eq!(doc["editor"]["text-width"].as_integer(), Some(93));
+        assert_eq!(doc["editor"]["soft-wrap"]["enable"].as_bool(), Some(true));
+        assert_eq!(doc["editor"]["soft-wrap"]["wrap-at-text-width"].as_bool(), Some(true));
+    }
+
+    #[test]
+    fn text_width_stays_usable_in_narrow_windows() {
+        assert_eq!(text_width(80), 73);
+        assert_eq!(text_width(10), MIN_TEXT_WIDTH);
+        assert_eq!(text_width(0), MIN_TEXT_WIDTH);
+    }
+}
@@ -0,0 +1,1024 @@
+//! Key names, key codes and modifier flags
+//!
+//! Maps the names used in the config to macOS virtual key codes and
+//! CGEventFlags bits and back, and formats hotkeys for display. Only plain
+//! numbers are involved, so this builds and tests on any platform.
+
+use crate::config::{HotkeyConfig, ModifierMatch, ModifierVariant};
+use anyhow::{bail, Context, Result};
+
+// macOS virtual key codes for common keys
+pub fn key_code_from_string(key: &str) -> Option<u16> {
+    match key.to_lowercase().as_str() {
+        "a" => Some(0x00),
+        "s" => Some(0x01),
+        "d" => Some(0x02),
+        "f" => Some(0x03),
+        "h" => Some(0x04),
+        "g" => Some(0x05),
+        "z" => Some(0x06),
+        "x" => Some(0x07),
+        "c" => Some(0x08),
+        "v" => Some(0x09),
+        "b" => Some(0x0B),
+        "q" => Some(0x0C),
+        "w" => Some(0x0D),
+        "e" => Some(0x0E),
+        "r" => Some(0x0F),
+        "y" => Some(0x10),
+        "t" => Some(0x11),
+        "1" => Some(0x12),
+        "2" => Some(0x13),
+        "3" => Some(0x14),
+        "4" => Some(0x15),
+        "6" => Some(0x16),
+        "5" => Some(0x17),
+        "=" => Some(0x18),
+        "9" => Some(0x19),
+        "7" => Some(0x1A),
+        "-" => Some(0x1B),
+        "8" => Some(0x1C),
+        "0" => Some(0x1D),
+        "]" => Some(0x1E),
+        "o" => Some(0x1F),
+        "u" => Some(0x20),
+        "[" => Some(0x21),
+        "i" => Some(0x22),
+        "p" => Some(0x23),
+        "l" => Some(0x25),
+        "j" => Some(0x26),
+        "'" | "quote" => Some(0x27),
+        "k" => Some(0x28),
+        ";" | "semicolon" => Some(0x29),
+        "\\" | "backslash" => Some(0x2A),
+        "," | "comma" => Some(0x2B),
+        "/" | "slash" => Some(0x2C),
+        "n" => Some(0x2D),
+        "m" => Some(0x2E),
+        "." | "period" => Some(0x2F),
+        "`" | "grave" | "backtick" => Some(0x32),
+        "space" => Some(0x31),
+        "return" | "enter" => Some(0x24),
+        "tab" => Some(0x30),
+        "delete" | "backspace" => Some(0x33),
+        "escape" | "esc" => Some(0x35),
+        "f1" => Some(0x7A),
+        "f2" => Some(0x78),
+        "f3" => Some(0x63),
+        "f4" => Some(0x76),
+        "f5" => Some(0x60),
+        "f6" => Some(0x61),
+        "f7" => Some(0x62),
+        "f8" => Some(0x64),
+        "f9" => Some(0x65),
+        "f10" => Some(0x6D),
+        "f11" => Some(0x67),
+        "f12" => Some(0x6F),
+        "f13" => Some(0x69),
+        "f14" => Some(0x6B),
+        "f15" => Some(0x71),
+        "f16" => Some(0x6A),
+        "f17" => Some(0x40),
+        "f18" => Some(0x4F),
+        "f19" => Some(0x50),
+        "f20" => Some(0x5A),
+        "left" | "left_arrow" => Some(0x7B),
+        "right" | "right_arrow" => Some(0x7C),
+        "down" | "down_arrow" => Some(0x7D),
+        "up" | "up_arrow" => Some(0x7E),
+        "home" => Some(0x73),
+        "end" => Some(0x77),
+        "page_up" | "pageup" => Some(0x74),
+        "page_down" | "pagedown" => Some(0x79),
+        "forward_delete" | "forwarddelete" => Some(0x75),
+        _ => None,
+    }
+}
+
+// Raw modifier flag values (from CGEvent.h)
+const FLAG_COMMAND: u64 = 0x00100000;
+const FLAG_SHIFT: u64 = 0x00020000;
+const FLAG_ALTERNATE: u64 = 0x00080000;
+const FLAG_CONTROL: u64 = 0x00040000;
+const FLAG_ALPHA_SHIFT: u64 = 0x00010000;
+
+/// Mask for relevant modifier flags
+const MODIFIER_MASK: u64 =
+    FLAG_COMMAND | FLAG_SHIFT | FLAG_ALTERNATE | FLAG_CONTROL | FLAG_ALPHA_SHIFT;
+
+/// Check whether an event's modifier flags match the target modifiers.
+///
+/// Caps Lock is a toggle: its flag reflects the lock state rather than whether
+/// the key is held. It is therefore only compared when the hotkey requires it,
+/// so an engaged Caps Lock doesn't break hotkeys that don't use it. Other
+/// flag bits (from a raw `modifier_flags`) must be set when the hotkey has them.
+pub fn modifiers_match(event_flags: u64, target_modifiers: u64) -> bool {
+    let mask = if target_modifiers & FLAG_ALPHA_SHIFT != 0 {
+        MODIFIER_MASK
+    } else {
+        MODIFIER_MASK & !FLAG_ALPHA_SHIFT
+    };
+    let raw = target_modifiers & !MODIFIER_MASK;
+    event_flags & mask == target_modifiers & mask && event_flags & raw == raw
+}
+
+impl ModifierMatch {
+    /// Check whether an event's modifier flags match the target modifiers
+    ///
+    /// `AtLeast` lets any other modifier be held too, e.g. for keyboards or
+    /// tools that add stray modifier bits.
+    pub fn matches(self, event_flags: u64, target_modifiers: u64) -> bool {
+        match self {
+            ModifierMatch::Exact => modifiers_match(event_flags, target_modifiers),
+            ModifierMatch::AtLeast => event_flags & target_modifiers == target_modifiers,
+        }
+    }
+}
+
+/// Flag bits for a canonical modifier name, alias, or symbol
+fn modifier_name_flags(name: &str) -> Option<u64> {
+    match name {
+        "cmd" | "command" | "meta" | "super" | "⌘" => Some(FLAG_COMMAND),
+        "shift" | "⇧" => Some(FLAG_SHIFT),
+        "alt" | "option" | "opt" | "⌥" => Some(FLAG_ALTERNATE),
+        "ctrl" | "control" | "⌃" => Some(FLAG_CONTROL),
+        "caps" | "capslock" | "caps_lock" | "⇪" => Some(FLAG_ALPHA_SHIFT),
+        "hyper" => Some(FLAG_COMMAND | FLAG_SHIFT | FLAG_ALTERNATE | FLAG_CONTROL),
+        "meh" => Some(FLAG_SHIFT | FLAG_ALTERNATE | FLAG_CONTROL),
+        _ => None,
+    }
+}
+
+/// Parse a single modifier token into flag bits.
+///
+/// Accepts the canonical names plus common synonyms from other tools
+/// (`meta`, `super`, `opt`, `hyper`, `meh`), left/right-specific spellings
+/// (`lcmd`, `right_shift`, ...) which match either side, and pasted symbols
+/// such as `⌘` or `⌘⇧`.
+pub fn modifier_flags(token: &str) -> Option<u64> {
+    let name = token.trim().to_lowercase();
+    if let Some(flags) = modifier_name_flags(&name) {
+        return Some(flags);
+    }
+
+    // Side-specific names: macOS reports both sides with the same flag
+    let sided = ["left", "right", "l", "r"].iter().find_map(|side| {
+        let rest = name.strip_prefix(side)?;
+        modifier_name_flags(rest.trim_start_matches(['_', '-', ' ']))
+    });
+    if sided.is_some() {
+        return sided;
+    }
+
+    // A run of symbols pasted as one token, e.g. "⌘⇧"
+    if name.is_empty() {
+        return None;
+    }
+    name.chars().try_fold(0, |flags, c| {
+        modifier_name_flags(c.encode_utf8(&mut [0; 4])).map(|f| flags | f)
+    })
+}
+
+/// Convert modifier strings to raw flag bits
+pub fn modifiers_from_config(modifiers: &[String]) -> u64 {
+    let mut flags: u64 = 0;
+
+    for modifier in modifiers {
+        match modifier_flags(modifier) {
+            Some(f) => flags |= f,
+            None => log::warn!("Unknown modifier: {}", modifier),
+        }
+    }
+
+    flags
+}
+
+/// Resolve a hotkey into the (key code, modifier flags) chords to match in order.
+///
+/// A plain hotkey has a single chord; a leader sequence has the leader chord
+/// followed by each chord in `sequence`.
+pub fn hotkey_chords(config: &HotkeyConfig) -> Result<Vec<(u16, u64)>> {
+    std::iter::once(config)
+        .chain(config.sequence.iter())
+        .map(|chord| Ok((chord_key_code(chord)?, chord_modifiers(chord)?)))
+        .collect()
+}
+
+/// Modifier flags of a single chord like [`chord_flags`], failing on an
+/// unknown modifier name instead of leaving it out
+pub fn chord_modifiers(chord: &HotkeyConfig) -> Result<u64> {
+    let named = chord.modifiers.iter().try_fold(0, |flags, name| {
+        modifier_flags(name)
+            .map(|f| flags | f)
+            .with_context(|| format!("Unknown modifier \"{}\"", name))
+    })?;
+    Ok(named | chord.modifier_flags.unwrap_or(0))
+}
+
+/// Key code of a single chord, from its raw `key_code` or its key name
+///
+/// Exactly one of the two must be set.
+pub fn chord_key_code(chord: &HotkeyConfig) -> Result<u16> {
+    match (chord.key.is_empty(), chord.key_code) {
+        (true, Some(key_code)) => Ok(key_code),
+        (false, None) => key_code_from_string(&chord.key)
+            .with_context(|| format!("Unknown key: {}", chord.key)),
+        (false, Some(_)) => bail!("Set either key or key_code, not both"),
+        (true, None) => bail!("No key set (use key or key_code)"),
+    }
+}
+
+/// Modifier flags of a single chord, named modifiers and raw flags combined
+pub fn chord_flags(chord: &HotkeyConfig) -> u64 {
+    modifiers_from_config(&chord.modifiers) | chord.modifier_flags.unwrap_or(0)
+}
+
+/// AppleScript command pressing a chord, for `tell application "System Events"`
+pub fn applescript_key_code(chord: &HotkeyConfig) -> Result<String> {
+    let key_code = chord_key_code(chord)?;
+    let flags = chord_flags(chord);
+    let modifiers: Vec<&str> = [
+        (FLAG_COMMAND, "command down"),
+        (FLAG_SHIFT, "shift down"),
+        (FLAG_ALTERNATE, "option down"),
+        (FLAG_CONTROL, "control down"),
+    ]
+    .into_iter()
+    .filter(|&(flag, _)| flags & flag != 0)
+    .map(|(_, name)| name)
+    .collect();
+
+    Ok(if modifiers.is_empty() {
+        format!("key code {}", key_code)
+    } else {
+        format!("key code {} using {{{}}}", key_code, modifiers.join(", "))
+    })
+}
+
+/// What a hotkey does when it's pressed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HotkeyAction {
+    /// Copy the selection, edit it and paste the result back
+    EditSelection,
+    /// Edit the clipboard's content and put the result back on the clipboard
+    EditClipboard,
+    /// Run the entry of `actions` at this index
+    Named(usize),
+}
+
+// ============================================================================
+// Display formatting functions
+// ============================================================================
+
+/// Convert a key code back to a display string
+pub fn key_code_to_display(key_code: u16) -> Option<String> {
+    match key_code {
+        0x00 => Some("A".to_string()),
+        0x01 => Some("S".to_string()),
+        0x02 => Some("D".to_string()),
+        0x03 => Some("F".to_string()),
+        0x04 => Some("H".to_string()),
+        0x05 => Some("G".to_string()),
+        0x06 => Some("Z".to_string()),
+        0x07 => Some("X".to_string()),
+        0x08 => Some("C".to_string()),
+        0x09 => Some("V".to_string()),
+        0x0B => Some("B".to_string()),
+        0x0C => Some("Q".to_string()),
+        0x0D => Some("W".to_string()),
+        0x0E => Some("E".to_string()),
+        0x0F => Some("R".to_string()),
+        0x10 => Some("Y".to_string()),
+        0x11 => Some("T".to_string()),
+        0x12 => Some("1".to_string()),
+        0x13 => Some("2".to_string()),
+        0x14 => Some("3".to_string()),
+        0x15 => Some("4".to_string()),
+        0x16 => Some("6".to_string()),
+        0x17 => Some("5".to_string()),
+        0x18 => Some("=".to_string()),
+        0x19 => Some("9".to_string()),
+        0x1A => Some("7".to_string()),
+        0x1B => Some("-".to_string()),
+        0x1C => Some("8".to_string()),
+        0x1D => Some("0".to_string()),
+        0x1E => Some("]".to_string()),
+        0x1F => Some("O".to_string()),
+        0x20 => Some("U".to_string()),
+        0x21 => Some("[".to_string()),
+        0x22 => Some("I".to_string()),
+        0x23 => Some("P".to_string()),
+        0x25 => Some("L".to_string()),
+        0x26 => Some("J".to_string()),
+        0x27 => Some("'".to_string()),
+        0x28 => Some("K".to_string()),
+        0x29 => Some(";".to_string()),
+        0x2A => Some("\\".to_string()),
+        0x2B => Some(",".to_string()),
+        0x2C => Some("/".to_string()),
+        0x2D => Some("N".to_string()),
+        0x2E => Some("M".to_string()),
+        0x2F => Some(".".to_string()),
+        0x32 => Some("`".to_string()),
+        0x31 => Some("Space".to_string()),
+        0x24 => Some("↵".to_string()),
+        0x30 => Some("⇥".to_string()),
+        0x33 => Some("⌫".to_string()),
+        0x35 => Some("⎋".to_string()),
+        0x7A => Some("F1".to_string()),
+        0x78 => Some("F2".to_string()),
+        0x63 => Some("F3".to_string()),
+        0x76 => Some("F4".to_string()),
+        0x60 => Some("F5".to_string()),
+        0x61 => Some("F6".to_string()),
+        0x62 => Some("F7".to_string()),
+        0x64 => Some("F8".to_string()),
+        0x65 => Some("F9".to_string()),
+        0x6D => Some("F10".to_string()),
+        0x67 => Some("F11".to_string()),
+        0x6F => Some("F12".to_string()),
+        0x69 => Some("F13".to_string()),
+        0x6B => Some("F14".to_string()),
+        0x71 => Some("F15".to_string()),
+        0x6A => Some("F16".to_string()),
+        0x40 => Some("F17".to_string()),
+        0x4F => Some("F18".to_string()),
+        0x50 => Some("F19".to_string()),
+        0x5A => Some("F20".to_string()),
+        0x7B => Some("←".to_string()),
+        0x7C => Some("→".to_string()),
+        0x7D => Some("↓".to_string()),
+        0x7E => Some("↑".to_string()),
+        0x73 => Some("↖".to_string()),
+        0x77 => Some("↘".to_string()),
+        0x74 => Some("⇞".to_string()),
+        0x79 => Some("⇟".to_string()),
+        0x75 => Some("⌦".to_string()),
+        _ => None,
+    }
+}
+
+/// Convert a key name to display symbol
+pub fn key_name_to_display(key: &str) -> String {
+    match key.to_lowercase().as_str() {
+        "semicolon" | ";" => ";".to_string(),
+        "comma" | "," => ",".to_string(),
+        "period" | "." => ".".to_string(),
+        "slash" | "/" => "/".to_string(),
+        "backslash" | "\\" => "\\".to_string(),
+        "quote" | "'" => "'".to_string(),
+        "grave" | "backtick" | "`" => "`".to_string(),
+        "space" => "Space".to_string(),
+        "return" | "enter" => "↵".to_string(),
+        "tab" => "⇥".to_string(),
+        "delete" | "backspace" => "⌫".to_string(),
+        "escape" | "esc" => "⎋".to_string(),
+        "left" | "left_arrow" => "←".to_string(),
+        "right" | "right_arrow" => "→".to_string(),
+        "down" | "down_arrow" => "↓".to_string(),
+        "up" | "up_arrow" => "↑".to_string(),
+        "home" => "↖".to_string(),
+        "end" => "↘".to_string(),
+        "page_up" | "pageup" => "⇞".to_string(),
+        "page_down" | "pagedown" => "⇟".to_string(),
+        "forward_delete" | "forwarddelete" => "⌦".to_string(),
+        other => other.to_uppercase(),
+    }
+}
+
+/// Convert modifier flags to display string with Unicode symbols
+pub fn modifiers_to_display(modifiers: u64) -> String {
+    let mut result = String::new();
+    // Order: Caps Lock, Control, Option, Shift, Command (standard macOS order)
+    if modifiers & FLAG_ALPHA_SHIFT != 0 {
+        result.push('⇪');
+    }
+    if modifiers & FLAG_CONTROL != 0 {
+        result.push('⌃');
+    }
+    if modifiers & FLAG_ALTERNATE != 0 {
+        result.push('⌥');
+    }
+    if modifiers & FLAG_SHIFT != 0 {
+        result.push('⇧');
+    }
+    if modifiers & FLAG_COMMAND != 0 {
+        result.push('⌘');
+    }
+    result
+}
+
+/// Check if two hotkeys trigger on the same keys, however they're spelled
+///
+/// Unlike `==`, a long press and variants are ignored, so this tells whether
+/// two hotkeys would conflict.
+pub fn same_hotkey(a: &HotkeyConfig, b: &HotkeyConfig) -> bool {
+    match (hotkey_chords(a), hotkey_chords(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+impl HotkeyConfig {
+    /// The same hotkey spelled the canonical way
+    ///
+    /// Modifiers get their canonical names in a fixed order, keys their config
+    /// name (or raw code when they have none), and flags without a name stay in
+    /// `modifier_flags`. Keys and modifiers that aren't known are kept, in lowercase.
+    pub fn canonical(&self) -> HotkeyConfig {
+        let flags = self.modifiers.iter().fold(self.modifier_flags.unwrap_or(0), |flags, name| {
+            flags | modifier_flags(name).unwrap_or(0)
+        });
+        let mut modifiers = modifiers_to_config(flags);
+        let mut unknown: Vec<String> = self
+            .modifiers
+            .iter()
+            .filter(|name| modifier_flags(name).is_none())
+            .map(|name| name.to_lowercase())
+            .collect();
+        unknown.sort();
+        modifiers.extend(unknown);
+
+        let (key, key_code) = match chord_key_code(self) {
+            Ok(code) => match key_code_to_config(code) {
+                Some(name) => (name, None),
+                None => (String::new(), Some(code)),
+            },
+            Err(_) => (self.key.to_lowercase(), self.key_code),
+        };
+
+        let variants = self
+            .variants
+            .iter()
+            .map(|(name, variant)| {
+                let canonical = modifier_flags(name).map(modifiers_to_config).unwrap_or_default();
+                match canonical.as_slice() {
+                    [single] => (single.clone(), *variant),
+                    _ => (name.to_lowercase(), *variant),
+                }
+            })
+            .collect();
+
+        HotkeyConfig {
+            modifiers,
+            key,
+            key_code,
+            modifier_flags: Some(flags & !MODIFIER_MASK).filter(|raw| *raw != 0),
+            sequence: self.sequence.iter().map(HotkeyConfig::canonical).collect(),
+            long_press_ms: self.long_press_ms,
+            variants,
+            modifier_match: self.modifier_match,
+            enabled: self.enabled,
+        }
+    }
+}
+
+/// Hotkeys are equal when their canonical spellings are, so the order and
+/// case of modifiers, and aliases of keys, don't matter
+impl PartialEq for HotkeyConfig {
+    fn eq(&self, other: &Self) -> bool {
+        let (a, b) = (self.canonical(), other.canonical());
+        a.modifiers == b.modifiers
+            && a.key == b.key
+            && a.key_code == b.key_code
+            && a.modifier_flags == b.modifier_flags
+            && a.sequence.len() == b.sequence.len()
+            && a.sequence.iter().zip(&b.sequence).all(|(a, b)| a == b)
+            && a.long_press_ms == b.long_press_ms
+            && a.variants == b.variants
+            && a.modifier_match == b.modifier_match
+    }
+}
+
+impl Eq for HotkeyConfig {}
+
+/// Flags of all modifiers that select a variant of the hotkey
+///
+/// Fails on a modifier name that isn't known.
+pub fn variant_modifiers(config: &HotkeyConfig) -> Result<u64> {
+    config.variants.keys().try_fold(0, |mask, name| {
+        modifier_flags(name)
+            .map(|flags| mask | flags)
+            .with_context(|| format!("Unknown modifier \"{}\"", name))
+    })
+}
+
+/// Variants selected by the modifiers held when the hotkey was triggered
+///
+/// Only modifiers the hotkey's last chord doesn't use itself count, so
+/// `shift` selects nothing for a hotkey that already includes Shift.
+pub fn held_variants(config: &HotkeyConfig, event_flags: u64) -> Vec<ModifierVariant> {
+    let last_chord = config.sequence.last().unwrap_or(config);
+    let extra = event_flags & MODIFIER_MASK & !chord_flags(last_chord);
+    config
+        .variants
+        .iter()
+        .filter(|(name, _)| modifier_flags(name).is_some_and(|flags| flags != 0 && extra & flags == flags))
+        .map(|(_, variant)| *variant)
+        .collect()
+}
+
+/// Common hotkeys that don't conflict with standard macOS shortcuts
+pub fn hotkey_presets() -> Vec<HotkeyConfig> {
+    let preset = HotkeyConfig::new;
+    vec![
+        preset(&["cmd", "shift"], "semicolon"),
+        preset(&["cmd", "shift"], "e"),
+        preset(&["ctrl", "alt"], "e"),
+        preset(&["hyper"], "semicolon"),
+        preset(&["meh"], "e"),
+    ]
+}
+
+/// Format a HotkeyConfig for display (e.g., "⌘⇧;", "⌘; E" for a sequence,
+/// or "⌘⇧; (hold)" for a long press)
+pub fn format_hotkey_display(config: &HotkeyConfig) -> String {
+    let chords = std::iter::once(config)
+        .chain(config.sequence.iter())
+        .map(|chord| {
+            let mod_str = modifiers_to_display(chord_flags(chord));
+            let key_str = match chord.key_code {
+                Some(key_code) if chord.key.is_empty() => key_code_to_display(key_code)
+                    .unwrap_or_else(|| format!("Key {:#04X}", key_code)),
+                _ => key_name_to_display(&chord.key),
+            };
+            format!("{}{}", mod_str, key_str)
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    match config.long_press_ms {
+        Some(_) => format!("{} (hold)", chords),
+        None => chords,
+    }
+}
+
+/// Convert modifier flags back to config strings
+///
+/// Always produces the canonical names, so aliases read from a config file
+/// are normalized the next time it is saved.
+pub fn modifiers_to_config(modifiers: u64) -> Vec<String> {
+    let mut result = Vec::new();
+    if modifiers & FLAG_COMMAND != 0 {
+        result.push("cmd".to_string());
+    }
+    if modifiers & FLAG_SHIFT != 0 {
+        result.push("shift".to_string());
+    }
+    if modifiers & FLAG_ALTERNATE != 0 {
+        result.push("alt".to_string());
+    }
+    if modifiers & FLAG_CONTROL != 0 {
+        result.push("ctrl".to_string());
+    }
+    if modifiers & FLAG_ALPHA_SHIFT != 0 {
+        result.push("caps".to_string());
+    }
+    result
+}
+
+/// Convert a key code back to config string
+pub fn key_code_to_config(key_code: u16) -> Option<String> {
+    match key_code {
+        0x00 => Some("a".to_string()),
+        0x01 => Some("s".to_string()),
+        0x02 => Some("d".to_string()),
+        0x03 => Some("f".to_string()),
+        0x04 => Some("h".to_string()),
+        0x05 => Some("g".to_string()),
+        0x06 => Some("z".to_string()),
+        0x07 => Some("x".to_string()),
+        0x08 => Some("c".to_string()),
+        0x09 => Some("v".to_string()),
+        0x0B => Some("b".to_string()),
+        0x0C => Some("q".to_string()),
+        0x0D => Some("w".to_string()),
+        0x0E => Some("e".to_string()),
+        0x0F => Some("r".to_string()),
+        0x10 => Some("y".to_string()),
+        0x11 => Some("t".to_string()),
+        0x12 => Some("1".to_string()),
+        0x13 => Some("2".to_string()),
+        0x14 => Some("3".to_string()),
+        0x15 => Some("4".to_string()),
+        0x16 => Some("6".to_string()),
+        0x17 => Some("5".to_string()),
+        0x18 => Some("=".to_string()),
+        0x19 => Some("9".to_string()),
+        0x1A => Some("7".to_string()),
+        0x1B => Some("-".to_string()),
+        0x1C => Some("8".to_string()),
+        0x1D => Some("0".to_string()),
+        0x1E => Some("]".to_string()),
+        0x1F => Some("o".to_string()),
+        0x20 => Some("u".to_string()),
+        0x21 => Some("[".to_string()),
+        0x22 => Some("i".to_string()),
+        0x23 => Some("p".to_string()),
+        0x25 => Some("l".to_string()),
+        0x26 => Some("j".to_string()),
+        0x27 => Some("'".to_string()),
+        0x28 => Some("k".to_string()),
+        0x29 => Some("semicolon".to_string()),
+        0x2A => Some("backslash".to_string()),
+        0x2B => Some("comma".to_string()),
+        0x2C => Some("slash".to_string()),
+        0x2D => Some("n".to_string()),
+        0x2E => Some("m".to_string()),
+        0x2F => Some("period".to_string()),
+        0x32 => Some("grave".to_string()),
+        0x31 => Some("space".to_string()),
+        0x24 => Some("return".to_string()),
+        0x30 => Some("tab".to_string()),
+        0x33 => Some("backspace".to_string()),
+        0x35 => Some("escape".to_string()),
+        0x7A => Some("f1".to_string()),
+        0x78 => Some("f2".to_string()),
+        0x63 => Some("f3".to_string()),
+        0x76 => Some("f4".to_string()),
+        0x60 => Some("f5".to_string()),
+        0x61 => Some("f6".to_string()),
+        0x62 => Some("f7".to_string()),
+        0x64 => Some("f8".to_string()),
+        0x65 => Some("f9".to_string()),
+        0x6D => Some("f10".to_string()),
+        0x67 => Some("f11".to_string()),
+        0x6F => Some("f12".to_string()),
+        0x69 => Some("f13".to_string()),
+        0x6B => Some("f14".to_string()),
+        0x71 => Some("f15".to_string()),
+        0x6A => Some("f16".to_string()),
+        0x40 => Some("f17".to_string()),
+        0x4F => Some("f18".to_string()),
+        0x50 => Some("f19".to_string()),
+        0x5A => Some("f20".to_string()),
+        0x7B => Some("left".to_string()),
+        0x7C => Some("right".to_string()),
+        0x7D => Some("down".to_string()),
+        0x7E => Some("up".to_string()),
+        0x73 => Some("home".to_string()),
+        0x77 => Some("end".to_string()),
+        0x74 => Some("page_up".to_string()),
+        0x79 => Some("page_down".to_string()),
+        0x75 => Some("forward_delete".to_string()),
+        _ => None,
+    }
+}
+
+/// The hotkey for a key press captured by the recorder
+///
+/// Keys without a config name are kept by their raw code.
+pub fn recorded_hotkey(key_code: u16, modifiers: u64) -> HotkeyConfig {
+    let key_name = key_code_to_config(key_code);
+    HotkeyConfig {
+        modifiers: modifiers_to_config(modifiers),
+        key_code: key_name.is_none().then_some(key_code),
+        key: key_name.unwrap_or_default(),
+        ..HotkeyConfig::default()
+    }
+}
+
+/// Key code of Caps Lock
+const KEY_CAPS_LOCK: u16 = 0x39;
+
+/// Modifiers a key press is recorded with
+///
+/// The Caps Lock flag only says the lock is on, so it's left out unless
+/// Caps Lock itself was the key pressed.
+pub fn recorded_modifiers(key_code: u16, modifiers: u64) -> u64 {
+    if key_code == KEY_CAPS_LOCK {
+        modifiers
+    } else {
+        modifiers & !FLAG_ALPHA_SHIFT
+    }
+}
+
+/// Check that a recorded hotkey matches the key press it was recorded from
+///
+/// Returns Some(description) when the config resolves to another key code
+/// or other modifiers, which means the key tables disagree and the hotkey
+/// would never fire.
+pub fn recording_mismatch(config: &HotkeyConfig, key_code: u16, modifiers: u64) -> Option<String> {
+    let modifiers = modifiers & MODIFIER_MASK;
+    match hotkey_chords(config).as_deref() {
+        Ok([(code, flags)]) if *code == key_code && *flags == modifiers => None,
+        Ok(chords) => Some(format!(
+            "recorded key {:#04X} with flags {:#x}, but the config resolves to {:?}",
+            key_code, modifiers, chords
+        )),
+        Err(e) => Some(format!("recorded key {:#04X}, but the config doesn't resolve: {}", key_code, e)),
+    }
+}
+
+/// A key that can be bound, as the recorder stores it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyEntry {
+    /// Name in the config
+    pub name: String,
+    /// macOS virtual key code
+    pub key_code: u16,
+    /// How the key is shown in the menu
+    pub display: String,
+}
+
+/// Every key with a config name, by key code
+///
+/// Derived from the table the recorder uses, so this is exactly what a
+/// recorded hotkey can contain. Aliases accepted in the config (e.g. `enter`)
+/// aren't listed.
+pub fn supported_keys() -> Vec<KeyEntry> {
+    (0..=0x7F)
+        .filter_map(|key_code| {
+            let name = key_code_to_config(key_code)?;
+            Some(KeyEntry {
+                display: key_name_to_display(&name),
+                name,
+                key_code,
+            })
+        })
+        .collect()
+}
+
+/// Canonical modifier names and the combinations with a name of their own,
+/// with their symbols
+pub fn supported_modifiers() -> Vec<(&'static str, String)> {
+    ["cmd", "shift", "alt", "ctrl", "caps", "hyper", "meh"]
+        .into_iter()
+        .map(|name| (name, modifiers_to_display(modifier_flags(name).unwrap_or(0))))
+        .collect()
+}
+
+/// Shifted characters of a US keyboard, with the key they're typed on
+const SHIFTED_CHARS: [(char, char); 21] = [
+    ('!', '1'), ('@', '2'), ('#', '3'), ('$', '4'), ('%', '5'), ('^', '6'), ('&', '7'),
+    ('*', '8'), ('(', '9'), (')', '0'), ('_', '-'), ('+', '='), ('{', '['), ('}', ']'),
+    ('|', '\\'), (':', ';'), ('"', '\''), ('<', ','), ('>', '.'), ('?', '/'), ('~', '`'),
+];
+
+/// Key code typing `c` on a US keyboard layout, and whether Shift is held for it
+///
+/// Covers printable ASCII, newlines (Return) and tabs; anything else can't be typed.
+pub fn typed_key(c: char) -> Option<(u16, bool)> {
+    match c {
+        '\n' => return key_code_from_string("return").map(|code| (code, false)),
+        '\t' => return key_code_from_string("tab").map(|code| (code, false)),
+        ' ' => return key_code_from_string("space").map(|code| (code, false)),
+        _ => {}
+    }
+    if !c.is_ascii_graphic() {
+        return None;
+    }
+    let (key, shift) = match SHIFTED_CHARS.iter().find(|(shifted, _)| *shifted == c) {
+        Some(&(_, key)) => (key, true),
+        None => (c.to_ascii_lowercase(), c.is_ascii_uppercase()),
+    };
+    key_code_from_string(&key.to_string()).map(|code| (code, shift))
+}
+
+/// Check if every character of `text` can be typed (see [`typed_key`])
+pub fn can_type(text: &str) -> bool {
+    text.chars().all(|c| typed_key(c).is_some())
+}
+
+/// Get the modifier mask constant (for use in recorder)
+pub const fn get_modifier_mask() -> u64 {
+    MODIFIER_MASK
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// Every key name and alias accepted in the config, plus every printable
+    /// ASCII character, so new table entries are covered automatically
+    fn candidate_keys() -> Vec<String> {
+        let aliases = [
+            "quote", "semicolon", "backslash", "comma", "slash", "period", "grave",
+            "backtick", "space", "return", "enter", "tab", "delete", "backspace",
+            "escape", "esc", "left", "left_arrow", "right", "right_arrow", "down",
+            "down_arrow", "up", "up_arrow", "home", "end", "page_up", "pageup", "page_down",
+            "pagedown", "forward_delete", "forwarddelete",
+        ];
+        (' '..='~')
+            .map(|c| c.to_string())
+            .chain(aliases.iter().map(|a| a.to_string()))
+            .chain((1..=20).map(|n| format!("f{}", n)))
+            .collect()
+    }
+
+    /// All combinations of the individual modifier flags
+    fn all_modifier_combinations() -> Vec<u64> {
+        let flags = [FLAG_COMMAND, FLAG_SHIFT, FLAG_ALTERNATE, FLAG_CONTROL, FLAG_ALPHA_SHIFT];
+        (0..1u32 << flags.len())
+            .map(|bits| {
+                flags
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| bits & (1 << i) != 0)
+                    .fold(0, |acc, (_, flag)| acc | flag)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn key_codes_round_trip_through_config_names() {
+        for code in 0..=0x7F {
+            if let Some(name) = key_code_to_config(code) {
+                assert_eq!(key_code_from_string(&name), Some(code), "key {:?}", name);
+                assert_eq!(key_code_from_string(&name.to_uppercase()), Some(code));
+                assert_eq!(key_code_to_display(code), Some(key_name_to_display(&name)), "key {:?}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn every_accepted_key_name_maps_to_a_recordable_code() {
+        for key in candidate_keys() {
+            if let Some(code) = key_code_from_string(&key) {
+                let name = key_code_to_config(code)
+                    .unwrap_or_else(|| panic!("{:?} parses to code {:#x} with no name", key, code));
+                assert_eq!(key_code_from_string(&name), Some(code), "key {:?}", key);
+                assert!(key_code_to_display(code).is_some(), "key {:?}", key);
+            }
+        }
+        assert_eq!(key_code_from_string("f12"), Some(0x6F));
+        assert_eq!(key_code_from_string("PageUp"), Some(0x74));
+    }
+
+    #[test]
+    fn supported_keys_match_the_parser() {
+        let keys = supported_keys();
+        assert!(keys.iter().any(|key| key.name == "semicolon" && key.display == ";"));
+        for key in &keys {
+            assert_eq!(key_code_from_string(&key.name), Some(key.key_code), "{:?}", key);
+            assert!(!key.display.is_empty(), "{:?}", key);
+        }
+        for (name, symbol) in supported_modifiers() {
+            assert!(modifier_flags(name).is_some(), "{}", name);
+            assert!(!symbol.is_empty(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn chords_are_pressed_through_applescript() {
+        let chord = HotkeyConfig::new;
+        assert_eq!(applescript_key_code(&chord(&["cmd"], "v")).unwrap(), "key code 9 using {command down}");
+        assert_eq!(
+            applescript_key_code(&chord(&["shift", "cmd", "alt"], "v")).unwrap(),
+            "key code 9 using {command down, shift down, option down}"
+        );
+        assert_eq!(applescript_key_code(&chord(&[], "return")).unwrap(), "key code 36");
+        assert!(applescript_key_code(&chord(&["cmd"], "nope")).is_err());
+    }
+
+    #[test]
+    fn printable_ascii_can_be_typed() {
+        for c in (0x20u8..0x7F).map(char::from).chain(['\n', '\t']) {
+            assert!(typed_key(c).is_some(), "{:?}", c);
+        }
+        assert_eq!(typed_key('a'), Some((0x00, false)));
+        assert_eq!(typed_key('A'), Some((0x00, true)));
+        assert_eq!(typed_key('?'), Some((0x2C, true)));
+        assert_eq!(typed_key('\n'), Some((0x24, false)));
+        assert!(can_type("Hello, world!\n\tfn main() {}"));
+        assert!(!can_type("café"));
+        assert!(!can_type("a\r\nb"));
+    }
+
+    #[test]
+    fn invalid_key_names_are_rejected() {
+        for key in ["", "ab", "cmd", "f21", "f0", "semi colon", " ", "é"] {
+            assert_eq!(key_code_from_string(key), None, "key {:?}", key);
+        }
+        assert_eq!(key_code_to_config(0x0A), None);
+        assert_eq!(key_code_to_config(0xFFFF), None);
+    }
+
+    #[test]
+    fn hotkeys_compare_by_their_canonical_spelling() {
+        let hotkey = HotkeyConfig::new;
+        let a = hotkey(&["cmd", "shift"], "semicolon");
+        assert_eq!(a, hotkey(&["shift", "CMD"], ";"));
+        assert_eq!(a, hotkey(&["⌘⇧"], "Semicolon"));
+        assert_eq!(hotkey(&["hyper"], "e"), hotkey(&["ctrl", "alt", "shift", "cmd"], "E"));
+        assert_eq!(hotkey(&["cmd"], "a"), HotkeyConfig { key: String::new(), key_code: Some(0x00), ..hotkey(&["cmd"], "") });
+        assert_eq!(hotkey(&["shift", "Cmd"], ";").canonical().modifiers, ["cmd", "shift"]);
+
+        assert_ne!(a, hotkey(&["cmd"], "semicolon"));
+        assert_ne!(a, HotkeyConfig { long_press_ms: Some(500), ..a.clone() });
+        let with_variant = |name: &str| HotkeyConfig {
+            variants: HashMap::from([(name.to_string(), ModifierVariant::Append)]),
+            ..a.clone()
+        };
+        assert_eq!(with_variant("option"), with_variant("alt"));
+        assert_ne!(with_variant("alt"), a);
+    }
+
+    #[test]
+    fn modifiers_round_trip_through_config_names() {
+        for flags in all_modifier_combinations() {
+            let names = modifiers_to_config(flags);
+            assert_eq!(modifiers_from_config(&names), flags, "modifiers {:?}", names);
+            // Names are canonical, so parsing them again changes nothing
+            assert_eq!(modifiers_to_config(modifiers_from_config(&names)), names);
+        }
+    }
+
+    #[test]
+    fn modifier_aliases_normalize_to_canonical_names() {
+        let aliases = [
+            "command", "meta", "super", "⌘", "lcmd", "right_cmd", "⇧", "left-shift", "option",
+            "opt", "⌥", "control", "⌃", "rctrl", "capslock", "caps_lock", "⇪", "hyper", "meh",
+            "⌘⇧",
+        ];
+        for alias in aliases {
+            let flags = modifier_flags(alias).unwrap_or_else(|| panic!("{:?} rejected", alias));
+            let names = modifiers_to_config(flags);
+            assert_eq!(modifiers_from_config(&names), flags, "alias {:?}", alias);
+        }
+    }
+
+    #[test]
+    fn invalid_modifiers_are_rejected() {
+        for token in ["", "cmdd", "leftover", "fn", "a", "⌘x", "shift+cmd"] {
+            assert_eq!(modifier_flags(token), None, "modifier {:?}", token);
+        }
+        assert_eq!(modifiers_from_config(&["bogus".to_string()]), 0);
+    }
+
+    #[test]
+    fn flags_outside_the_mask_are_ignored() {
+        for flags in all_modifier_combinations() {
+            let noisy = flags | 0x0080_0000 | 0x100;
+            assert_eq!(modifiers_to_config(noisy), modifiers_to_config(flags));
+        }
+    }
+
+    #[test]
+    fn raw_key_code_is_used_instead_of_a_key_name() {
+        let chord: HotkeyConfig =
+            toml::from_str("modifiers = [\"cmd\"]\nkey_code = 0xA0\nmodifier_flags = 0x800000").unwrap();
+        assert_eq!(hotkey_chords(&chord).unwrap(), vec![(0xA0, FLAG_COMMAND | 0x80_0000)]);
+
+        // Keys without a name are recorded and shown by their code
+        let recorded = recorded_hotkey(0xA0, FLAG_COMMAND);
+        assert_eq!((recorded.key.as_str(), recorded.key_code), ("", Some(0xA0)));
+        assert_eq!(format_hotkey_display(&recorded), "⌘Key 0xA0");
+
+        let both: HotkeyConfig = toml::from_str("key = \"a\"\nkey_code = 0").unwrap();
+        assert!(chord_key_code(&both).is_err());
+        let neither: HotkeyConfig = toml::from_str("modifiers = [\"cmd\"]").unwrap();
+        assert!(chord_key_code(&neither).is_err());
+    }
+
+    #[test]
+    fn at_least_matching_allows_extra_modifiers() {
+        let target = FLAG_COMMAND | FLAG_SHIFT;
+        let extra = target | FLAG_ALTERNATE;
+        assert!(!ModifierMatch::Exact.matches(extra, target));
+        assert!(ModifierMatch::AtLeast.matches(extra, target));
+        assert!(ModifierMatch::AtLeast.matches(target, target));
+        assert!(!ModifierMatch::AtLeast.matches(FLAG_COMMAND | FLAG_ALTERNATE, target));
+        assert!(ModifierMatch::Exact.matches(target | FLAG_ALPHA_SHIFT, target));
+    }
+
+    #[test]
+    fn recorded_hotkeys_resolve_to_the_captured_keys() {
+        for code in 0..=0x7F {
+            for modifiers in all_modifier_combinations() {
+                let config = recorded_hotkey(code, modifiers);
+                assert_eq!(recording_mismatch(&config, code, modifiers), None, "{:?}", config);
+            }
+        }
+        let config = recorded_hotkey(0x29, FLAG_COMMAND);
+        assert!(recording_mismatch(&config, 0x27, FLAG_COMMAND).is_some());
+
+        // Caps Lock being on doesn't make it part of the hotkey
+        assert_eq!(recorded_modifiers(0x00, FLAG_ALPHA_SHIFT), 0);
+        assert_eq!(recorded_modifiers(0x0C, FLAG_ALPHA_SHIFT | FLAG_COMMAND), FLAG_COMMAND);
+        assert_eq!(recorded_modifiers(KEY_CAPS_LOCK, FLAG_ALPHA_SHIFT), FLAG_ALPHA_SHIFT);
+        assert!(recording_mismatch(&config, 0x29, FLAG_COMMAND | FLAG_SHIFT).is_some());
+    }
+
+    #[test]
+    fn extra_modifiers_select_variants() {
+        let hotkey: HotkeyConfig = toml::from_str(
+            "modifiers = [\"cmd\", \"alt\"]\nkey = \"e\"\n[variants]\nshift = \"select_all\"\nctrl = \"append\"",
+        )
+        .unwrap();
+        assert_eq!(variant_modifiers(&hotkey).unwrap(), FLAG_SHIFT | FLAG_CONTROL);
+
+        let base = FLAG_COMMAND | FLAG_ALTERNATE;
+        assert!(held_variants(&hotkey, base).is_empty());
+        assert_eq!(held_variants(&hotkey, base | FLAG_SHIFT), [ModifierVariant::SelectAll]);
+        assert_eq!(held_variants(&hotkey, base | FLAG_CONTROL | 0x100), [ModifierVariant::Append]);
+
+        // The hotkey's own modifiers don't select anything
+        let alt_variant: HotkeyConfig =
+            toml::from_str("modifiers = [\"cmd\", \"alt\"]\nkey = \"e\"\nvariants = { alt = \"append\" }").unwrap();
+        assert!(held_variants(&alt_variant, base).is_empty());
+
+        let unknown: HotkeyConfig = toml::from_str("key = \"e\"\nvariants = { fn = \"append\" }").unwrap();
+        assert!(variant_modifiers(&unknown).is_err());
+    }
+}
@@ -0,0 +1,17 @@
+//! Platform-independent core of helix-anywhere
+//!
+//! Everything here is plain Rust without AppKit or Core Graphics, so it
+//! builds and tests on any platform (`cargo test -p helix-anywhere-core`).
+//! The macOS app in the root crate adds the menu bar, the event taps and
+//! keystroke simulation on top.
+
+pub mod config;
+pub mod control;
+pub mod edit;
+pub mod helix_config;
+pub mod keys;
+pub mod markdown;
+pub mod terminal;
+
+/// File name prefix of every temp file and directory the app creates
+pub const TEMP_FILE_PREFIX: &str = "helix-anywhere-";
@@ -0,0 +1,495 @@
+//! Conversion between the HTML of rich selections and Markdown
+//!
+//! Only the common tags are handled: headings, paragraphs, line breaks,
+//! lists, links, images, bold, italic and code. Anything else is reduced to
+//! its text, so the result is always something a Markdown editor can show.
+
+/// Convert HTML (e.g. from the clipboard) to Markdown
+pub fn html_to_markdown(html: &str) -> String {
+    let mut writer = MarkdownWriter::default();
+    let mut rest = html;
+
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("<!--") {
+            rest = after.find("-->").map_or("", |end| &after[end + 3..]);
+            continue;
+        }
+        if rest.starts_with('<') {
+            if let Some(end) = rest.find('>') {
+                let tag = Tag::parse(&rest[1..end]);
+                rest = &rest[end + 1..];
+                // Their content isn't text
+                if !tag.closing && matches!(tag.name.as_str(), "head" | "style" | "script" | "title") {
+                    let close = format!("</{}", tag.name);
+                    rest = find_ignore_case(rest, &close)
+                        .and_then(|start| rest[start..].find('>').map(|end| &rest[start + end + 1..]))
+                        .unwrap_or("");
+                    continue;
+                }
+                writer.tag(&tag);
+                continue;
+            }
+        }
+
+        let first = rest.chars().next().map_or(1, char::len_utf8);
+        let end = rest[first..].find('<').map_or(rest.len(), |i| i + first);
+        writer.text(&decode_entities(&rest[..end]));
+        rest = &rest[end..];
+    }
+
+    writer.finish()
+}
+
+/// Convert Markdown back to HTML
+pub fn markdown_to_html(markdown: &str) -> String {
+    let mut html = String::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    // Open lists as (indent, ordered)
+    let mut lists: Vec<(usize, bool)> = Vec::new();
+    let mut lines = markdown.lines();
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+
+        if let Some(item) = list_item(trimmed) {
+            flush_paragraph(&mut html, &mut paragraph);
+            let (ordered, content) = item;
+            while lists.last().is_some_and(|&(open, _)| open > indent) {
+                close_list(&mut html, &mut lists);
+            }
+            match lists.last() {
+                Some(&(open, open_ordered)) if open == indent && open_ordered == ordered => {
+                    html.push_str("</li>");
+                }
+                Some(&(open, _)) if open == indent => {
+                    close_list(&mut html, &mut lists);
+                    open_list(&mut html, &mut lists, indent, ordered);
+                }
+                _ => open_list(&mut html, &mut lists, indent, ordered),
+            }
+            html.push_str("<li>");
+            html.push_str(&inline_to_html(content));
+            continue;
+        }
+
+        if trimmed.is_empty() {
+            flush_paragraph(&mut html, &mut paragraph);
+            while !lists.is_empty() {
+                close_list(&mut html, &mut lists);
+            }
+            continue;
+        }
+
+        if !lists.is_empty() && indent > 0 {
+            // Continuation of a list item
+            html.push_str("<br>");
+            html.push_str(&inline_to_html(trimmed));
+            continue;
+        }
+        while !lists.is_empty() {
+            close_list(&mut html, &mut lists);
+        }
+
+        if trimmed.starts_with("```") {
+            flush_paragraph(&mut html, &mut paragraph);
+            let code: Vec<&str> = lines.by_ref().take_while(|l| !l.trim_start().starts_with("```")).collect();
+            html.push_str("<pre><code>");
+            html.push_str(&escape_html(&code.join("\n")));
+            html.push_str("</code></pre>");
+            continue;
+        }
+
+        if let Some((level, title)) = heading(trimmed) {
+            flush_paragraph(&mut html, &mut paragraph);
+            html.push_str(&format!("<h{0}>{1}</h{0}>", level, inline_to_html(title)));
+            continue;
+        }
+
+        paragraph.push(trimmed);
+    }
+
+    flush_paragraph(&mut html, &mut paragraph);
+    while !lists.is_empty() {
+        close_list(&mut html, &mut lists);
+    }
+    html
+}
+
+/// A parsed HTML tag
+struct Tag {
+    name: String,
+    closing: bool,
+    attributes: String,
+}
+
+impl Tag {
+    fn parse(inner: &str) -> Self {
+        let inner = inner.trim().trim_end_matches('/');
+        let (closing, inner) = match inner.strip_prefix('/') {
+            Some(inner) => (true, inner),
+            None => (false, inner),
+        };
+        let name_end = inner.find(|c: char| c.is_whitespace()).unwrap_or(inner.len());
+        Self {
+            name: inner[..name_end].to_ascii_lowercase(),
+            closing,
+            attributes: inner[name_end..].to_string(),
+        }
+    }
+
+    /// Value of an attribute, e.g. `href`
+    fn attribute(&self, name: &str) -> Option<String> {
+        let mut rest = self.attributes.as_str();
+        while let Some(start) = find_ignore_case(rest, name) {
+            let before_ok = start == 0 || rest[..start].ends_with(char::is_whitespace);
+            let after = rest[start + name.len()..].trim_start();
+            if before_ok {
+                if let Some(value) = after.strip_prefix('=') {
+                    let value = value.trim_start();
+                    let raw = match value.chars().next() {
+                        Some(quote @ ('"' | '\'')) => value[1..].split(quote).next().unwrap_or(""),
+                        _ => value.split(char::is_whitespace).next().unwrap_or(""),
+                    };
+                    return Some(decode_entities(raw));
+                }
+            }
+            rest = &rest[start + name.len()..];
+        }
+        None
+    }
+}
+
+/// Builds Markdown from a stream of HTML tags and text
+#[derive(Default)]
+struct MarkdownWriter {
+    out: String,
+    /// Open lists, with the next number of ordered ones
+    lists: Vec<Option<usize>>,
+    /// Targets of the open links
+    links: Vec<String>,
+    /// Inside `<pre>`, where whitespace is kept
+    preformatted: bool,
+}
+
+impl MarkdownWriter {
+    fn tag(&mut self, tag: &Tag) {
+        match (tag.name.as_str(), tag.closing) {
+            ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", false) => {
+                self.block_break();
+                let level = tag.name[1..].parse().unwrap_or(1);
+                self.out.push_str(&"#".repeat(level));
+                self.out.push(' ');
+            }
+            ("h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "p" | "blockquote", true) => self.block_break(),
+            ("p" | "blockquote", false) => self.block_break(),
+            ("div" | "tr", _) => self.line_break(),
+            ("br", _) => self.out.push('\n'),
+            ("ul", false) => self.open_list(None),
+            ("ol", false) => self.open_list(Some(1)),
+            ("ul" | "ol", true) => {
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.block_break();
+                }
+            }
+            ("li", false) => {
+                self.line_break();
+                let depth = self.lists.len().max(1) - 1;
+                self.out.push_str(&"  ".repeat(depth));
+                match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        self.out.push_str(&format!("{}. ", number));
+                        *number += 1;
+                    }
+                    _ => self.out.push_str("- "),
+                }
+            }
+            ("strong" | "b", _) => self.out.push_str("**"),
+            ("em" | "i", _) => self.out.push('*'),
+            ("code", _) if !self.preformatted => self.out.push('`'),
+            ("pre", false) => {
+                self.block_break();
+                self.out.push_str("```\n");
+                self.preformatted = true;
+            }
+            ("pre", true) => {
+                self.line_break();
+                self.out.push_str("```");
+                self.preformatted = false;
+                self.block_break();
+            }
+            ("a", false) => {
+                self.links.push(tag.attribute("href").unwrap_or_default());
+                self.out.push('[');
+            }
+            ("a", true) => {
+                if let Some(href) = self.links.pop() {
+                    self.out.push_str(&format!("]({})", href));
+                }
+            }
+            ("img", false) => {
+                let alt = tag.attribute("alt").unwrap_or_default();
+                let src = tag.attribute("src").unwrap_or_default();
+                self.out.push_str(&format!("![{}]({})", alt, src));
+            }
+            _ => {}
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.preformatted {
+            self.out.push_str(text);
+            return;
+        }
+
+        let mut collapsed = String::new();
+        for word in text.split_whitespace() {
+            if !collapsed.is_empty() {
+                collapsed.push(' ');
+            }
+            collapsed.push_str(word);
+        }
+        let at_line_start = self.out.is_empty() || self.out.ends_with('\n') || self.out.ends_with(' ');
+        if text.starts_with(char::is_whitespace) && !at_line_start && !collapsed.is_empty() {
+            self.out.push(' ');
+        }
+        self.out.push_str(&collapsed);
+        if text.ends_with(char::is_whitespace) && !collapsed.is_empty() {
+            self.out.push(' ');
+        }
+    }
+
+    fn open_list(&mut self, start: Option<usize>) {
+        if self.lists.is_empty() {
+            self.block_break();
+        }
+        self.lists.push(start);
+    }
+
+    /// Start a new line, unless at the start of one
+    fn line_break(&mut self) {
+        trim_trailing_spaces(&mut self.out);
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.out.push('\n');
+        }
+    }
+
+    /// Leave a blank line, unless at the start of the text or after one
+    fn block_break(&mut self) {
+        self.line_break();
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    fn finish(self) -> String {
+        let mut lines: Vec<&str> = self.out.lines().map(str::trim_end).collect();
+        lines.dedup_by(|a, b| a.is_empty() && b.is_empty());
+        lines.join("\n").trim().to_string()
+    }
+}
+
+fn trim_trailing_spaces(text: &mut String) {
+    let trimmed = text.trim_end_matches(' ').len();
+    text.truncate(trimmed);
+}
+
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack.to_ascii_lowercase().find(&needle.to_ascii_lowercase())
+}
+
+/// Replace the HTML entities that show up in copied text
+fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest.find(';').filter(|&end| end <= 10).and_then(|end| {
+            let entity = &rest[1..end];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                    .and_then(char::from_u32),
+            };
+            c.map(|c| (c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Simple HTML for plain text: paragraphs at blank lines, line breaks within them
+///
+/// Unlike `markdown_to_html`, nothing in the text is taken as formatting.
+pub fn text_to_html(text: &str) -> String {
+    text.split("\n\n")
+        .map(|paragraph| paragraph.trim_matches('\n'))
+        .filter(|paragraph| !paragraph.is_empty())
+        .map(|paragraph| {
+            let lines: Vec<String> = paragraph.lines().map(escape_html).collect();
+            format!("<p>{}</p>", lines.join("<br>"))
+        })
+        .collect()
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// `(level, title)` of an ATX heading line
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    let title = line[level..].strip_prefix(' ')?;
+    (1..=6).contains(&level).then(|| (level, title.trim_end_matches('#').trim()))
+}
+
+/// `(ordered, content)` of a list item line (without its indentation)
+fn list_item(line: &str) -> Option<(bool, &str)> {
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(content) = line.strip_prefix(bullet) {
+            return Some((false, content));
+        }
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if digits == 0 {
+        return None;
+    }
+    line[digits..].strip_prefix(". ").map(|content| (true, content))
+}
+
+fn open_list(html: &mut String, lists: &mut Vec<(usize, bool)>, indent: usize, ordered: bool) {
+    html.push_str(if ordered { "<ol>" } else { "<ul>" });
+    lists.push((indent, ordered));
+}
+
+fn close_list(html: &mut String, lists: &mut Vec<(usize, bool)>) {
+    if let Some((_, ordered)) = lists.pop() {
+        html.push_str(if ordered { "</li></ol>" } else { "</li></ul>" });
+    }
+}
+
+fn flush_paragraph(html: &mut String, paragraph: &mut Vec<&str>) {
+    if paragraph.is_empty() {
+        return;
+    }
+    let lines: Vec<String> = paragraph.iter().map(|line| inline_to_html(line)).collect();
+    html.push_str("<p>");
+    html.push_str(&lines.join("<br>"));
+    html.push_str("</p>");
+    paragraph.clear();
+}
+
+/// Convert the inline Markdown of a line (emphasis, code, links, images)
+fn inline_to_html(text: &str) -> String {
+    let mut html = String::new();
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        let converted = match c {
+            '`' => delimited(rest, "`").map(|(inner, len)| (format!("<code>{}</code>", escape_html(inner)), len)),
+            '*' | '_' if rest[1..].starts_with(c) => {
+                let marker = &rest[..2];
+                delimited(rest, marker)
+                    .map(|(inner, len)| (format!("<strong>{}</strong>", inline_to_html(inner)), len))
+            }
+            '*' | '_' => delimited(rest, &rest[..1])
+                .map(|(inner, len)| (format!("<em>{}</em>", inline_to_html(inner)), len)),
+            '!' if rest[1..].starts_with('[') => link(&rest[1..]).map(|(alt, src, len)| {
+                (format!("<img src=\"{}\" alt=\"{}\">", escape_html(src), escape_html(alt)), len + 1)
+            }),
+            '[' => link(rest).map(|(label, href, len)| {
+                (format!("<a href=\"{}\">{}</a>", escape_html(href), inline_to_html(label)), len)
+            }),
+            _ => None,
+        };
+
+        match converted {
+            Some((converted, len)) => {
+                html.push_str(&converted);
+                rest = &rest[len..];
+            }
+            None => {
+                html.push_str(&escape_html(&rest[..c.len_utf8()]));
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    html
+}
+
+/// Content between `marker` at the start of `text` and its closing
+/// counterpart, with the total length taken up
+fn delimited<'a>(text: &'a str, marker: &str) -> Option<(&'a str, usize)> {
+    let after = &text[marker.len()..];
+    let end = after.find(marker).filter(|&end| end > 0)?;
+    Some((&after[..end], marker.len() * 2 + end))
+}
+
+/// `(label, target, length)` of a `[label](target)` link at the start of `text`
+fn link(text: &str) -> Option<(&str, &str, usize)> {
+    let label_end = text.find("](")?;
+    let target_end = text[label_end + 2..].find(')')? + label_end + 2;
+    Some((&text[1..label_end], &text[label_end + 2..target_end], target_end + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn common_tags_become_markdown() {
+        let html = r#"<meta charset="utf-8"><h2>Plan</h2><p>Read <b>this</b> and <a href="https://example.com/?a=1&amp;b=2">that</a>, <i>then</i> run <code>make</code>.</p><ul><li>one</li><li>two<ol><li>first</li><li>second</li></ol></li></ul><p>Last<br>line</p><p>é &lt; ü</p>"#;
+        assert_eq!(
+            html_to_markdown(html),
+            "## Plan\n\nRead **this** and [that](https://example.com/?a=1&b=2), *then* run `make`.\n\n- one\n- two\n  1. first\n  2. second\n\nLast\nline\n\né < ü"
+        );
+    }
+
+    #[test]
+    fn markdown_becomes_html() {
+        let markdown = "## Plan\n\nRead **this** and [that](https://example.com), *then* run `a < b`.\n\n- one\n- two\n  1. first\n  2. second\n\nLast\nline";
+        assert_eq!(
+            markdown_to_html(markdown),
+            "<h2>Plan</h2><p>Read <strong>this</strong> and <a href=\"https://example.com\">that</a>, <em>then</em> run <code>a &lt; b</code>.</p><ul><li>one</li><li>two<ol><li>first</li><li>second</li></ol></li></ul><p>Last<br>line</p>"
+        );
+    }
+
+    #[test]
+    fn plain_text_becomes_simple_html() {
+        assert_eq!(
+            text_to_html("Dear *team*,\n\nfirst <line>\nsecond & last\n"),
+            "<p>Dear *team*,</p><p>first &lt;line&gt;<br>second &amp; last</p>"
+        );
+        assert_eq!(text_to_html(""), "");
+    }
+
+    #[test]
+    fn markdown_survives_a_round_trip() {
+        let markdown = "# Title\n\nSome *text* with a [link](https://example.com).\n\n1. a\n2. b\n\n```\nlet x = 1 < 2;\n```";
+        assert_eq!(html_to_markdown(&markdown_to_html(markdown)), markdown);
+    }
+}
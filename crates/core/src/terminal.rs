@@ -0,0 +1,1082 @@
+use crate::config::{Config, TerminalConfig, WindowMode};
+use crate::helix_config::GUTTER_WIDTH;
+use crate::TEMP_FILE_PREFIX;
+use anyhow::{bail, Result};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use tempfile::TempPath;
+
+/// Options for launching the editor in a terminal
+#[derive(Debug, Clone, Default)]
+pub struct LaunchOptions {
+    /// Window width in columns
+    pub width: u32,
+    /// Window height in lines
+    pub height: u32,
+    /// Extra environment variables for the editor process
+    pub env: Vec<(String, String)>,
+    /// File to write the window size (`rows cols`) to when the editor exits
+    pub size_report: Option<PathBuf>,
+    /// File created when the editor exits, for terminals that can't be waited on
+    pub exit_marker: Option<PathBuf>,
+    /// Directory the editor is started in
+    pub working_dir: Option<PathBuf>,
+    /// Editors to try, in order (`hx` if empty)
+    pub editors: Vec<String>,
+    /// Extra arguments passed to the editor before the file
+    pub editor_args: Vec<String>,
+    /// Helix config file, passed with `-c` before `editor_args`
+    pub helix_config: Option<PathBuf>,
+    /// Whether the window opens maximized or fullscreen
+    pub window_mode: WindowMode,
+    /// Title of the window, where the terminal lets it be set
+    pub window_title: Option<String>,
+}
+
+impl LaunchOptions {
+    /// Build launch options from the config
+    pub fn from_config(config: &Config) -> Self {
+        let mut env = Vec::new();
+        if let Some(ref dir) = config.editor.helix_config_dir {
+            env.push((
+                "XDG_CONFIG_HOME".to_string(),
+                dir.to_string_lossy().to_string(),
+            ));
+        }
+
+        // Sorted so the generated commands are stable
+        let mut editor_env: Vec<_> = config.editor.env.clone().into_iter().collect();
+        editor_env.sort();
+        env.extend(editor_env);
+
+        let working_dir = config.editor.working_dir.clone().filter(|dir| {
+            let exists = dir.is_dir();
+            if !exists {
+                log::warn!("Working directory {:?} doesn't exist, ignoring it", dir);
+            }
+            exists
+        });
+
+        // `-c` means something else to other editors
+        let helix_config = config.editor.config_path.clone().filter(|_| {
+            let is_helix = resolve_editor(&config.editor.editors).is_ok_and(|editor| is_helix(&editor));
+            if !is_helix {
+                log::debug!("Not passing editor.config_path: the editor isn't Helix");
+            }
+            is_helix
+        });
+
+        let (width, height) = clamp_window_size(config.terminal.width, config.terminal.height);
+        if (width, height) != (config.terminal.width, config.terminal.height) {
+            log::warn!(
+                "Window size {}x{} is out of range, using {}x{}",
+                config.terminal.width,
+                config.terminal.height,
+                width,
+                height
+            );
+        }
+
+        Self {
+            width,
+            height,
+            env,
+            size_report: None,
+            exit_marker: None,
+            working_dir,
+            editors: config.editor.editors.clone(),
+            editor_args: config.editor.startup_args.clone(),
+            helix_config,
+            window_mode: config.terminal.window_mode,
+            window_title: None,
+        }
+    }
+
+    /// Command line running the editor on `file`
+    ///
+    /// With a size report file or an exit marker, the editor runs through
+    /// `sh` so `stty size` can record the final window size, and the marker
+    /// be created, once it exits. With `title_escape`, `sh` also sets the
+    /// window title first, for terminals without a title option.
+    fn editor_command(&self, hx: &str, file: &str, title_escape: bool) -> Vec<String> {
+        let title_prefix = if title_escape { self.title_command_prefix() } else { String::new() };
+        let mut command = if self.size_report.is_none()
+            && self.exit_marker.is_none()
+            && title_prefix.is_empty()
+        {
+            vec![hx.to_string()]
+        } else {
+            let script = match self.size_report {
+                Some(_) => r#"report="$1"; shift; "$0" "$@"; stty size > "$report""#,
+                None => r#""$0" "$@""#,
+            };
+            let mut command = vec![
+                "/bin/sh".to_string(),
+                "-c".to_string(),
+                format!("{}{}{}", title_prefix, script, self.exit_marker_suffix()),
+                hx.to_string(),
+            ];
+            command.extend(self.size_report.iter().map(|report| report.to_string_lossy().to_string()));
+            command
+        };
+        command.extend(self.all_editor_args());
+        command.push(file.to_string());
+        command
+    }
+
+    /// Write the Ghostty script to an executable temp file, deleted when the
+    /// returned path is dropped
+    ///
+    /// In the temp dir, so editing a user's file never overwrites a sibling script.
+    fn write_ghostty_script(&self, hx: &str, file: &str) -> Result<TempPath> {
+        let script_path = tempfile::Builder::new()
+            .prefix(TEMP_FILE_PREFIX)
+            .suffix(".sh")
+            .tempfile()
+            .map_err(|e| anyhow::anyhow!("Failed to create script: {}", e))?
+            .into_temp_path();
+        std::fs::write(&script_path, self.ghostty_script(hx, file))
+            .map_err(|e| anyhow::anyhow!("Failed to create script: {}", e))?;
+
+        // Make script executable
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&script_path, std::fs::Permissions::from_mode(0o755))
+                .map_err(|e| anyhow::anyhow!("Failed to set script permissions: {}", e))?;
+        }
+        Ok(script_path)
+    }
+
+    /// Script run by Ghostty, which can't be given a command line directly
+    fn ghostty_script(&self, hx: &str, file: &str) -> String {
+        let exports: String = self
+            .env
+            .iter()
+            .map(|(key, value)| format!("export {}={}\n", key, shell_quote(value)))
+            .collect();
+        format!(
+            "#!/bin/bash\n{}{}{}{} {}{}\n",
+            exports,
+            self.cd_command_prefix(),
+            shell_quote(hx),
+            self.editor_args_words(),
+            shell_quote(file),
+            self.exit_marker_suffix()
+        )
+    }
+
+    /// Arguments passed to the editor before the file: the Helix config, then `editor_args`
+    fn all_editor_args(&self) -> Vec<String> {
+        let helix_config = self
+            .helix_config
+            .iter()
+            .flat_map(|path| ["-c".to_string(), path.to_string_lossy().to_string()]);
+        helix_config.chain(self.editor_args.iter().cloned()).collect()
+    }
+
+    /// ` 'arg' ...` of the extra editor arguments for shell commands, empty without any
+    fn editor_args_words(&self) -> String {
+        self.all_editor_args()
+            .iter()
+            .map(|arg| format!(" {}", shell_quote(arg)))
+            .collect()
+    }
+
+    /// `; touch <marker>` suffix for shell commands, empty without an exit marker
+    fn exit_marker_suffix(&self) -> String {
+        match self.exit_marker {
+            Some(ref marker) => format!("; touch {}", shell_quote(&marker.to_string_lossy())),
+            None => String::new(),
+        }
+    }
+
+    /// `printf` of the escape sequence setting the window title, followed by
+    /// `; `, empty without a title
+    fn title_command_prefix(&self) -> String {
+        match self.window_title {
+            Some(ref title) => format!(r"printf '\033]2;%s\007' {}; ", shell_quote(title)),
+            None => String::new(),
+        }
+    }
+
+    /// `cd '<dir>' && ` prefix for shell commands, empty without a working directory
+    fn cd_command_prefix(&self) -> String {
+        match self.working_dir {
+            Some(ref dir) => format!("cd {} && ", shell_quote(&dir.to_string_lossy())),
+            None => String::new(),
+        }
+    }
+
+    /// Command iTerm2 runs in its new window
+    ///
+    /// iTerm2 splits the command into words itself instead of running it in
+    /// a shell, so it's wrapped in `sh -c` for the `cd`, `env` and exit
+    /// marker parts to work.
+    fn iterm_command(&self, hx: &str, file: &str) -> String {
+        let command = format!(
+            "{}{}{}{} {}{}",
+            self.cd_command_prefix(),
+            self.env_command_prefix(),
+            shell_quote(hx),
+            self.editor_args_words(),
+            shell_quote(file),
+            self.exit_marker_suffix()
+        );
+        format!("/bin/sh -c {}", shell_quote(&command))
+    }
+
+    /// Start a CLI-launched terminal in the working directory, if one is set
+    fn apply_working_dir(&self, command: &mut Command) {
+        if let Some(ref dir) = self.working_dir {
+            command.current_dir(dir);
+        }
+    }
+
+    /// `env KEY='value' ...` prefix for shell commands, empty if there's no env
+    fn env_command_prefix(&self) -> String {
+        if self.env.is_empty() {
+            return String::new();
+        }
+        let vars: Vec<String> = self
+            .env
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, shell_quote(value)))
+            .collect();
+        format!("env {} ", vars.join(" "))
+    }
+}
+
+/// How long the command run by `Terminal::probe_waits` takes
+const PROBE_COMMAND_DURATION: Duration = Duration::from_secs(2);
+
+/// How long `Terminal::probe_waits` waits for the terminal to exit
+const PROBE_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Smallest window size (columns, lines) the editor is opened with
+pub const MIN_WINDOW_SIZE: (u32, u32) = (20, 5);
+
+/// Largest window size (columns, lines) the editor is opened with
+pub const MAX_WINDOW_SIZE: (u32, u32) = (400, 150);
+
+/// Bring a window size into the range terminals can open
+pub fn clamp_window_size(width: u32, height: u32) -> (u32, u32) {
+    (
+        width.clamp(MIN_WINDOW_SIZE.0, MAX_WINDOW_SIZE.0),
+        height.clamp(MIN_WINDOW_SIZE.1, MAX_WINDOW_SIZE.1),
+    )
+}
+
+/// Default smallest size (columns, lines) of an automatically sized window
+pub const AUTO_SIZE_MIN: (u32, u32) = (40, 10);
+
+/// Lines Helix takes up besides the text (status line and command line)
+const EDITOR_CHROME_LINES: u32 = 2;
+
+/// Window size (columns, lines) to open `text` with
+///
+/// With `auto_size`, the window fits the text (plus the gutters and status
+/// line) within `min_*`/`max_*`, where the maximum defaults to the configured
+/// width and height. Otherwise the configured size is used.
+pub fn window_size_for(text: &str, terminal: &TerminalConfig) -> (u32, u32) {
+    if !terminal.auto_size {
+        return (terminal.width, terminal.height);
+    }
+
+    let longest_line = text.lines().map(|line| line.chars().count()).max().unwrap_or(0);
+    let lines = text.lines().count().max(1);
+    let content_width = u32::try_from(longest_line)
+        .unwrap_or(u32::MAX)
+        .saturating_add(GUTTER_WIDTH + 1);
+    let content_height = u32::try_from(lines)
+        .unwrap_or(u32::MAX)
+        .saturating_add(EDITOR_CHROME_LINES);
+
+    let max_width = terminal.max_width.unwrap_or(terminal.width);
+    let max_height = terminal.max_height.unwrap_or(terminal.height);
+    // A minimum above the maximum gives way to the maximum
+    let min_width = terminal.min_width.unwrap_or(AUTO_SIZE_MIN.0).min(max_width);
+    let min_height = terminal.min_height.unwrap_or(AUTO_SIZE_MIN.1).min(max_height);
+    (
+        content_width.clamp(min_width, max_width),
+        content_height.clamp(min_height, max_height),
+    )
+}
+
+/// Title of the edit window for text from `source_app`, edited since `time`
+pub fn window_title(source_app: Option<&str>, time: &str) -> String {
+    match source_app.map(str::trim).filter(|name| !name.is_empty()) {
+        Some(name) => format!("helix-anywhere: {} ({})", name, time),
+        None => format!("helix-anywhere ({})", time),
+    }
+}
+
+/// Quote a string for use as a single POSIX shell word
+pub fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// Escape a string for embedding in an AppleScript string literal
+pub fn applescript_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// A terminal being launched, with the files its launch needs
+pub struct LaunchedTerminal {
+    /// The terminal, or the `open`/`osascript` process launching it
+    pub child: Child,
+    /// Ghostty's script, deleted when dropped; keep it until the editor is done
+    pub script: Option<TempPath>,
+}
+
+impl LaunchedTerminal {
+    fn new(child: Child) -> Self {
+        LaunchedTerminal { child, script: None }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Terminal {
+    Ghostty,
+    WezTerm,
+    Kitty,
+    Alacritty,
+    ITerm,
+    TerminalApp,
+}
+
+impl Terminal {
+    /// Parse terminal name from string
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "ghostty" => Some(Terminal::Ghostty),
+            "wezterm" => Some(Terminal::WezTerm),
+            "kitty" => Some(Terminal::Kitty),
+            "alacritty" => Some(Terminal::Alacritty),
+            "iterm" | "iterm2" => Some(Terminal::ITerm),
+            "terminal" | "terminal.app" => Some(Terminal::TerminalApp),
+            _ => None,
+        }
+    }
+
+    /// Get all supported terminals (shown in menu)
+    pub fn all() -> Vec<Terminal> {
+        vec![
+            Terminal::Ghostty,
+            Terminal::WezTerm,
+            Terminal::Kitty,
+            Terminal::Alacritty,
+            Terminal::ITerm,
+            Terminal::TerminalApp,
+        ]
+    }
+
+    /// Get display name for the terminal
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Terminal::Ghostty => "Ghostty",
+            Terminal::WezTerm => "WezTerm",
+            Terminal::Kitty => "Kitty",
+            Terminal::Alacritty => "Alacritty",
+            Terminal::ITerm => "iTerm2",
+            Terminal::TerminalApp => "Terminal.app",
+        }
+    }
+
+    /// Get the config name for the terminal
+    pub fn config_name(&self) -> &'static str {
+        match self {
+            Terminal::Ghostty => "ghostty",
+            Terminal::WezTerm => "wezterm",
+            Terminal::Kitty => "kitty",
+            Terminal::Alacritty => "alacritty",
+            Terminal::ITerm => "iterm",
+            Terminal::TerminalApp => "terminal",
+        }
+    }
+
+    /// Check if the terminal is installed
+    pub fn is_installed(&self) -> bool {
+        match self {
+            Terminal::Ghostty => Path::new("/Applications/Ghostty.app").exists(),
+            Terminal::WezTerm => Path::new("/Applications/WezTerm.app").exists(),
+            Terminal::Kitty => Path::new("/Applications/kitty.app").exists(),
+            Terminal::Alacritty => Path::new("/Applications/Alacritty.app").exists(),
+            Terminal::ITerm => Path::new("/Applications/iTerm.app").exists(),
+            Terminal::TerminalApp => Path::new("/System/Applications/Utilities/Terminal.app").exists(),
+        }
+    }
+
+    /// Web page to download the terminal from (Terminal.app ships with macOS)
+    pub fn install_url(&self) -> Option<&'static str> {
+        match self {
+            Terminal::Ghostty => Some("https://ghostty.org"),
+            Terminal::WezTerm => Some("https://wezterm.org"),
+            Terminal::Kitty => Some("https://sw.kovidgoyal.net/kitty/"),
+            Terminal::Alacritty => Some("https://alacritty.org"),
+            Terminal::ITerm => Some("https://iterm2.com"),
+            Terminal::TerminalApp => None,
+        }
+    }
+
+    /// Homebrew cask that installs the terminal
+    pub fn brew_cask(&self) -> Option<&'static str> {
+        match self {
+            Terminal::Ghostty => Some("ghostty"),
+            Terminal::WezTerm => Some("wezterm"),
+            Terminal::Kitty => Some("kitty"),
+            Terminal::Alacritty => Some("alacritty"),
+            Terminal::ITerm => Some("iterm2"),
+            Terminal::TerminalApp => None,
+        }
+    }
+
+    /// Check if this terminal requires file polling to detect completion
+    /// (Some terminals launched via `open` can't be waited on directly)
+    pub fn needs_polling(&self) -> bool {
+        matches!(self, Terminal::Ghostty | Terminal::ITerm | Terminal::TerminalApp)
+    }
+
+    /// Check if launching this terminal blocks until its window is closed
+    ///
+    /// Opens a window running `sleep` for a moment. A launcher that exits
+    /// well before the command is done has handed the window off and can't be
+    /// waited on. One still running after `PROBE_TIMEOUT` keeps its window
+    /// open after the command (so it blocks), and is closed.
+    pub fn probe_waits(&self, options: &LaunchOptions) -> Result<bool> {
+        let options = LaunchOptions {
+            size_report: None,
+            exit_marker: None,
+            editors: vec!["/bin/sleep".to_string()],
+            editor_args: Vec::new(),
+            helix_config: None,
+            window_mode: WindowMode::Normal,
+            window_title: None,
+            ..options.clone()
+        };
+        let seconds = PROBE_COMMAND_DURATION.as_secs().to_string();
+
+        let start = Instant::now();
+        let LaunchedTerminal { mut child, script } = self.launch(Path::new(&seconds), &options)?;
+        loop {
+            if child.try_wait()?.is_some() {
+                break;
+            }
+            if start.elapsed() >= PROBE_TIMEOUT {
+                let _ = child.kill();
+                let _ = child.wait();
+                break;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+
+        let waits = start.elapsed() >= PROBE_COMMAND_DURATION;
+        // A launcher that returned right away still needs Ghostty's script
+        if script.is_some() {
+            thread::sleep(PROBE_COMMAND_DURATION.saturating_sub(start.elapsed()));
+        }
+        log::info!(
+            "{} {} until its window closes (launcher ran {:?})",
+            self.display_name(),
+            if waits { "blocks" } else { "doesn't block" },
+            start.elapsed()
+        );
+        Ok(waits)
+    }
+
+    /// Command line arguments opening the window in `mode`
+    ///
+    /// Empty for a normal window, and for terminals that have no such option
+    /// (WezTerm's `start` doesn't, and the AppleScript-launched ones use
+    /// `window_mode_script` instead).
+    fn window_mode_args(&self, mode: WindowMode) -> Vec<&'static str> {
+        match (self, mode) {
+            (_, WindowMode::Normal) => Vec::new(),
+            (Terminal::Ghostty, WindowMode::Maximized) => vec!["--maximize=true"],
+            (Terminal::Ghostty, WindowMode::Fullscreen) => vec!["--fullscreen=true"],
+            (Terminal::Kitty, WindowMode::Maximized) => vec!["--start-as=maximized"],
+            (Terminal::Kitty, WindowMode::Fullscreen) => vec!["--start-as=fullscreen"],
+            (Terminal::Alacritty, WindowMode::Maximized) => {
+                vec!["-o", "window.startup_mode=\"Maximized\""]
+            }
+            (Terminal::Alacritty, WindowMode::Fullscreen) => {
+                vec!["-o", "window.startup_mode=\"Fullscreen\""]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Command line arguments setting the window title
+    ///
+    /// Empty without a title, and for terminals that have no such option
+    /// (WezTerm's title is set with an escape sequence by the editor command,
+    /// and the AppleScript-launched ones use `window_title_script`).
+    fn window_title_args(&self, title: Option<&str>) -> Vec<String> {
+        match (self, title) {
+            (_, None) => Vec::new(),
+            (Terminal::Ghostty, Some(title)) => vec![format!("--title={}", title)],
+            (Terminal::Kitty | Terminal::Alacritty, Some(title)) => {
+                vec!["--title".to_string(), title.to_string()]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// AppleScript titling the window just created by the launch script,
+    /// `newWindow` (iTerm2) or `newTab` (Terminal.app)
+    fn window_title_script(&self, title: Option<&str>) -> String {
+        let Some(title) = title else {
+            return String::new();
+        };
+        match self {
+            Terminal::ITerm => format!(
+                "tell current session of newWindow to set name to \"{}\"",
+                applescript_escape(title)
+            ),
+            Terminal::TerminalApp => {
+                format!("set custom title of newTab to \"{}\"", applescript_escape(title))
+            }
+            _ => String::new(),
+        }
+    }
+
+    /// AppleScript zooming or fullscreening the front window of the
+    /// AppleScript-launched terminals after it's been created
+    fn window_mode_script(&self, mode: WindowMode) -> String {
+        let (app, process) = match self {
+            Terminal::ITerm => ("iTerm", "iTerm2"),
+            Terminal::TerminalApp => ("Terminal", "Terminal"),
+            _ => return String::new(),
+        };
+        match mode {
+            WindowMode::Normal => String::new(),
+            WindowMode::Maximized => {
+                format!("tell application \"{}\" to set zoomed of front window to true", app)
+            }
+            WindowMode::Fullscreen => format!(
+                "tell application \"System Events\" to set value of attribute \"AXFullScreen\" of front window of process \"{}\" to true",
+                process
+            ),
+        }
+    }
+
+    /// Check if the terminal opens its window at the configured width and height
+    ///
+    /// Ghostty, WezTerm and the AppleScript-launched terminals use their own
+    /// default size.
+    pub fn supports_window_size(&self) -> bool {
+        matches!(self, Terminal::Kitty | Terminal::Alacritty)
+    }
+
+    /// Check if the terminal can report its window size when the editor exits
+    pub fn supports_size_report(&self) -> bool {
+        matches!(self, Terminal::WezTerm | Terminal::Kitty | Terminal::Alacritty)
+    }
+
+    /// Launch the terminal with the editor editing the given file (or directory)
+    ///
+    /// Stderr is piped so launch failures can be reported with the terminal's
+    /// (or launcher's) own error message.
+    ///
+    /// Environment variables from `options` are set on the spawned process for
+    /// CLI-launched terminals, exported in the generated Ghostty script, and
+    /// passed through `env` in the AppleScript-launched terminals' commands.
+    /// The working directory is applied the same way, with `cd` in scripts.
+    pub fn launch(&self, file_path: &Path, options: &LaunchOptions) -> Result<LaunchedTerminal> {
+        let file_str = file_path.to_string_lossy();
+        let (width, height) = (options.width, options.height);
+
+        // Find the editor binary (full path needed when running from .app bundle)
+        let editor_path = resolve_editor(&options.editors)?;
+        let editor_str = editor_path.to_string_lossy();
+
+        if *self == Terminal::WezTerm && options.window_mode != WindowMode::Normal {
+            log::info!(
+                "WezTerm can't be asked for a {:?} window from the command line, opening it normally",
+                options.window_mode
+            );
+        }
+
+        match self {
+            Terminal::Ghostty => {
+                // On macOS, Ghostty doesn't support -e properly via `open --args`
+                // Create a temporary shell script and tell Ghostty to run it
+                let script_path = options.write_ghostty_script(&editor_str, &file_str)?;
+
+                // Launch Ghostty with the script
+                let child = Command::new("open")
+                    .stderr(Stdio::piped())
+                    .arg("-na")
+                    .arg("/Applications/Ghostty.app")
+                    .arg("--args")
+                    .args(self.window_mode_args(options.window_mode))
+                    .args(self.window_title_args(options.window_title.as_deref()))
+                    .arg("-e")
+                    .arg(script_path.to_string_lossy().as_ref())
+                    .spawn()
+                    .map_err(|e| anyhow::anyhow!("Failed to launch Ghostty: {}", e))?;
+                Ok(LaunchedTerminal { child, script: Some(script_path) })
+            }
+            Terminal::WezTerm => {
+                // Use the CLI from within the .app bundle
+                let wezterm_cli = "/Applications/WezTerm.app/Contents/MacOS/wezterm";
+
+                // --always-new-process ensures we can wait for it to finish
+                let mut command = Command::new(wezterm_cli);
+                options.apply_working_dir(&mut command);
+                let child = command
+                    .envs(options.env.iter().cloned())
+                    .stderr(Stdio::piped())
+                    .arg("start")
+                    .arg("--always-new-process")
+                    .arg("--")
+                    .args(options.editor_command(&editor_str, &file_str, true))
+                    .spawn()
+                    .map_err(|e| anyhow::anyhow!("Failed to launch WezTerm: {}", e))?;
+
+                // Bring WezTerm to front using AppleScript
+                std::thread::sleep(std::time::Duration::from_millis(200));
+                let _ = Command::new("osascript")
+                    .arg("-e")
+                    .arg("tell application \"WezTerm\" to activate")
+                    .spawn();
+
+                Ok(LaunchedTerminal { child, script: None })
+            }
+            Terminal::Kitty => {
+                // Use the CLI from within the .app bundle
+                let kitty_cli = "/Applications/kitty.app/Contents/MacOS/kitty";
+
+                let mut command = Command::new(kitty_cli);
+                options.apply_working_dir(&mut command);
+                command
+                    .envs(options.env.iter().cloned())
+                    .stderr(Stdio::piped())
+                    .arg("--override")
+                    .arg(format!("initial_window_width={}c", width))
+                    .arg("--override")
+                    .arg(format!("initial_window_height={}c", height))
+                    .args(self.window_mode_args(options.window_mode))
+                    .args(self.window_title_args(options.window_title.as_deref()))
+                    .args(options.editor_command(&editor_str, &file_str, false))
+                    .spawn()
+                    .map_err(|e| anyhow::anyhow!("Failed to launch Kitty: {}", e))
+                    .map(LaunchedTerminal::new)
+            }
+            Terminal::Alacritty => {
+                // Use the CLI from within the .app bundle
+                let alacritty_cli = "/Applications/Alacritty.app/Contents/MacOS/alacritty";
+
+                let mut command = Command::new(alacritty_cli);
+                options.apply_working_dir(&mut command);
+                command
+                    .envs(options.env.iter().cloned())
+                    .stderr(Stdio::piped())
+                    .arg("-o")
+                    .arg(format!("window.dimensions.columns={}", width))
+                    .arg("-o")
+                    .arg(format!("window.dimensions.lines={}", height))
+                    .args(self.window_mode_args(options.window_mode))
+                    .args(self.window_title_args(options.window_title.as_deref()))
+                    .arg("-e")
+                    .args(options.editor_command(&editor_str, &file_str, false))
+                    .spawn()
+                    .map_err(|e| anyhow::anyhow!("Failed to launch Alacritty: {}", e))
+                    .map(LaunchedTerminal::new)
+            }
+            Terminal::ITerm => {
+                // Use AppleScript to launch iTerm with full path to hx
+                let command = options.iterm_command(&editor_str, &file_str);
+                let script = format!(
+                    r#"
+                    tell application "iTerm"
+                        activate
+                        set newWindow to (create window with default profile command "{}")
+                        {}
+                    end tell
+                    {}
+                    "#,
+                    applescript_escape(&command),
+                    self.window_title_script(options.window_title.as_deref()),
+                    self.window_mode_script(options.window_mode)
+                );
+                Command::new("osascript")
+                    .stderr(Stdio::piped())
+                    .arg("-e")
+                    .arg(&script)
+                    .spawn()
+                    .map_err(|e| anyhow::anyhow!("Failed to launch iTerm: {}", e))
+                    .map(LaunchedTerminal::new)
+            }
+            Terminal::TerminalApp => {
+                // Use AppleScript to launch Terminal.app with full path to hx
+                let command = format!(
+                    "{}{}{}{} {}{}; exit",
+                    options.cd_command_prefix(),
+                    options.env_command_prefix(),
+                    shell_quote(&editor_str),
+                    options.editor_args_words(),
+                    shell_quote(&file_str),
+                    options.exit_marker_suffix()
+                );
+                let script = format!(
+                    r#"
+                    tell application "Terminal"
+                        activate
+                        set newTab to do script "{}"
+                        {}
+                    end tell
+                    {}
+                    "#,
+                    applescript_escape(&command),
+                    self.window_title_script(options.window_title.as_deref()),
+                    self.window_mode_script(options.window_mode)
+                );
+                Command::new("osascript")
+                    .stderr(Stdio::piped())
+                    .arg("-e")
+                    .arg(&script)
+                    .spawn()
+                    .map_err(|e| anyhow::anyhow!("Failed to launch Terminal.app: {}", e))
+                    .map(LaunchedTerminal::new)
+            }
+        }
+    }
+}
+
+/// Wait for the `open`/`osascript` process launching a polled terminal to finish
+///
+/// These exit as soon as the terminal has been asked to open, so one that
+/// runs past `timeout` is stuck (e.g. the terminal is mid-launch or showing a
+/// prompt) and is killed.
+pub fn wait_for_launcher(child: &mut Child, timeout: Duration) -> Result<()> {
+    const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if start.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            bail!("Terminal didn't launch within {:?}", timeout);
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    if !status.success() {
+        let mut stderr = String::new();
+        if let Some(mut pipe) = child.stderr.take() {
+            let _ = pipe.read_to_string(&mut stderr);
+        }
+        bail!("Terminal launch failed ({}): {}", status, stderr.trim());
+    }
+    Ok(())
+}
+
+/// Read a window size written by `stty size`, as (columns, lines)
+pub fn read_size_report(path: &Path) -> Option<(u32, u32)> {
+    let content = std::fs::read_to_string(path).ok()?;
+    let mut parts = content.split_whitespace();
+    let lines = parts.next()?.parse().ok()?;
+    let columns = parts.next()?.parse().ok()?;
+    // Anything outside the launchable range is a bogus report, not a resize
+    (clamp_window_size(columns, lines) == (columns, lines)).then_some((columns, lines))
+}
+
+/// Check if an editor binary is Helix (`hx`, or `helix` as some packages name it)
+pub fn is_helix(editor: &Path) -> bool {
+    editor.file_name().is_some_and(|name| name == "hx" || name == "helix")
+}
+
+/// Find the helix editor binary in common locations
+pub fn find_helix() -> Option<std::path::PathBuf> {
+    find_editor("hx")
+}
+
+/// Pick the first of the configured editors that is installed
+///
+/// Candidates are binary names (looked up like `hx`) or absolute paths.
+/// Without candidates, Helix is used.
+pub fn resolve_editor(candidates: &[String]) -> Result<PathBuf> {
+    if candidates.is_empty() {
+        return find_helix().ok_or_else(|| {
+            anyhow::anyhow!("Helix editor (hx) not found. Install with: brew install helix")
+        });
+    }
+
+    for candidate in candidates {
+        if let Some(path) = find_editor(candidate) {
+            log::info!("Using editor {:?}", path);
+            return Ok(path);
+        }
+        log::debug!("Editor {} not found, trying the next one", candidate);
+    }
+    bail!("None of the configured editors were found: {}", candidates.join(", "))
+}
+
+/// Find an editor binary by name (or check an absolute path)
+pub fn find_editor(name: &str) -> Option<PathBuf> {
+    let path = Path::new(name);
+    if path.is_absolute() {
+        return path.is_file().then(|| path.to_path_buf());
+    }
+
+    let common_paths = [
+        format!("/opt/homebrew/bin/{}", name),  // Homebrew on Apple Silicon
+        format!("/usr/local/bin/{}", name),     // Homebrew on Intel
+        format!("{}/.cargo/bin/{}", std::env::var("HOME").unwrap_or_default(), name), // Cargo install
+        format!("/usr/bin/{}", name),           // System install
+    ];
+
+    for path in &common_paths {
+        let p = std::path::PathBuf::from(path);
+        if p.exists() {
+            return Some(p);
+        }
+    }
+
+    // Fallback: try PATH (works when run from terminal)
+    std::env::var_os("PATH").and_then(|paths| {
+        std::env::split_paths(&paths)
+            .filter_map(|dir| {
+                let full_path = dir.join(name);
+                if full_path.is_file() {
+                    Some(full_path)
+                } else {
+                    None
+                }
+            })
+            .next()
+    })
+}
+
+/// Get list of installed terminals
+#[allow(dead_code)]
+pub fn get_installed_terminals() -> Vec<Terminal> {
+    Terminal::all()
+        .into_iter()
+        .filter(|t| t.is_installed())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn editor_args_go_before_the_file() {
+        let mut options = LaunchOptions {
+            editor_args: vec!["-c".to_string(), "/tmp/prose config.toml".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            options.editor_command("hx", "/tmp/a.md", false),
+            ["hx", "-c", "/tmp/prose config.toml", "/tmp/a.md"]
+        );
+        assert_eq!(options.editor_args_words(), " '-c' '/tmp/prose config.toml'");
+
+        options.size_report = Some(PathBuf::from("/tmp/a.size"));
+        let command = options.editor_command("hx", "/tmp/a.md", false);
+        assert_eq!(command[3..], ["hx", "/tmp/a.size", "-c", "/tmp/prose config.toml", "/tmp/a.md"]);
+
+        // Polled CLI terminals create the exit marker themselves
+        options.size_report = None;
+        options.exit_marker = Some(PathBuf::from("/tmp/a.done"));
+        let command = options.editor_command("hx", "/tmp/a.md", false);
+        assert_eq!(command[2], r#""$0" "$@"; touch '/tmp/a.done'"#);
+        assert_eq!(command[3..], ["hx", "-c", "/tmp/prose config.toml", "/tmp/a.md"]);
+    }
+
+    #[test]
+    fn helix_config_goes_first_and_survives_quoting() {
+        let options = LaunchOptions {
+            helix_config: Some(PathBuf::from("/Users/me/My \"quick\" edits/config.toml")),
+            editor_args: vec!["--log".to_string(), "/tmp/hx log".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            options.editor_command("hx", "/tmp/a.md", false),
+            ["hx", "-c", "/Users/me/My \"quick\" edits/config.toml", "--log", "/tmp/hx log", "/tmp/a.md"]
+        );
+
+        // AppleScript terminals get the shell words inside an AppleScript string
+        let words = options.editor_args_words();
+        assert_eq!(words, r#" '-c' '/Users/me/My "quick" edits/config.toml' '--log' '/tmp/hx log'"#);
+        assert_eq!(
+            applescript_escape(&words),
+            r#" '-c' '/Users/me/My \"quick\" edits/config.toml' '--log' '/tmp/hx log'"#
+        );
+        assert!(options.ghostty_script("hx", "/tmp/a.md").contains(&words));
+
+        assert!(is_helix(Path::new("/opt/homebrew/bin/hx")));
+        assert!(is_helix(Path::new("/usr/bin/helix")));
+        assert!(!is_helix(Path::new("/usr/bin/vim")));
+    }
+
+    #[test]
+    fn launch_args_stay_bounded_for_a_very_long_line() {
+        let line = "x".repeat(50_000);
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, line.as_bytes()).unwrap();
+        let file_str = file.path().to_string_lossy().to_string();
+
+        // As if the window had been sized after the line
+        let mut config = Config::default();
+        config.terminal.width = line.len() as u32;
+        config.terminal.height = u32::MAX;
+        let options = LaunchOptions {
+            size_report: Some(PathBuf::from("/tmp/a.size")),
+            exit_marker: Some(PathBuf::from("/tmp/a.done")),
+            ..LaunchOptions::from_config(&config)
+        };
+        assert_eq!((options.width, options.height), MAX_WINDOW_SIZE);
+
+        // The text only ever travels through the file
+        let command = options.editor_command("hx", &file_str, false);
+        assert!(command.iter().all(|arg| arg.len() < 256));
+        assert!(options.ghostty_script("hx", &file_str).len() < 1024);
+    }
+
+    #[test]
+    fn auto_size_fits_the_text_within_bounds() {
+        let mut terminal = Config::default().terminal;
+        terminal.auto_size = true;
+        terminal.max_width = Some(120);
+        terminal.max_height = Some(40);
+
+        // Tiny selections get the minimum size
+        assert_eq!(window_size_for("hi", &terminal), AUTO_SIZE_MIN);
+
+        // In between, the window fits the text
+        let text = format!("{}\n", "x".repeat(60)).repeat(20);
+        assert_eq!(window_size_for(&text, &terminal), (60 + GUTTER_WIDTH + 1, 22));
+
+        // Huge selections are capped
+        let text = format!("{}\n", "x".repeat(1000)).repeat(500);
+        assert_eq!(window_size_for(&text, &terminal), (120, 40));
+
+        // Without a maximum, the configured size caps it
+        terminal.max_width = None;
+        terminal.max_height = None;
+        assert_eq!(window_size_for(&text, &terminal), (terminal.width, terminal.height));
+
+        terminal.min_width = Some(90);
+        terminal.min_height = Some(20);
+        assert_eq!(window_size_for("hi", &terminal), (90, 20));
+    }
+
+    #[test]
+    fn without_auto_size_the_configured_size_is_used() {
+        let terminal = Config::default().terminal;
+        let text = format!("{}\n", "x".repeat(1000)).repeat(500);
+        assert_eq!(window_size_for(&text, &terminal), (100, 30));
+        assert_eq!(window_size_for("", &terminal), (100, 30));
+    }
+
+    #[test]
+    fn every_terminal_round_trips_through_its_config_name() {
+        assert_eq!(Terminal::all().len(), 6);
+        for terminal in Terminal::all() {
+            assert_eq!(Terminal::from_name(terminal.config_name()), Some(terminal));
+            assert_eq!(Terminal::from_name(&terminal.display_name().to_lowercase()), Some(terminal));
+            // Only the terminals launched through `open` or `osascript` can't be waited on
+            let launched_indirectly = matches!(
+                terminal,
+                Terminal::Ghostty | Terminal::ITerm | Terminal::TerminalApp
+            );
+            assert_eq!(terminal.needs_polling(), launched_indirectly, "{:?}", terminal);
+            // Terminals sized by their launch options can report the size back too
+            assert!(!terminal.supports_window_size() || terminal.supports_size_report());
+        }
+    }
+
+    #[test]
+    fn window_modes_are_passed_where_supported() {
+        assert!(Terminal::Kitty.window_mode_args(WindowMode::Normal).is_empty());
+        assert_eq!(Terminal::Kitty.window_mode_args(WindowMode::Fullscreen), ["--start-as=fullscreen"]);
+        assert_eq!(
+            Terminal::Alacritty.window_mode_args(WindowMode::Maximized),
+            ["-o", r#"window.startup_mode="Maximized""#]
+        );
+        assert!(Terminal::WezTerm.window_mode_args(WindowMode::Fullscreen).is_empty());
+
+        assert_eq!(Terminal::Kitty.window_mode_script(WindowMode::Maximized), "");
+        assert!(Terminal::TerminalApp
+            .window_mode_script(WindowMode::Maximized)
+            .contains("set zoomed of front window"));
+        assert!(Terminal::ITerm
+            .window_mode_script(WindowMode::Fullscreen)
+            .contains(r#"of process "iTerm2""#));
+    }
+
+    #[test]
+    fn window_titles_are_passed_where_supported() {
+        assert_eq!(window_title(Some("Mail"), "14:32"), "helix-anywhere: Mail (14:32)");
+        assert_eq!(window_title(Some(" "), "14:32"), "helix-anywhere (14:32)");
+
+        let title = Some("helix-anywhere: Mail (14:32)");
+        assert!(Terminal::Kitty.window_title_args(None).is_empty());
+        assert_eq!(Terminal::Kitty.window_title_args(title), ["--title", "helix-anywhere: Mail (14:32)"]);
+        assert_eq!(Terminal::Ghostty.window_title_args(title), ["--title=helix-anywhere: Mail (14:32)"]);
+        assert!(Terminal::WezTerm.window_title_args(title).is_empty());
+        assert_eq!(
+            Terminal::TerminalApp.window_title_script(Some(r#"a "b""#)),
+            r#"set custom title of newTab to "a \"b\"""#
+        );
+
+        // WezTerm gets the title through an escape sequence instead
+        let options = LaunchOptions {
+            window_title: title.map(str::to_string),
+            ..Default::default()
+        };
+        assert_eq!(options.editor_command("hx", "/tmp/a.md", false), ["hx", "/tmp/a.md"]);
+        let command = options.editor_command("hx", "/tmp/a.md", true);
+        assert_eq!(
+            command[2],
+            r#"printf '\033]2;%s\007' 'helix-anywhere: Mail (14:32)'; "$0" "$@""#
+        );
+        assert_eq!(command[3..], ["hx", "/tmp/a.md"]);
+    }
+
+    #[test]
+    fn ghostty_script_is_deleted_when_dropped() {
+        let script = LaunchOptions::default()
+            .write_ghostty_script("/opt/hx", "/tmp/a.md")
+            .unwrap();
+        let path = script.to_path_buf();
+        assert!(std::fs::read_to_string(&path).unwrap().starts_with("#!/bin/bash\n"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o755);
+        }
+
+        drop(script);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn iterm_runs_the_command_through_sh() {
+        let options = LaunchOptions {
+            working_dir: Some(PathBuf::from("/tmp/project")),
+            exit_marker: Some(PathBuf::from("/tmp/a.done")),
+            ..Default::default()
+        };
+        assert_eq!(
+            options.iterm_command("/opt/hx", "/tmp/a.md"),
+            r#"/bin/sh -c 'cd '\''/tmp/project'\'' && '\''/opt/hx'\'' '\''/tmp/a.md'\''; touch '\''/tmp/a.done'\'''"#
+        );
+    }
+
+    #[test]
+    fn ghostty_script_quotes_paths() {
+        let options = LaunchOptions::default();
+        let script = options.ghostty_script("/opt/hx", "/tmp/it's \"$HOME\".md");
+        assert_eq!(script, "#!/bin/bash\n'/opt/hx' '/tmp/it'\\''s \"$HOME\".md'\n");
+    }
+}
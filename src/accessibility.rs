@@ -0,0 +1,100 @@
+//! Reading and replacing the selection through the Accessibility API
+//!
+//! Used instead of simulated copy/paste for the apps listed in `ax_apps`
+//! when `capture_mode = "ax"`. It leaves the clipboard alone and doesn't
+//! depend on the app's copy/paste shortcuts, but only works with text views
+//! that expose their selection (`AXSelectedText`) as settable.
+
+use anyhow::{bail, Result};
+use core_foundation::base::{CFType, CFTypeRef, TCFType};
+use core_foundation::string::{CFString, CFStringRef};
+use std::ffi::c_void;
+use std::ptr;
+
+type AXUIElementRef = *const c_void;
+type AXError = i32;
+
+const AX_ERROR_SUCCESS: AXError = 0;
+
+#[link(name = "ApplicationServices", kind = "framework")]
+extern "C" {
+    fn AXUIElementCreateSystemWide() -> AXUIElementRef;
+    fn AXUIElementCopyAttributeValue(
+        element: AXUIElementRef,
+        attribute: CFStringRef,
+        value: *mut CFTypeRef,
+    ) -> AXError;
+    fn AXUIElementSetAttributeValue(
+        element: AXUIElementRef,
+        attribute: CFStringRef,
+        value: CFTypeRef,
+    ) -> AXError;
+    fn AXUIElementIsAttributeSettable(
+        element: AXUIElementRef,
+        attribute: CFStringRef,
+        settable: *mut u8,
+    ) -> AXError;
+}
+
+/// The UI element that has keyboard focus, e.g. a text view
+pub struct FocusedElement(CFType);
+
+impl FocusedElement {
+    /// The element focused system-wide, if the app exposes one
+    pub fn current() -> Option<Self> {
+        let system = unsafe { CFType::wrap_under_create_rule(AXUIElementCreateSystemWide()) };
+        copy_attribute(element_ref(&system), "AXFocusedUIElement").map(FocusedElement)
+    }
+
+    /// The selected text, `None` if the element has no text selection
+    pub fn selected_text(&self) -> Option<String> {
+        copy_attribute(element_ref(&self.0), "AXSelectedText")?
+            .downcast::<CFString>()
+            .map(|text| text.to_string())
+    }
+
+    /// Replace the selection with `text`
+    pub fn replace_selection(&self, text: &str) -> Result<()> {
+        let element = element_ref(&self.0);
+        let attribute = CFString::from_static_string("AXSelectedText");
+
+        let mut settable = 0u8;
+        let error = unsafe {
+            AXUIElementIsAttributeSettable(element, attribute.as_concrete_TypeRef(), &mut settable)
+        };
+        if error != AX_ERROR_SUCCESS || settable == 0 {
+            bail!("The focused element's selection can't be replaced (AXError {})", error);
+        }
+
+        let value = CFString::new(text);
+        let error = unsafe {
+            AXUIElementSetAttributeValue(
+                element,
+                attribute.as_concrete_TypeRef(),
+                value.as_CFTypeRef(),
+            )
+        };
+        if error != AX_ERROR_SUCCESS {
+            bail!("Failed to replace the selection (AXError {})", error);
+        }
+        Ok(())
+    }
+}
+
+fn element_ref(element: &CFType) -> AXUIElementRef {
+    element.as_CFTypeRef()
+}
+
+/// Copy an attribute's value, `None` if the element doesn't have it
+fn copy_attribute(element: AXUIElementRef, attribute: &'static str) -> Option<CFType> {
+    let attribute = CFString::from_static_string(attribute);
+    let mut value: CFTypeRef = ptr::null();
+    let error = unsafe {
+        AXUIElementCopyAttributeValue(element, attribute.as_concrete_TypeRef(), &mut value)
+    };
+    if error != AX_ERROR_SUCCESS || value.is_null() {
+        log::debug!("No {} attribute (AXError {})", attribute, error);
+        return None;
+    }
+    Some(unsafe { CFType::wrap_under_create_rule(value) })
+}
@@ -0,0 +1,239 @@
+//! Command line subcommands
+//!
+//! Without arguments the app starts normally in the menu bar. Subcommands do
+//! their job and exit without starting the app. `--config <path>` can come
+//! before any of them, or alone to start the app with that config file.
+
+use crate::clipboard::{ClipboardAccess, NamedPasteboard, SystemClipboard};
+use crate::config::Config;
+use crate::edit_session;
+use crate::stats::SessionOutcome;
+use crate::keys;
+use crate::terminal::LaunchOptions;
+use anyhow::{bail, Result};
+use serde_json::{json, Value};
+use std::io::{Read, Write};
+use std::path::Path;
+
+const USAGE: &str = "\
+Usage: helix-anywhere [--config <path>] [COMMAND]
+
+Options:
+  --config <path>        Use the config file at <path> instead of the default one
+  --version, -V          Print the version
+  --reset-config         Move the config file to config.toml.bak and write the defaults
+  --edit-once            Edit the selection once, without the menu bar, and exit
+
+Commands:
+  edit --file <path>     Open <path> in the configured terminal and wait until it's closed
+  edit --stdin           Edit the text read from stdin and print the result to stdout
+  edit --pasteboard <name>
+                         Edit the text on the named pasteboard and write the result back
+  config export <path>   Write the current config to <path>
+  config import <path>   Validate the config at <path> and make it the current config
+  config example         Print the default config, with every option
+  keys [--json]          List the key and modifier names that can be used in hotkeys
+
+Without a command, helix-anywhere starts in the menu bar.";
+
+/// Run the subcommand given on the command line, if any
+///
+/// Returns `None` when there is no subcommand and the app should start.
+pub fn run(args: &[String]) -> Option<Result<()>> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let args = match split_config_path(&args) {
+        Ok((Some(path), rest)) => match Config::set_config_path(path.into()) {
+            Ok(()) => rest,
+            Err(e) => return Some(Err(e)),
+        },
+        Ok((None, rest)) => rest,
+        Err(e) => return Some(Err(e)),
+    };
+    let result = match args.as_slice() {
+        [] => return None,
+        ["--version" | "-V"] => {
+            println!("helix-anywhere {}", env!("CARGO_PKG_VERSION"));
+            Ok(())
+        }
+        ["--reset-config"] => reset_config(),
+        ["--edit-once"] => edit_once(),
+        ["edit", "--file", path] => edit_file(Path::new(path)),
+        ["edit", "--stdin"] => edit_stdin(),
+        ["edit", "--pasteboard", name] => edit_pasteboard(name),
+        ["config", "export", path] => export_config(Path::new(path)),
+        ["config", "import", path] => import_config(Path::new(path)),
+        ["config", "example"] => Config::default().to_toml_string().map(|content| print!("{}", content)),
+        ["keys"] => {
+            print_keys();
+            Ok(())
+        }
+        ["keys", "--json"] => {
+            println!("{}", keys_json());
+            Ok(())
+        }
+        ["help" | "--help" | "-h"] => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => Err(anyhow::anyhow!("Invalid arguments\n\n{}", USAGE)),
+    };
+    Some(result)
+}
+
+/// Take `--config <path>` out of the arguments, wherever it is
+fn split_config_path<'a>(args: &[&'a str]) -> Result<(Option<&'a str>, Vec<&'a str>)> {
+    let mut path = None;
+    let mut rest = Vec::new();
+    let mut args = args.iter();
+    while let Some(&arg) = args.next() {
+        if let Some(value) = arg.strip_prefix("--config=") {
+            path = Some(value);
+        } else if arg == "--config" {
+            let value = args.next().ok_or_else(|| anyhow::anyhow!("--config needs a path\n\n{}", USAGE))?;
+            path = Some(*value);
+        } else {
+            rest.push(arg);
+        }
+    }
+    Ok((path, rest))
+}
+
+/// Write the default config, keeping the old file as a backup
+fn reset_config() -> Result<()> {
+    let backup = Config::reset()?;
+    if let Some(path) = Config::config_path() {
+        println!("Default config written to {}", path.display());
+    }
+    if let Some(backup) = backup {
+        println!("The previous config was moved to {}", backup.display());
+    }
+    println!("Restart helix-anywhere (Quit from the menu bar, then open it again) to apply it.");
+    Ok(())
+}
+
+/// Edit the selection of the frontmost app once, like the hotkey, for other launchers
+///
+/// Fails (exiting non-zero) unless the edited text was pasted back, left
+/// unchanged or collected.
+fn edit_once() -> Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
+    let config = Config::load()?;
+    let outcome = edit_session::run_edit_session(&config, &SystemClipboard, &[])?;
+    edit_session::mark_session_end();
+    match outcome {
+        SessionOutcome::Completed | SessionOutcome::Unchanged | SessionOutcome::Collected => Ok(()),
+        outcome => bail!("Edit not completed: {:?}", outcome),
+    }
+}
+
+/// Print the bindable keys and modifiers as a table
+fn print_keys() {
+    println!("Keys:");
+    for key in keys::supported_keys() {
+        println!("  {:<12} {:<4} {:#04X}", key.name, key.display, key.key_code);
+    }
+    println!("\nModifiers:");
+    for (name, symbol) in keys::supported_modifiers() {
+        println!("  {:<12} {}", name, symbol);
+    }
+}
+
+/// The bindable keys and modifiers as JSON, for scripts and documentation
+fn keys_json() -> Value {
+    let keys: Vec<Value> = keys::supported_keys()
+        .into_iter()
+        .map(|key| json!({"name": key.name, "key_code": key.key_code, "display": key.display}))
+        .collect();
+    let modifiers: Vec<Value> = keys::supported_modifiers()
+        .into_iter()
+        .map(|(name, symbol)| json!({"name": name, "display": symbol}))
+        .collect();
+    json!({"keys": keys, "modifiers": modifiers})
+}
+
+/// Open a file in the configured terminal and block until the user is done
+fn edit_file(path: &Path) -> Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
+    if !path.is_file() {
+        bail!("No such file: {}", path.display());
+    }
+
+    let config = Config::load()?;
+    let options = LaunchOptions::from_config(&config);
+    if let Some(error) = edit_session::open_and_wait(&config, path, options)? {
+        bail!(error);
+    }
+    Ok(())
+}
+
+/// Edit text from stdin and print the result, for Shortcuts and shell pipelines
+///
+/// Prints the original text if it was left unchanged, so the output can
+/// always be used as the result.
+fn edit_stdin() -> Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
+    let mut text = String::new();
+    std::io::stdin().read_to_string(&mut text)?;
+
+    let config = Config::load()?;
+    let edited = edit_session::edit_text(&config, &text, None)?;
+    print!("{}", edited.as_deref().unwrap_or(&text));
+    std::io::stdout().flush()?;
+    Ok(())
+}
+
+/// Edit the text on a named pasteboard in place, leaving the clipboard alone
+fn edit_pasteboard(name: &str) -> Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
+    let pasteboard = NamedPasteboard { name: name.to_string() };
+    let text = pasteboard.get_text()?;
+
+    let config = Config::load()?;
+    if let Some(edited) = edit_session::edit_text(&config, &text, None)? {
+        pasteboard.set_text(&edited)?;
+    }
+    Ok(())
+}
+
+/// Copy the current config to a file
+fn export_config(path: &Path) -> Result<()> {
+    let config = Config::load()?;
+    config.export(path)?;
+    println!("Config exported to {}", path.display());
+    Ok(())
+}
+
+/// Replace the current config with a validated one from a file
+fn import_config(path: &Path) -> Result<()> {
+    let config = match Config::import(path) {
+        Ok(config) => config,
+        Err(e) => bail!("Not importing {}: {:#}", path.display(), e),
+    };
+    config.save()?;
+    println!("Config imported from {}", path.display());
+    println!("Restart helix-anywhere (Quit from the menu bar, then open it again) to apply it.");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_path_is_taken_from_anywhere_in_the_arguments() {
+        assert_eq!(
+            split_config_path(&["--config", "/tmp/a.toml", "--edit-once"]).unwrap(),
+            (Some("/tmp/a.toml"), vec!["--edit-once"])
+        );
+        assert_eq!(
+            split_config_path(&["config", "export", "out.toml", "--config=/tmp/a.toml"]).unwrap(),
+            (Some("/tmp/a.toml"), vec!["config", "export", "out.toml"])
+        );
+        assert_eq!(split_config_path(&["keys"]).unwrap(), (None, vec!["keys"]));
+        assert!(split_config_path(&["--version", "--config"]).is_err());
+    }
+}
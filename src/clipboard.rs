@@ -1,20 +1,267 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use arboard::Clipboard;
+use cocoa::base::{id, nil, NO};
+use cocoa::foundation::{NSAutoreleasePool, NSString};
+use objc::{class, msg_send, sel, sel_impl};
+use std::thread;
+use std::time::{Duration, Instant};
 
-/// Get text from the clipboard
-pub fn get_text() -> Result<String> {
-    let mut clipboard = Clipboard::new()
-        .context("Failed to access clipboard")?;
+/// UTI of plain text on the pasteboard (NSPasteboardTypeString)
+const PASTEBOARD_TYPE_STRING: &str = "public.utf8-plain-text";
 
-    clipboard.get_text()
-        .context("Failed to read text from clipboard")
+/// Text access to a clipboard
+///
+/// The edit session takes this as a dependency so it can be driven by an
+/// in-memory clipboard in tests instead of the real pasteboard.
+pub trait ClipboardAccess {
+    /// Get text from the clipboard
+    fn get_text(&self) -> Result<String>;
+
+    /// Set text to the clipboard
+    fn set_text(&self, text: &str) -> Result<()>;
+
+    /// Get the HTML version of the clipboard's content, if it has one
+    fn get_html(&self) -> Option<String> {
+        None
+    }
+
+    /// Set HTML to the clipboard, with `alt_text` for apps that only take plain text
+    fn set_html(&self, _html: &str, alt_text: &str) -> Result<()> {
+        self.set_text(alt_text)
+    }
+}
+
+/// The system clipboard (general pasteboard), backed by arboard
+pub struct SystemClipboard;
+
+impl ClipboardAccess for SystemClipboard {
+    fn get_text(&self) -> Result<String> {
+        with_retries("read", || {
+            let mut clipboard = Clipboard::new()
+                .context("Failed to access clipboard")?;
+
+            clipboard.get_text()
+                .context("Failed to read text from clipboard")
+        })
+    }
+
+    fn set_text(&self, text: &str) -> Result<()> {
+        with_retries("write", || {
+            let mut clipboard = Clipboard::new()
+                .context("Failed to access clipboard")?;
+
+            clipboard.set_text(text.to_string())
+                .context("Failed to write text to clipboard")
+        })
+    }
+
+    fn get_html(&self) -> Option<String> {
+        let mut clipboard = Clipboard::new().ok()?;
+        clipboard.get().html().ok().filter(|html| !html.trim().is_empty())
+    }
+
+    fn set_html(&self, html: &str, alt_text: &str) -> Result<()> {
+        with_retries("write", || {
+            let mut clipboard = Clipboard::new()
+                .context("Failed to access clipboard")?;
+
+            clipboard.set_html(html, Some(alt_text))
+                .context("Failed to write HTML to clipboard")
+        })
+    }
 }
 
-/// Set text to the clipboard
-pub fn set_text(text: &str) -> Result<()> {
-    let mut clipboard = Clipboard::new()
-        .context("Failed to access clipboard")?;
+/// Retry a clipboard operation a few times
+///
+/// Access can fail transiently while another process (e.g. a clipboard
+/// manager) holds the pasteboard.
+fn with_retries<T>(operation: &str, mut f: impl FnMut() -> Result<T>) -> Result<T> {
+    const ATTEMPTS: u32 = 3;
+    const DELAY: Duration = Duration::from_millis(20);
+
+    let mut attempt = 1;
+    loop {
+        match f() {
+            Ok(value) => {
+                if attempt > 1 {
+                    log::info!("Clipboard {} succeeded after {} attempts", operation, attempt);
+                }
+                return Ok(value);
+            }
+            // An empty clipboard isn't contention, retrying won't help
+            Err(e) if attempt >= ATTEMPTS || is_empty_error(&e) => return Err(e),
+            Err(e) => {
+                log::debug!("Clipboard {} failed (attempt {}): {:#}", operation, attempt, e);
+                thread::sleep(DELAY * attempt);
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// Check if an error means the clipboard has no text
+fn is_empty_error(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<arboard::Error>(),
+        Some(arboard::Error::ContentNotAvailable)
+    )
+}
+
+/// Get the clipboard's text once it differs from `previous`
+///
+/// Polls until the content changes or `timeout` passes, then returns
+/// whatever is there. Copying text equal to `previous` only ends at the
+/// timeout, which is logged.
+pub fn get_text_after_change(
+    clipboard: &dyn ClipboardAccess,
+    previous: Option<&str>,
+    timeout: Duration,
+) -> Result<String> {
+    const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        let text = clipboard.get_text();
+        match text {
+            Ok(ref text) if Some(text.as_str()) != previous => return Ok(text.clone()),
+            _ if Instant::now() >= deadline => {
+                log::info!(
+                    "Clipboard unchanged {}ms after copying (nothing selected, or the selection equals the old clipboard)",
+                    timeout.as_millis()
+                );
+                return text;
+            }
+            _ => thread::sleep(POLL_INTERVAL),
+        }
+    }
+}
+
+/// A named pasteboard, separate from the general one
+///
+/// Scripts can exchange text through it without clobbering the user's
+/// clipboard.
+pub struct NamedPasteboard {
+    pub name: String,
+}
+
+impl ClipboardAccess for NamedPasteboard {
+    fn get_text(&self) -> Result<String> {
+        get_text_from(&self.name)
+    }
+
+    fn set_text(&self, text: &str) -> Result<()> {
+        set_text_to(&self.name, text)
+    }
+}
+
+/// Whether a pasteboard name is one of the system's (general, find, font,
+/// ruler, drag), which hold the user's own data
+pub fn is_system_pasteboard(name: &str) -> bool {
+    name.starts_with("Apple ") || name.starts_with("com.apple.")
+}
+
+/// Get text from the pasteboard with the given name
+pub fn get_text_from(name: &str) -> Result<String> {
+    unsafe {
+        let _pool = NSAutoreleasePool::new(nil);
+        pasteboard_text(pasteboard_with_name(name))
+            .with_context(|| format!("Failed to read pasteboard '{}'", name))
+    }
+}
+
+/// Set text to the pasteboard with the given name
+pub fn set_text_to(name: &str, text: &str) -> Result<()> {
+    unsafe {
+        let _pool = NSAutoreleasePool::new(nil);
+        set_pasteboard_text(pasteboard_with_name(name), text)
+            .with_context(|| format!("Failed to write pasteboard '{}'", name))
+    }
+}
+
+/// Get (or create) the NSPasteboard with the given name
+unsafe fn pasteboard_with_name(name: &str) -> id {
+    msg_send![class!(NSPasteboard), pasteboardWithName: NSString::alloc(nil).init_str(name)]
+}
+
+/// Get the text on an NSPasteboard
+pub unsafe fn pasteboard_text(pasteboard: id) -> Result<String> {
+    let text_type = NSString::alloc(nil).init_str(PASTEBOARD_TYPE_STRING);
+    let text: id = msg_send![pasteboard, stringForType: text_type];
+    if text == nil {
+        bail!("Pasteboard has no text");
+    }
+    let text: *const i8 = msg_send![text, UTF8String];
+    Ok(std::ffi::CStr::from_ptr(text).to_string_lossy().to_string())
+}
+
+/// Replace the content of an NSPasteboard with text
+pub unsafe fn set_pasteboard_text(pasteboard: id, text: &str) -> Result<()> {
+    let _: i64 = msg_send![pasteboard, clearContents];
+    let ok: cocoa::base::BOOL = msg_send![pasteboard,
+        setString: NSString::alloc(nil).init_str(text)
+        forType: NSString::alloc(nil).init_str(PASTEBOARD_TYPE_STRING)];
+    if ok == NO {
+        bail!("Failed to write text to pasteboard");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod mock {
+    use super::ClipboardAccess;
+    use anyhow::{bail, Result};
+    use std::cell::RefCell;
+
+    /// In-memory clipboard for tests
+    #[derive(Default)]
+    pub struct MemoryClipboard {
+        text: RefCell<Option<String>>,
+    }
+
+    impl MemoryClipboard {
+        /// Create a clipboard holding the given text
+        pub fn with_text(text: &str) -> Self {
+            Self {
+                text: RefCell::new(Some(text.to_string())),
+            }
+        }
+
+        /// Current clipboard content, if any
+        pub fn contents(&self) -> Option<String> {
+            self.text.borrow().clone()
+        }
+    }
+
+    impl ClipboardAccess for MemoryClipboard {
+        fn get_text(&self) -> Result<String> {
+            match self.text.borrow().as_ref() {
+                Some(text) => Ok(text.clone()),
+                None => bail!("Clipboard is empty"),
+            }
+        }
+
+        fn set_text(&self, text: &str) -> Result<()> {
+            *self.text.borrow_mut() = Some(text.to_string());
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::mock::MemoryClipboard;
+    use super::*;
+
+    #[test]
+    fn changed_text_is_returned_right_away() {
+        let clipboard = MemoryClipboard::with_text("selection");
+        let start = Instant::now();
+        let text = get_text_after_change(&clipboard, Some("old"), Duration::from_secs(5)).unwrap();
+        assert_eq!(text, "selection");
+        assert!(start.elapsed() < Duration::from_secs(1));
 
-    clipboard.set_text(text.to_string())
-        .context("Failed to write text to clipboard")
+        let text = get_text_after_change(&clipboard, Some("selection"), Duration::from_millis(20)).unwrap();
+        assert_eq!(text, "selection");
+        assert!(get_text_after_change(&MemoryClipboard::default(), None, Duration::ZERO).is_err());
+    }
 }
@@ -0,0 +1,199 @@
+//! Snippet collection mode
+//!
+//! Each hotkey press with a selection saves it as a file in a temp directory
+//! instead of opening the editor. Pressing the hotkey with nothing selected
+//! opens Helix on the directory, so all snippets can be edited in one go, and
+//! then pastes every changed snippet back into the window it came from, in
+//! the order they were collected.
+
+use crate::clipboard::ClipboardAccess;
+use crate::config::{Config, NotificationEvent};
+use crate::edit::{apply_trailing_newline, content_changed, read_edited_file};
+use crate::edit_session::{
+    self, file_extension_for, paste_back, restore_clipboard, ActiveSession, FrontmostApp,
+};
+use crate::menu_bar;
+use crate::stats::SessionOutcome;
+use crate::temp_files;
+use crate::terminal::{self, LaunchOptions, LaunchedTerminal, Terminal};
+use crate::terminal_probe;
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tempfile::TempDir;
+
+/// Snippets collected so far, waiting to be edited
+static COLLECTION: Mutex<Option<Collection>> = Mutex::new(None);
+
+/// A collected selection and where it should be pasted back
+struct Snippet {
+    path: PathBuf,
+    original: String,
+    app: Option<FrontmostApp>,
+}
+
+/// Temp directory holding one file per snippet
+struct Collection {
+    dir: TempDir,
+    snippets: Vec<Snippet>,
+}
+
+/// Directory of the snippets being collected, if any
+pub fn active_dir() -> Option<PathBuf> {
+    COLLECTION
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|collection| collection.dir.path().to_path_buf())
+}
+
+/// Handle a hotkey press in collect mode
+///
+/// A selection is added to the collection; an empty selection ends the
+/// collection and opens the editor on it.
+pub fn handle_selection(
+    config: &Config,
+    clipboard: &dyn ClipboardAccess,
+    app: Option<FrontmostApp>,
+    original_clipboard: Option<String>,
+    selected_text: String,
+) -> Result<SessionOutcome> {
+    // The clipboard only carried the selection, put the user's content back
+    restore_clipboard(clipboard, original_clipboard);
+
+    if !selected_text.is_empty() {
+        let count = add_snippet(config, app, selected_text)?;
+        let message = format!(
+            "Collected snippet {}. Press the hotkey with nothing selected to edit.",
+            count
+        );
+        menu_bar::notify(NotificationEvent::EditDone, &message);
+        return Ok(SessionOutcome::Collected);
+    }
+
+    let collection = COLLECTION.lock().unwrap().take();
+    match collection {
+        Some(collection) => edit_collection(config, clipboard, collection),
+        None => {
+            log::warn!("No text selected and no snippets collected");
+            menu_bar::notify(NotificationEvent::NoSelection, "No text selected");
+            Ok(SessionOutcome::NoSelection)
+        }
+    }
+}
+
+/// Save a selection as the next file of the collection, returning the snippet count
+fn add_snippet(config: &Config, app: Option<FrontmostApp>, text: String) -> Result<usize> {
+    let mut collection = COLLECTION.lock().unwrap();
+    if collection.is_none() {
+        let dir = tempfile::Builder::new()
+            .prefix(&format!("{}snippets-", temp_files::PREFIX))
+            .tempdir()
+            .context("Failed to create snippet directory")?;
+        log::info!("Collecting snippets in {:?}", dir.path());
+        *collection = Some(Collection {
+            dir,
+            snippets: Vec::new(),
+        });
+    }
+    let collection = collection.as_mut().unwrap();
+
+    // Numbered names keep the files in collection order in the picker
+    let extension = app
+        .as_ref()
+        .and_then(|app| file_extension_for(app, config))
+        .unwrap_or_else(|| "txt".to_string());
+    let number = collection.snippets.len() + 1;
+    let path = collection
+        .dir
+        .path()
+        .join(format!("snippet-{:02}.{}", number, extension));
+
+    fs::write(&path, &text).with_context(|| format!("Failed to write snippet: {:?}", path))?;
+    log::info!("Collected snippet {} ({} characters)", number, text.len());
+
+    collection.snippets.push(Snippet {
+        path,
+        original: text,
+        app,
+    });
+    Ok(number)
+}
+
+/// Open the editor on the collected snippets and paste the changed ones back
+fn edit_collection(
+    config: &Config,
+    clipboard: &dyn ClipboardAccess,
+    collection: Collection,
+) -> Result<SessionOutcome> {
+    let terminal = Terminal::from_name(&config.terminal.name)
+        .context("Invalid terminal name in config")?;
+
+    if !terminal.is_installed() {
+        bail!(
+            "Terminal '{}' is not installed. Please install it or change the terminal in config.",
+            terminal.display_name()
+        );
+    }
+
+    log::info!(
+        "Editing {} snippets in {}",
+        collection.snippets.len(),
+        terminal.display_name()
+    );
+
+    let _active = ActiveSession::start(collection.dir.path());
+
+    // Kept outside the directory so it doesn't show up in the picker
+    let marker = collection.dir.path().with_extension("done");
+    let needs_polling = terminal_probe::needs_polling(config, terminal);
+    let mut options = LaunchOptions::from_config(config);
+    if needs_polling {
+        options.exit_marker = Some(marker.clone());
+    }
+
+    let LaunchedTerminal { mut child, script: _script } = terminal
+        .launch(collection.dir.path(), &options)
+        .context("Failed to launch terminal")?;
+
+    if needs_polling {
+        let launch_timeout = Duration::from_millis(config.timing.launch_timeout_ms);
+        terminal::wait_for_launcher(&mut child, launch_timeout)?;
+        let poll_interval = Duration::from_millis(config.timing.file_poll_ms);
+        edit_session::wait_for_exit_marker(&marker, poll_interval)?;
+        let _ = fs::remove_file(&marker);
+    } else {
+        let status = child.wait().context("Failed to wait for terminal")?;
+        log::info!("Terminal exited with status: {:?}", status);
+    }
+
+    let mut outcome = SessionOutcome::Unchanged;
+    for (index, snippet) in collection.snippets.iter().enumerate() {
+        let edited = read_edited_file(&snippet.path)?;
+        let edited = apply_trailing_newline(&edited, &snippet.original, config.session.trailing_newline);
+
+        if !content_changed(&snippet.original, &edited, &config.session) {
+            log::info!("Snippet {} unchanged", index + 1);
+            continue;
+        }
+
+        log::info!("Pasting back snippet {} ({} characters)", index + 1, edited.len());
+        clipboard.set_text(&edited)
+            .context("Failed to set clipboard with edited text")?;
+
+        outcome = paste_back(config, snippet.app.as_ref(), &edited)?;
+        if outcome != SessionOutcome::Completed {
+            // The user was told the text is on the clipboard; don't overwrite it
+            break;
+        }
+
+        // Let the app process the paste before switching to the next target
+        thread::sleep(Duration::from_millis(200));
+    }
+
+    Ok(outcome)
+}
+
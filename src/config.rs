@@ -1,93 +0,0 @@
-use anyhow::{Context, Result};
-use directories::ProjectDirs;
-use serde::{Deserialize, Serialize};
-use std::fs;
-use std::path::PathBuf;
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Config {
-    pub hotkey: HotkeyConfig,
-    pub terminal: TerminalConfig,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct HotkeyConfig {
-    pub modifiers: Vec<String>,
-    pub key: String,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TerminalConfig {
-    pub name: String,
-    pub width: u32,
-    pub height: u32,
-}
-
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            hotkey: HotkeyConfig {
-                modifiers: vec!["cmd".to_string(), "shift".to_string()],
-                key: "semicolon".to_string(),
-            },
-            terminal: TerminalConfig {
-                name: "ghostty".to_string(),
-                width: 100,
-                height: 30,
-            },
-        }
-    }
-}
-
-impl Config {
-    /// Get the config directory path
-    pub fn config_dir() -> Option<PathBuf> {
-        ProjectDirs::from("com", "helix-anywhere", "helix-anywhere")
-            .map(|dirs| dirs.config_dir().to_path_buf())
-    }
-
-    /// Get the config file path
-    pub fn config_path() -> Option<PathBuf> {
-        Self::config_dir().map(|dir| dir.join("config.toml"))
-    }
-
-    /// Load config from file, or create default if not exists
-    pub fn load() -> Result<Self> {
-        let config_path = Self::config_path()
-            .context("Could not determine config directory")?;
-
-        if config_path.exists() {
-            let content = fs::read_to_string(&config_path)
-                .with_context(|| format!("Failed to read config file: {:?}", config_path))?;
-            let config: Config = toml::from_str(&content)
-                .with_context(|| "Failed to parse config file")?;
-            Ok(config)
-        } else {
-            // Create default config
-            let config = Config::default();
-            config.save()?;
-            Ok(config)
-        }
-    }
-
-    /// Save config to file
-    pub fn save(&self) -> Result<()> {
-        let config_dir = Self::config_dir()
-            .context("Could not determine config directory")?;
-        let config_path = Self::config_path()
-            .context("Could not determine config path")?;
-
-        // Create directory if it doesn't exist
-        fs::create_dir_all(&config_dir)
-            .with_context(|| format!("Failed to create config directory: {:?}", config_dir))?;
-
-        let content = toml::to_string_pretty(self)
-            .context("Failed to serialize config")?;
-
-        fs::write(&config_path, content)
-            .with_context(|| format!("Failed to write config file: {:?}", config_path))?;
-
-        log::info!("Config saved to {:?}", config_path);
-        Ok(())
-    }
-}
@@ -0,0 +1,150 @@
+//! Control socket for scripts and launchers (Raycast, Hammerspoon, ...)
+//!
+//! Listens on a Unix socket in the app's data directory when
+//! `automation.control_socket` is on. Requests and responses are lines of
+//! JSON, see `control` in the core crate. There is no TCP listener: only
+//! processes of the current user can reach the socket.
+
+use crate::config::Config;
+use crate::control::{self, ControlCommand};
+use crate::edit_session;
+use crate::keys::{self, HotkeyAction};
+use crate::menu_bar;
+use anyhow::{anyhow, Context, Result};
+use directories::ProjectDirs;
+use serde_json::Value;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Longest request line accepted, including the text of `edit_text`
+const MAX_REQUEST_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Path of the control socket
+pub fn socket_path() -> Option<PathBuf> {
+    ProjectDirs::from("com", "helix-anywhere", "helix-anywhere")
+        .map(|dirs| dirs.data_dir().join("control.sock"))
+}
+
+/// Start listening on the control socket in the background
+pub fn start(config: Arc<Mutex<Config>>) {
+    let listener = match bind() {
+        Ok(listener) => listener,
+        Err(e) => {
+            log::error!("Failed to start the control socket: {:#}", e);
+            return;
+        }
+    };
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let config = config.clone();
+                    thread::spawn(move || handle_connection(stream, &config));
+                }
+                Err(e) => log::warn!("Control socket connection failed: {}", e),
+            }
+        }
+    });
+}
+
+/// Create the socket, replacing one left behind by a previous run
+fn bind() -> Result<UnixListener> {
+    let path = socket_path().context("Could not determine data directory")?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).context("Failed to create data directory")?;
+    }
+    if path.exists() {
+        fs::remove_file(&path).context("Failed to remove the old control socket")?;
+    }
+
+    let listener = UnixListener::bind(&path)
+        .with_context(|| format!("Failed to bind {:?}", path))?;
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o600))
+        .context("Failed to restrict the control socket to the current user")?;
+    log::info!("Control socket listening at {:?}", path);
+    Ok(listener)
+}
+
+/// Answer each request line of a connection until it's closed
+fn handle_connection(stream: UnixStream, config: &Arc<Mutex<Config>>) {
+    let mut writer = match stream.try_clone() {
+        Ok(writer) => writer,
+        Err(e) => {
+            log::warn!("Control socket connection failed: {}", e);
+            return;
+        }
+    };
+
+    let mut reader = BufReader::new(stream);
+    loop {
+        // Reading is capped, so a client can't make the app buffer without limit
+        let mut line = String::new();
+        match (&mut reader).take(MAX_REQUEST_BYTES).read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        if !line.ends_with('\n') && line.len() as u64 == MAX_REQUEST_BYTES {
+            let error = anyhow!("Request longer than {} bytes", MAX_REQUEST_BYTES);
+            log::warn!("Control command failed: {:#}", error);
+            let _ = writeln!(writer, "{}", control::error_response(&error));
+            break;
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match ControlCommand::parse(&line).and_then(|command| run(command, config)) {
+            Ok(fields) => control::ok_response(fields),
+            Err(e) => {
+                log::warn!("Control command failed: {:#}", e);
+                control::error_response(&e)
+            }
+        };
+        if writeln!(writer, "{}", response).is_err() {
+            break;
+        }
+    }
+}
+
+/// Run a command, returning the fields of its response
+fn run(command: ControlCommand, config: &Arc<Mutex<Config>>) -> Result<Vec<(&'static str, Value)>> {
+    log::info!("Control command: {:?}", command);
+    // Sessions run on a snapshot, like hotkey sessions, so settings can change meanwhile
+    let snapshot = config.lock().unwrap().clone();
+
+    match command {
+        ControlCommand::Edit => {
+            let outcome = crate::run_action(&snapshot, HotkeyAction::EditSelection, &[])?;
+            Ok(vec![("outcome", Value::String(format!("{:?}", outcome)))])
+        }
+        ControlCommand::EditText(text) => {
+            let edited = edit_session::edit_text(&snapshot, &text, None)?;
+            Ok(vec![
+                ("changed", Value::Bool(edited.is_some())),
+                ("text", Value::String(edited.unwrap_or(text))),
+            ])
+        }
+        ControlCommand::GetHotkey => Ok(vec![
+            ("hotkey", control::hotkey_to_json(&snapshot.hotkey)?),
+            ("display", Value::String(keys::format_hotkey_display(&snapshot.hotkey))),
+        ]),
+        ControlCommand::SetHotkey(hotkey) => {
+            let mut updated = snapshot;
+            updated.hotkey = hotkey.clone();
+            updated.validate()?;
+            let display = keys::format_hotkey_display(&hotkey);
+            menu_bar::on_main_thread(move || menu_bar::apply_hotkey(hotkey));
+            Ok(vec![("display", Value::String(display))])
+        }
+        ControlCommand::ReloadConfig => {
+            menu_bar::on_main_thread(menu_bar::reload_config)?;
+            Ok(Vec::new())
+        }
+    }
+}
@@ -1,36 +1,262 @@
-use crate::clipboard;
-use crate::config::Config;
+use crate::accessibility;
+use crate::clipboard::{self, ClipboardAccess, SystemClipboard};
+use crate::collect;
+use crate::config::{Config, ModifierVariant, NotificationEvent, OutputMode, PasteMethod, PasteMode};
+use crate::edit::{apply_trailing_newline, content_changed, read_edited_file, trim_selection};
+use crate::file_watch;
+use crate::helix_config;
+use crate::keys;
 use crate::keystroke;
-use crate::terminal::Terminal;
+use crate::last_edit::{self, LastEdit};
+use crate::markdown;
+use crate::menu_bar::{self, RecoveryAction};
+use crate::stats::SessionOutcome;
+use crate::temp_files;
+use crate::terminal::{self, applescript_escape, LaunchOptions, LaunchedTerminal, Terminal};
+use crate::terminal_probe;
 use anyhow::{bail, Context, Result};
 use std::fs;
-use std::io::Write;
-use std::path::Path;
+use std::io::{Seek, SeekFrom, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::Mutex;
 use std::thread;
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
 use tempfile::NamedTempFile;
 
-/// Get the bundle identifier of the frontmost application
-fn get_frontmost_app() -> Option<String> {
+/// Temp file of the edit in progress, if any
+static ACTIVE_TEMP_FILE: Mutex<Option<(PathBuf, bool)>> = Mutex::new(None);
+
+/// Longest pasted text that is selected again with `reselect_after_paste`
+const MAX_RESELECT_CHARS: usize = 2000;
+
+/// Longest file extension used for the temp file
+const MAX_EXTENSION_LEN: usize = 16;
+
+/// Line length (in bytes) from which a selection is logged as unusually long
+const LONG_LINE_BYTES: usize = 10_000;
+
+/// Pause between pasting and putting the previous clipboard content back
+const CLIPBOARD_RESTORE_DELAY: Duration = Duration::from_millis(300);
+
+/// Longest wait for the hotkey's modifiers to be released before copying
+const MODIFIER_RELEASE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Longest wait for the copied selection to show up on the clipboard
+const COPY_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Get the temp file being edited by the active session
+pub fn active_temp_file() -> Option<PathBuf> {
+    ACTIVE_TEMP_FILE.lock().unwrap().as_ref().map(|(path, _)| path.clone())
+}
+
+/// Get the file being edited if the app created it (not a user's own file
+/// opened by smart open, or the scratchpad), so it may be deleted
+pub fn owned_temp_file() -> Option<PathBuf> {
+    ACTIVE_TEMP_FILE
+        .lock()
+        .unwrap()
+        .as_ref()
+        .filter(|(_, owned)| *owned)
+        .map(|(path, _)| path.clone())
+}
+
+/// Marks a temp file (or snippet directory) as being edited until dropped
+pub struct ActiveSession;
+
+impl ActiveSession {
+    /// Start editing a temp file the app created
+    pub fn start(temp_path: &Path) -> Self {
+        *ACTIVE_TEMP_FILE.lock().unwrap() = Some((temp_path.to_path_buf(), true));
+        ActiveSession
+    }
+
+    /// Start editing a file of the user's, which is never deleted
+    pub fn start_in_place(path: &Path) -> Self {
+        *ACTIVE_TEMP_FILE.lock().unwrap() = Some((path.to_path_buf(), false));
+        ActiveSession
+    }
+}
+
+impl Drop for ActiveSession {
+    fn drop(&mut self) {
+        *ACTIVE_TEMP_FILE.lock().unwrap() = None;
+    }
+}
+
+/// When the last session (of any kind) ended
+static LAST_SESSION_END: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Note that a session just ended, starting the post-session cooldown
+pub fn mark_session_end() {
+    *LAST_SESSION_END.lock().unwrap() = Some(Instant::now());
+}
+
+/// Check if a session ended less than `post_session_cooldown_ms` ago
+pub fn in_post_session_cooldown(config: &Config) -> bool {
+    let cooldown = Duration::from_millis(config.timing.post_session_cooldown_ms);
+    LAST_SESSION_END
+        .lock()
+        .unwrap()
+        .is_some_and(|end| end.elapsed() < cooldown)
+}
+
+/// Last app other than this one detected by `capture_frontmost_app`
+static LAST_FRONTMOST_APP: Mutex<Option<FrontmostApp>> = Mutex::new(None);
+
+/// Attempts at detecting the frontmost app when a session starts
+const FRONTMOST_APP_ATTEMPTS: u32 = 3;
+
+/// Pause between frontmost app detection attempts
+const FRONTMOST_APP_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// The application that was frontmost when the session started
+#[derive(Clone)]
+pub struct FrontmostApp {
+    pub bundle_id: String,
+    /// Its display name (e.g. "Mail")
+    pub name: Option<String>,
+    /// Title of its focused window, if it has one
+    pub window_title: Option<String>,
+    /// Process identifier
+    pub pid: Option<u32>,
+}
+
+impl FrontmostApp {
+    /// Whether this is helix-anywhere itself (e.g. its menu or a panel is focused)
+    pub fn is_own_app(&self) -> bool {
+        self.pid == Some(std::process::id())
+    }
+}
+
+/// Get the bundle identifier, name and focused window title of the frontmost application
+fn get_frontmost_app() -> Option<FrontmostApp> {
+    let script = r#"
+        tell application "System Events"
+            set frontApp to first application process whose frontmost is true
+            set windowTitle to ""
+            try
+                set windowTitle to name of front window of frontApp
+            end try
+            return (bundle identifier of frontApp) & linefeed & (unix id of frontApp) & linefeed & (name of frontApp) & linefeed & windowTitle
+        end tell
+    "#;
     let output = Command::new("osascript")
         .arg("-e")
-        .arg(r#"tell application "System Events" to get bundle identifier of first application process whose frontmost is true"#)
+        .arg(script)
         .output()
         .ok()?;
 
     if output.status.success() {
-        let bundle_id = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let mut lines = stdout.trim_end().splitn(4, '\n');
+        let bundle_id = lines.next().unwrap_or("").trim().to_string();
+        let pid = lines.next().and_then(|pid| pid.trim().parse().ok());
+        let name = lines.next().unwrap_or("").trim();
+        let title = lines.next().unwrap_or("").trim();
         if !bundle_id.is_empty() {
-            log::info!("Frontmost app: {}", bundle_id);
-            return Some(bundle_id);
+            log::debug!("Frontmost app: {}", bundle_id);
+            return Some(FrontmostApp {
+                bundle_id,
+                name: (!name.is_empty()).then(|| name.to_string()),
+                window_title: (!title.is_empty()).then(|| title.to_string()),
+                pid,
+            });
         }
     }
     None
 }
 
+/// Detect the frontmost app at the start of a session
+///
+/// System Events can be briefly busy, so detection is retried a few times.
+/// If it still fails, the app detected by the previous session is used, on
+/// the assumption that focus is rarely somewhere new.
+fn capture_frontmost_app() -> Option<FrontmostApp> {
+    for attempt in 1..=FRONTMOST_APP_ATTEMPTS {
+        if let Some(app) = get_frontmost_app() {
+            // Pasting into our own menu or panel makes no sense, the app
+            // before it is the one to go back to
+            if !app.is_own_app() {
+                *LAST_FRONTMOST_APP.lock().unwrap() = Some(app.clone());
+            }
+            return Some(app);
+        }
+        if attempt < FRONTMOST_APP_ATTEMPTS {
+            log::debug!("Frontmost app detection failed (attempt {}), retrying", attempt);
+            thread::sleep(FRONTMOST_APP_RETRY_DELAY);
+        }
+    }
+
+    let cached = LAST_FRONTMOST_APP.lock().unwrap().clone();
+    match cached {
+        Some(ref app) => log::warn!(
+            "Couldn't detect the frontmost app, using the last detected one ({})",
+            app.bundle_id
+        ),
+        None => log::warn!("Couldn't detect the frontmost app"),
+    }
+    cached
+}
+
+/// Bring the last app other than this one back to the front, and return it
+///
+/// `None` if there's no such app or it didn't come back in time.
+fn leave_own_app(config: &Config) -> Option<FrontmostApp> {
+    let app = LAST_FRONTMOST_APP.lock().unwrap().clone()?;
+    log::info!("helix-anywhere itself is frontmost, returning to {}", app.bundle_id);
+    let timeout = Duration::from_millis(config.timing.activation_timeout_ms);
+    match activate_app(&app.bundle_id, timeout) {
+        Ok(true) => Some(app),
+        Ok(false) => {
+            log::warn!("{} didn't come to the front within {:?}", app.bundle_id, timeout);
+            None
+        }
+        Err(e) => {
+            log::warn!("Failed to return to {}: {}", app.bundle_id, e);
+            None
+        }
+    }
+}
+
+/// Guess the file extension of the text being edited from the frontmost app
+///
+/// Best-effort: uses a file name in the window title when enabled (editors
+/// and IDEs usually show one), then the per-app extension from the config.
+pub fn file_extension_for(app: &FrontmostApp, config: &Config) -> Option<String> {
+    let from_title = if config.editor.extension_from_window_title {
+        app.window_title.as_deref().and_then(extension_from_title)
+    } else {
+        None
+    };
+
+    from_title.or_else(|| config.editor.app_extensions.get(&app.bundle_id).cloned())
+}
+
+/// Extract the extension of a file name in a window title
+/// (e.g. "rs" from "main.rs — helix-anywhere" or "● config.toml - Visual Studio Code")
+fn extension_from_title(title: &str) -> Option<String> {
+    title
+        .split([' ', '—', '–', '|', '(', ')', '[', ']'])
+        .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric()))
+        .find_map(|word| {
+            let (stem, ext) = word.rsplit_once('.')?;
+            let valid = !stem.is_empty()
+                && (1..=10).contains(&ext.len())
+                && ext.chars().all(|c| c.is_ascii_alphanumeric())
+                && ext.chars().any(|c| c.is_ascii_alphabetic());
+            valid.then(|| ext.to_lowercase())
+        })
+}
+
 /// Activate an application by its bundle identifier
-fn activate_app(bundle_id: &str) -> Result<()> {
+///
+/// Waits up to `timeout` for the app to actually come to the front and
+/// returns whether it did.
+fn activate_app(bundle_id: &str, timeout: Duration) -> Result<bool> {
+    const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
     let script = format!(
         r#"tell application id "{}" to activate"#,
         bundle_id
@@ -42,8 +268,57 @@ fn activate_app(bundle_id: &str) -> Result<()> {
         .context("Failed to activate app")?;
 
     // Give the app time to come to front
-    thread::sleep(Duration::from_millis(100));
-    Ok(())
+    let start = std::time::Instant::now();
+    loop {
+        if get_frontmost_app().is_some_and(|app| app.bundle_id == bundle_id) {
+            log::debug!("{} is frontmost after {:?}", bundle_id, start.elapsed());
+            return Ok(true);
+        }
+        if start.elapsed() >= timeout {
+            return Ok(false);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Bring the app's window with the given title to the front (best-effort)
+///
+/// Activating by bundle id focuses the app's last active window, which may be
+/// a different window or one on another Space.
+fn raise_window(bundle_id: &str, title: &str) {
+    let script = format!(
+        r#"
+        tell application "System Events"
+            tell (first application process whose bundle identifier is "{}")
+                perform action "AXRaise" of (first window whose name is "{}")
+            end tell
+        end tell
+        "#,
+        applescript_escape(bundle_id),
+        applescript_escape(title)
+    );
+    match Command::new("osascript").arg("-e").arg(&script).output() {
+        Ok(output) if output.status.success() => thread::sleep(Duration::from_millis(100)),
+        _ => log::debug!("Could not raise window {:?}", title),
+    }
+}
+
+/// Check that focus is back on the window the text was copied from
+fn is_paste_target(original: &FrontmostApp) -> bool {
+    let Some(current) = get_frontmost_app() else {
+        return false;
+    };
+    if current.bundle_id != original.bundle_id {
+        log::warn!("Frontmost app is {}, expected {}", current.bundle_id, original.bundle_id);
+        return false;
+    }
+    match (&original.window_title, &current.window_title) {
+        (Some(expected), Some(actual)) if expected != actual => {
+            log::warn!("Frontmost window is {:?}, expected {:?}", actual, expected);
+            false
+        }
+        _ => true,
+    }
 }
 
 /// Run an edit session
@@ -54,55 +329,916 @@ fn activate_app(bundle_id: &str) -> Result<()> {
 /// 4. Launch terminal with helix
 /// 5. Wait for terminal to exit
 /// 6. If content changed, paste back
-pub fn run_edit_session(config: &Config) -> Result<()> {
+///
+/// `variants` are those selected by modifiers held with the hotkey.
+pub fn run_edit_session(
+    config: &Config,
+    clipboard: &dyn ClipboardAccess,
+    variants: &[ModifierVariant],
+) -> Result<SessionOutcome> {
     log::info!("Starting edit session");
 
+    // Modifiers held with the hotkey can change how the result is delivered
+    let config = &with_variants(config, variants);
+    let select_all = variants.contains(&ModifierVariant::SelectAll);
+
+    // In scratchpad mode the hotkey opens the same file every time
+    if let Some(ref path) = config.session.scratchpad_path {
+        let paste = variants.contains(&ModifierVariant::PasteScratchpad);
+        return run_scratchpad_session(config, clipboard, path, paste);
+    }
+
+    // Password fields block copying, and their content shouldn't be edited anyway
+    if keystroke::is_secure_input_enabled() {
+        log::warn!("Secure input is enabled, aborting edit session");
+        menu_bar::notify(NotificationEvent::EditError, "Can't edit secure fields");
+        return Ok(SessionOutcome::SecureInput);
+    }
+
     // Step 0: Remember the frontmost app so we can return to it
-    let original_app = get_frontmost_app();
+    let original_app = match capture_frontmost_app() {
+        Some(app) if app.is_own_app() => match leave_own_app(config) {
+            Some(app) => Some(app),
+            None => {
+                menu_bar::notify(
+                    NotificationEvent::EditError,
+                    "Switch to the app with the text to edit, then press the hotkey",
+                );
+                return Ok(SessionOutcome::NoSelection);
+            }
+        },
+        app => app,
+    };
+    if let Some(ref app) = original_app {
+        log::info!("Frontmost app: {}", app.bundle_id);
+        // Before anything is copied, so the clipboard stays untouched
+        if config.session.is_app_ignored(&app.bundle_id) {
+            log::info!("{} is in ignored_apps, not editing", app.bundle_id);
+            return Ok(SessionOutcome::IgnoredApp);
+        }
+    }
 
-    // Step 1: Save current clipboard content (to restore if aborted)
-    let original_clipboard = clipboard::get_text().ok();
+    // Apps listed in ax_apps can be edited without going through the clipboard
+    let bundle_id = original_app.as_ref().map(|app| app.bundle_id.as_str());
+    if config.session.uses_ax(bundle_id) && !select_all {
+        if let Some(outcome) = run_ax_session(config, clipboard, original_app.as_ref())? {
+            return Ok(outcome);
+        }
+        log::info!("No selection through the Accessibility API, copying it instead");
+    }
+
+    // Step 1: Save current clipboard content; it's put back on every way out
+    // of the session (errors included) unless the edited text is pasted
+    let original_clipboard = ClipboardGuard::new(clipboard, clipboard.get_text().ok());
+
+    // Step 2: Let go of the hotkey first; a modifier still held down (or its
+    // key up arriving mid-combo) can turn the copy into another shortcut
+    thread::sleep(Duration::from_millis(config.timing.focus_grace_ms));
+    if !keystroke::wait_for_modifiers_released(MODIFIER_RELEASE_TIMEOUT) {
+        log::warn!("Hotkey modifiers still held, copying anyway");
+    }
+
+    if select_all {
+        keystroke::simulate_select_all().context("Failed to select all")?;
+    }
 
-    // Step 2: Simulate Cmd+C to copy selection
-    keystroke::simulate_copy()
+    // Simulate Cmd+C (or the app's copy combo) to copy selection
+    keystroke::simulate_copy(config.shortcuts.copy_for(bundle_id))
         .context("Failed to simulate copy")?;
 
-    // Small delay to ensure clipboard is updated
-    thread::sleep(Duration::from_millis(50));
+    // Step 3: Get the selected text from clipboard, once the app has copied it
+    let selected_text =
+        clipboard::get_text_after_change(clipboard, original_clipboard.original(), COPY_TIMEOUT)
+            .context("Failed to read selected text from clipboard")?;
 
-    // Step 3: Get the selected text from clipboard
-    let selected_text = clipboard::get_text()
-        .context("Failed to read selected text from clipboard")?;
+    edit_copied_selection(config, clipboard, original_app, original_clipboard, selected_text)
+}
+
+/// Steps 4-11 of an edit session, once the selection has been copied
+///
+/// `original_clipboard` puts the clipboard's content from before the copy
+/// back when the session ends without pasting the result.
+fn edit_copied_selection(
+    config: &Config,
+    clipboard: &dyn ClipboardAccess,
+    original_app: Option<FrontmostApp>,
+    original_clipboard: ClipboardGuard<'_>,
+    selected_text: String,
+) -> Result<SessionOutcome> {
+    // In collect mode, selections are gathered and edited together later
+    if config.session.collect_snippets {
+        return collect::handle_selection(
+            config,
+            clipboard,
+            original_app,
+            original_clipboard.disarm(),
+            selected_text,
+        );
+    }
 
     if selected_text.is_empty() {
         log::warn!("No text selected, aborting edit session");
-        // Restore original clipboard if we had one
-        if let Some(orig) = original_clipboard {
-            let _ = clipboard::set_text(&orig);
+        original_clipboard.restore();
+        menu_bar::notify(NotificationEvent::NoSelection, "No text selected");
+        return Ok(SessionOutcome::NoSelection);
+    }
+
+    log::info!("Captured {} characters of selected text", selected_text.len());
+
+    // A selected path or URL can be opened itself instead of its text
+    if config.session.smart_open {
+        if let Some(target) = smart_target(&selected_text) {
+            original_clipboard.restore();
+            return open_smart_target(config, clipboard, target);
+        }
+    }
+
+    // Rich selections are edited as Markdown and pasted back as HTML
+    let rich_text = (config.session.rich_text_as_markdown && !config.session.paste_on_save)
+        .then(|| clipboard.get_html())
+        .flatten()
+        .map(|html| markdown::html_to_markdown(&html))
+        .filter(|markdown| !markdown.is_empty());
+    let is_rich_text = rich_text.is_some();
+    let selected_text = match rich_text {
+        Some(markdown) => {
+            log::info!("Selection has HTML, editing it as {} characters of Markdown", markdown.len());
+            markdown
         }
+        None => selected_text,
+    };
+
+    // Stray whitespace around the selection stays out of the editor, and is
+    // put back around the result so the surrounding text isn't disturbed
+    let original_selection = selected_text;
+    let (selected_text, trimmed_edges) =
+        trim_selection(&original_selection, config.session.trim_selection);
+
+    let extension = if is_rich_text {
+        Some("md".to_string())
+    } else {
+        original_app
+            .as_ref()
+            .and_then(|app| file_extension_for(app, config))
+    };
+
+    // With paste_on_save, every save is pasted back while the editor stays open
+    let mut paste_on_save = config
+        .session
+        .paste_on_save
+        .then(|| PasteOnSave::new(config, original_app.as_ref(), clipboard));
+    let mut on_save = |saved: &str| {
+        if let Some(ref mut paste_on_save) = paste_on_save {
+            if let Err(e) = paste_on_save.paste(&trimmed_edges.restore(saved)) {
+                log::warn!("Failed to paste saved text: {}", e);
+            }
+        }
+    };
+    let on_save: Option<&mut dyn FnMut(&str)> = if config.session.paste_on_save {
+        Some(&mut on_save)
+    } else {
+        None
+    };
+
+    // Steps 4-8: Edit the text in the terminal
+    let source_app = original_app.as_ref().and_then(|app| app.name.as_deref());
+    let edited_text = match edit_text_watching(
+        config,
+        &selected_text,
+        extension.as_deref(),
+        source_app,
+        on_save,
+    ) {
+        Ok(Some(text)) => text,
+        // Don't leave the selection on the clipboard when the edit never happened
+        Err(e) => return Err(e),
+        Ok(None) => {
+            // A save was pasted, but the text ended up as it started: put that back too
+            if let Some(mut paste_on_save) = paste_on_save.filter(|sync| sync.pasted.is_some()) {
+                log::info!("Content back to the original, pasting it over the saved text");
+                original_clipboard.disarm();
+                return paste_on_save.paste(&original_selection);
+            }
+            log::info!("Content unchanged, not pasting back (user likely aborted)");
+            original_clipboard.restore();
+            return Ok(SessionOutcome::Unchanged);
+        }
+    };
+    let edited_text = trimmed_edges.restore(&edited_text);
+
+    log::info!("Content changed, pasting back {} characters", edited_text.len());
+
+    if config.session.remember_last_edit {
+        last_edit::remember(&LastEdit {
+            original: original_selection.clone(),
+            result: edited_text.clone(),
+            bundle_id: original_app.as_ref().map(|app| app.bundle_id.clone()),
+            extension: extension.clone(),
+        });
+    }
+
+    // Step 9: Put edited text in clipboard
+    if is_rich_text {
+        clipboard.set_html(&markdown::markdown_to_html(&edited_text), &edited_text)
+            .context("Failed to set clipboard with edited text")?;
+    } else {
+        set_plain_result(config, clipboard, &edited_text)?;
+    }
+
+    // Steps 10-11: Return to the original app and paste
+    let outcome = match paste_on_save.filter(|sync| sync.pasted.is_some()) {
+        Some(mut paste_on_save) => paste_on_save.paste(&edited_text)?,
+        None => paste_back(config, original_app.as_ref(), &edited_text)?,
+    };
+    if outcome == SessionOutcome::Completed && config.session.restores_clipboard_after_paste() {
+        // The app reads the clipboard some time after the paste keystroke
+        thread::sleep(CLIPBOARD_RESTORE_DELAY);
+        original_clipboard.restore();
+    } else {
+        // Pasted, or left on the clipboard for the user to paste themselves
+        original_clipboard.disarm();
+    }
+    if outcome == SessionOutcome::Completed {
+        session_completed(config, &edited_text);
+    }
+    Ok(outcome)
+}
+
+/// Apply the variants selected by modifiers held with the hotkey to the config
+fn with_variants(config: &Config, variants: &[ModifierVariant]) -> Config {
+    let mut config = config.clone();
+    for variant in variants {
+        log::info!("Hotkey variant: {:?}", variant);
+        match variant {
+            ModifierVariant::SelectAll | ModifierVariant::PasteScratchpad => {}
+            ModifierVariant::Append => config.session.output_mode = OutputMode::Append,
+            ModifierVariant::ClipboardOnly => config.session.output_mode = OutputMode::ClipboardOnly,
+        }
+    }
+    config
+}
+
+/// Edit the selection read through the Accessibility API (`capture_mode = "ax"`)
+///
+/// Returns `None` when the focused element has no selection to read, so the
+/// caller can fall back to copying it. If the selection can't be replaced,
+/// the edited text is pasted instead.
+fn run_ax_session(
+    config: &Config,
+    clipboard: &dyn ClipboardAccess,
+    app: Option<&FrontmostApp>,
+) -> Result<Option<SessionOutcome>> {
+    let Some(element) = accessibility::FocusedElement::current() else {
+        return Ok(None);
+    };
+    let Some(selected_text) = element.selected_text().filter(|text| !text.is_empty()) else {
+        return Ok(None);
+    };
+    log::info!("Captured {} characters through the Accessibility API", selected_text.len());
+
+    let (trimmed_text, trimmed_edges) = trim_selection(&selected_text, config.session.trim_selection);
+    let extension = app.and_then(|app| file_extension_for(app, config));
+    let source_app = app.and_then(|app| app.name.as_deref());
+    let Some(edited_text) =
+        edit_text_watching(config, &trimmed_text, extension.as_deref(), source_app, None)?
+    else {
+        log::info!("Content unchanged, not replacing the selection");
+        return Ok(Some(SessionOutcome::Unchanged));
+    };
+    let edited_text = trimmed_edges.restore(&edited_text);
+
+    if config.session.remember_last_edit {
+        last_edit::remember(&LastEdit {
+            original: selected_text.clone(),
+            result: edited_text.clone(),
+            bundle_id: app.map(|app| app.bundle_id.clone()),
+            extension: extension.clone(),
+        });
+    }
+
+    let replacement = match config.session.output_mode {
+        OutputMode::Replace => edited_text.clone(),
+        OutputMode::Append => format!("{}{}", selected_text, edited_text),
+        OutputMode::ClipboardOnly => {
+            clipboard.set_text(&edited_text)
+                .context("Failed to set clipboard with edited text")?;
+            session_completed(config, &edited_text);
+            return Ok(Some(SessionOutcome::Completed));
+        }
+    };
+
+    let outcome = match element.replace_selection(&replacement) {
+        Ok(()) => {
+            log::info!("Replaced the selection through the Accessibility API");
+            if let Some(app) = app {
+                let timeout = Duration::from_millis(config.timing.activation_timeout_ms);
+                if !activate_app(&app.bundle_id, timeout).unwrap_or(false) {
+                    log::warn!("{} didn't come back to the front", app.bundle_id);
+                }
+            }
+            SessionOutcome::Completed
+        }
+        Err(e) => {
+            log::warn!("{:#}, pasting instead", e);
+            clipboard.set_text(&edited_text)
+                .context("Failed to set clipboard with edited text")?;
+            paste_back(config, app, &edited_text)?
+        }
+    };
+    if outcome == SessionOutcome::Completed {
+        session_completed(config, &edited_text);
+    }
+    Ok(Some(outcome))
+}
+
+/// Report a successful edit and run the post-edit command
+fn session_completed(config: &Config, edited_text: &str) {
+    log::info!("Edit session completed successfully");
+    let message = match config.session.output_mode {
+        OutputMode::ClipboardOnly => "Edited text copied to the clipboard",
+        _ => "Edited text pasted",
+    };
+    menu_bar::notify(NotificationEvent::EditDone, message);
+
+    if let Some(ref command) = config.session.post_edit_command {
+        run_post_edit_command(command, edited_text);
+    }
+}
+
+/// Edit the clipboard's content and put the result back on the clipboard
+///
+/// Nothing is copied from or pasted into the frontmost app, so this works
+/// in apps where simulated copy/paste doesn't.
+pub fn run_clipboard_session(config: &Config, clipboard: &dyn ClipboardAccess) -> Result<SessionOutcome> {
+    log::info!("Starting clipboard edit session");
+
+    let text = clipboard.get_text().unwrap_or_default();
+    if text.is_empty() {
+        log::warn!("Clipboard is empty, aborting edit session");
+        menu_bar::notify(NotificationEvent::NoSelection, "The clipboard has no text");
+        return Ok(SessionOutcome::NoSelection);
+    }
+
+    log::info!("Captured {} characters from the clipboard", text.len());
+
+    let Some(edited_text) = edit_text(config, &text, None)? else {
+        log::info!("Content unchanged, leaving the clipboard as it was");
+        return Ok(SessionOutcome::Unchanged);
+    };
+
+    set_plain_result(config, clipboard, &edited_text)?;
+    log::info!("Clipboard edit session completed successfully");
+    menu_bar::notify(NotificationEvent::EditDone, "Edited text copied to the clipboard");
+
+    if config.session.remember_last_edit {
+        last_edit::remember(&LastEdit {
+            original: text,
+            result: edited_text.clone(),
+            bundle_id: None,
+            extension: None,
+        });
+    }
+    if let Some(ref command) = config.session.post_edit_command {
+        run_post_edit_command(command, &edited_text);
+    }
+    Ok(SessionOutcome::Completed)
+}
+
+/// What a selection refers to, when `smart_open` is on
+#[derive(Debug, PartialEq, Eq)]
+enum SmartTarget {
+    /// An existing file, edited in place
+    File(PathBuf),
+    /// An http(s) URL, whose content is downloaded and edited
+    Url(String),
+}
+
+/// Check whether the selection is a single existing file path or URL
+///
+/// Paths must be absolute or start with `~/`.
+fn smart_target(selection: &str) -> Option<SmartTarget> {
+    let selection = selection.trim();
+    if selection.is_empty() || selection.contains('\n') {
+        return None;
+    }
+
+    if selection.starts_with("https://") || selection.starts_with("http://") {
+        if selection.contains(char::is_whitespace) {
+            return None;
+        }
+        return Some(SmartTarget::Url(selection.to_string()));
+    }
+
+    let path = match selection.strip_prefix("~/") {
+        Some(rest) => PathBuf::from(std::env::var_os("HOME")?).join(rest),
+        None => PathBuf::from(selection),
+    };
+    (path.is_absolute() && path.is_file()).then_some(SmartTarget::File(path))
+}
+
+/// Put a plain-text result on the clipboard
+///
+/// With `paste_html`, a simple HTML version goes along, which rich editors
+/// paste instead of the text.
+fn set_plain_result(config: &Config, clipboard: &dyn ClipboardAccess, text: &str) -> Result<()> {
+    let result = if config.session.paste_html {
+        clipboard.set_html(&markdown::text_to_html(text), text)
+    } else {
+        clipboard.set_text(text)
+    };
+    result.context("Failed to set clipboard with edited text")
+}
+
+/// Open the scratchpad file (`scratchpad_path`) instead of the selection
+///
+/// Nothing is copied, and the file is created if it doesn't exist yet. With
+/// `paste`, its whole content is pasted into the frontmost app once the
+/// editor closes.
+fn run_scratchpad_session(
+    config: &Config,
+    clipboard: &dyn ClipboardAccess,
+    path: &Path,
+    paste: bool,
+) -> Result<SessionOutcome> {
+    let path = match path.strip_prefix("~") {
+        Ok(rest) => PathBuf::from(std::env::var_os("HOME").context("HOME is not set")?).join(rest),
+        Err(_) => path.to_path_buf(),
+    };
+    if !path.exists() {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create scratchpad directory: {:?}", dir))?;
+        }
+        fs::write(&path, "").with_context(|| format!("Failed to create scratchpad: {:?}", path))?;
+    }
+    log::info!("Opening scratchpad {:?}", path);
+
+    let app = if paste { capture_frontmost_app() } else { None };
+    let before = fs::read(&path).ok();
+    {
+        let _active = ActiveSession::start_in_place(&path);
+        if let Some(error) = open_and_wait(config, &path, LaunchOptions::from_config(config))? {
+            log::warn!("Terminal reported an error: {}", error);
+        }
+    }
+
+    if paste {
+        let text = read_edited_file(&path)?;
+        let text = apply_trailing_newline(&text, "", config.session.trailing_newline);
+        if text.is_empty() {
+            return Ok(SessionOutcome::Unchanged);
+        }
+        clipboard.set_text(&text)
+            .context("Failed to set clipboard with the scratchpad")?;
+        return paste_back(config, app.as_ref(), &text);
+    }
+    if fs::read(&path).ok() == before {
+        return Ok(SessionOutcome::Unchanged);
+    }
+    Ok(SessionOutcome::Completed)
+}
+
+/// Open a selected file or URL in the editor instead of the selected text
+///
+/// Nothing is pasted back: a file is edited in place, and an edited copy of
+/// a URL's content is put on the clipboard.
+fn open_smart_target(
+    config: &Config,
+    clipboard: &dyn ClipboardAccess,
+    target: SmartTarget,
+) -> Result<SessionOutcome> {
+    match target {
+        SmartTarget::File(path) => {
+            log::info!("Selection is a file, opening {:?}", path);
+            let before = fs::read(&path).ok();
+            let _active = ActiveSession::start_in_place(&path);
+            if let Some(error) = open_and_wait(config, &path, LaunchOptions::from_config(config))? {
+                log::warn!("Terminal reported an error: {}", error);
+            }
+            if fs::read(&path).ok() == before {
+                return Ok(SessionOutcome::Unchanged);
+            }
+            menu_bar::notify(NotificationEvent::EditDone, "File saved");
+            Ok(SessionOutcome::Completed)
+        }
+        SmartTarget::Url(url) => {
+            log::info!("Selection is a URL, downloading {}", url);
+            let content = download(&url)?;
+            let extension = Path::new(url.split(['?', '#']).next().unwrap_or(&url))
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .filter(|ext| ext.chars().all(|c| c.is_ascii_alphanumeric()));
+
+            let Some(edited) = edit_text(config, &content, extension)? else {
+                return Ok(SessionOutcome::Unchanged);
+            };
+            clipboard.set_text(&edited)
+                .context("Failed to set clipboard with edited text")?;
+            menu_bar::notify(NotificationEvent::EditDone, "Edited text copied to the clipboard");
+            Ok(SessionOutcome::Completed)
+        }
+    }
+}
+
+/// Download the text at a URL with curl
+fn download(url: &str) -> Result<String> {
+    let output = Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--location", "--max-time", "30"])
+        .arg(url)
+        .output()
+        .context("Failed to run curl")?;
+    if !output.status.success() {
+        bail!("Failed to download {}: {}", url, stderr_summary(&output.stderr));
+    }
+    String::from_utf8(output.stdout).with_context(|| format!("{} is not text", url))
+}
+
+/// Start the user's post-edit command without waiting for it
+///
+/// The edited text is written to its stdin and, if the command contains
+/// `{file}`, to a temp file that is removed once the command exits.
+/// Failures are only logged, the edit itself has already succeeded.
+fn run_post_edit_command(command: &str, text: &str) {
+    let command = command.to_string();
+    let text = text.to_string();
+
+    thread::spawn(move || {
+        let mut file = None;
+        let mut command_line = command.clone();
+        if command.contains("{file}") {
+            let temp_file = tempfile::Builder::new()
+                .prefix(temp_files::PREFIX)
+                .suffix(".txt")
+                .tempfile()
+                .and_then(|mut temp_file| temp_file.write_all(text.as_bytes()).map(|_| temp_file));
+            match temp_file {
+                Ok(temp_file) => {
+                    let path = temp_file.path().to_string_lossy().to_string();
+                    command_line = command.replace("{file}", &terminal::shell_quote(&path));
+                    file = Some(temp_file);
+                }
+                Err(e) => {
+                    log::debug!("Failed to write file for post-edit command: {}", e);
+                    return;
+                }
+            }
+        }
+
+        log::info!("Running post-edit command: {}", command_line);
+        let child = Command::new("/bin/sh")
+            .arg("-c")
+            .arg(&command_line)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::piped())
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(e) => {
+                log::debug!("Failed to start post-edit command: {}", e);
+                return;
+            }
+        };
+
+        if let Some(mut stdin) = child.stdin.take() {
+            // The command may not read stdin at all
+            let _ = stdin.write_all(text.as_bytes());
+        }
+        match child.wait_with_output() {
+            Ok(output) if !output.status.success() => log::debug!(
+                "Post-edit command exited with {}: {}",
+                output.status,
+                stderr_summary(&output.stderr)
+            ),
+            Ok(_) => log::debug!("Post-edit command finished"),
+            Err(e) => log::debug!("Failed to wait for post-edit command: {}", e),
+        }
+
+        // Only now may the file go away
+        drop(file);
+    });
+}
+
+/// Put `text` on the clipboard again and paste it into `app`
+///
+/// Offered on the notification when a paste couldn't reach its target.
+pub fn retry_paste(config: &Config, app: Option<&FrontmostApp>, text: &str) -> Result<SessionOutcome> {
+    SystemClipboard
+        .set_text(text)
+        .context("Failed to put the edited text on the clipboard")?;
+    paste_back(config, app, text)
+}
+
+/// Return focus to the app the text came from and paste the clipboard into it
+///
+/// Depending on the output mode, the text replaces the selection, is pasted
+/// after it, or is only left on the clipboard.
+///
+/// Leaves the text on the clipboard (and tells the user) if the app or its
+/// window can't be brought back to the front. `text` is what the clipboard
+/// holds, used to select it again after pasting.
+pub fn paste_back(config: &Config, app: Option<&FrontmostApp>, text: &str) -> Result<SessionOutcome> {
+    if config.session.output_mode == OutputMode::ClipboardOnly {
+        log::info!("Output mode is clipboard only, not pasting");
+        return Ok(SessionOutcome::Completed);
+    }
+
+    // Step 10: Return focus to the original app
+    if let Some(app) = app {
+        log::info!("Restoring focus to original app: {}", app.bundle_id);
+        let timeout = Duration::from_millis(config.timing.activation_timeout_ms);
+        if !activate_app(&app.bundle_id, timeout)? {
+            log::warn!("{} didn't come to the front within {:?}", app.bundle_id, timeout);
+            menu_bar::notify_with_recovery(
+                NotificationEvent::EditError,
+                "Couldn't return to the original app. The edited text is on the clipboard.",
+                RecoveryAction::RetryPaste { app: Some(app.clone()), text: text.to_string() },
+            );
+            return Ok(SessionOutcome::TargetLost);
+        }
+        if let Some(ref title) = app.window_title {
+            raise_window(&app.bundle_id, title);
+        }
+
+        // The window may be on another Space; don't paste into the wrong one
+        if config.session.verify_paste_target && !is_paste_target(app) {
+            log::warn!("Original window not focused, leaving edited text on the clipboard");
+            menu_bar::notify_with_recovery(
+                NotificationEvent::EditError,
+                "Couldn't return to the original window. The edited text is on the clipboard.",
+                RecoveryAction::RetryPaste { app: Some(app.clone()), text: text.to_string() },
+            );
+            return Ok(SessionOutcome::TargetLost);
+        }
+    } else {
+        // Fallback: small delay hoping focus returns naturally
+        thread::sleep(Duration::from_millis(100));
+    }
+
+    // Some apps swallow keystrokes that arrive right after they're activated
+    let bundle_id = app.map(|app| app.bundle_id.as_str());
+    let paste_delay = config.paste_delay_ms(bundle_id);
+    if paste_delay > 0 {
+        log::debug!("Waiting {}ms before pasting", paste_delay);
+        thread::sleep(Duration::from_millis(paste_delay));
+    }
+
+    // In append mode, collapse the selection to its end so the original stays
+    if config.session.output_mode == OutputMode::Append {
+        keystroke::simulate_right_arrow()
+            .context("Failed to move the cursor after the selection")?;
+    }
+
+    // Step 11: Simulate Cmd+V (or the app's paste combo) to paste, or type the text
+    if config.session.paste_mode == PasteMode::Type && keys::can_type(text) {
+        keystroke::type_text(text).context("Failed to type the edited text")?;
+    } else {
+        if config.session.paste_mode == PasteMode::Type {
+            log::info!("Edited text has characters that can't be typed, pasting it instead");
+        }
+        let combo = config.shortcuts.paste_for(bundle_id);
+        match config.session.paste_method {
+            PasteMethod::CgEvent => keystroke::simulate_paste(combo),
+            PasteMethod::AppleScript => keystroke::simulate_paste_applescript(combo),
+        }
+        .context("Failed to simulate paste")?;
+    }
+
+    if config.session.reselect_after_paste {
+        reselect_pasted(text)?;
+    }
+
+    Ok(SessionOutcome::Completed)
+}
+
+/// Pastes each save of the edited text over the previously pasted one (`paste_on_save`)
+struct PasteOnSave<'a> {
+    /// The session's config, pasting over what was pasted before
+    config: Config,
+    app: Option<&'a FrontmostApp>,
+    clipboard: &'a dyn ClipboardAccess,
+    /// Text pasted by the last save
+    pasted: Option<String>,
+}
+
+impl<'a> PasteOnSave<'a> {
+    fn new(config: &Config, app: Option<&'a FrontmostApp>, clipboard: &'a dyn ClipboardAccess) -> Self {
+        let mut config = config.clone();
+        // Keep the pasted text selected, so the next save replaces it
+        config.session.reselect_after_paste = true;
+        Self {
+            config,
+            app,
+            clipboard,
+            pasted: None,
+        }
+    }
+
+    /// Paste the saved text, replacing the text pasted by the previous save
+    fn paste(&mut self, text: &str) -> Result<SessionOutcome> {
+        if self.pasted.as_deref() == Some(text) {
+            return Ok(SessionOutcome::Completed);
+        }
+
+        self.clipboard.set_text(text)
+            .context("Failed to set clipboard with edited text")?;
+
+        // Too long to have been selected again, pasting would add a second copy
+        if self.pasted.as_deref().is_some_and(|pasted| reselect_length(pasted) > MAX_RESELECT_CHARS) {
+            log::warn!("Previously pasted text wasn't reselected, leaving this save on the clipboard");
+            menu_bar::notify(
+                NotificationEvent::EditError,
+                "The text is too long to replace again. The edited text is on the clipboard.",
+            );
+            return Ok(SessionOutcome::TargetLost);
+        }
+
+        log::info!("Pasting saved text ({} characters)", text.len());
+        let outcome = paste_back(&self.config, self.app, text)?;
+        if outcome == SessionOutcome::Completed {
+            self.pasted = Some(text.to_string());
+            // Later saves replace the appended text instead of appending again
+            if self.config.session.output_mode == OutputMode::Append {
+                self.config.session.output_mode = OutputMode::Replace;
+            }
+        }
+        Ok(outcome)
+    }
+}
+
+/// Number of cursor positions the text takes up (line breaks count once, even as "\r\n")
+fn reselect_length(text: &str) -> usize {
+    text.replace("\r\n", "\n").chars().count()
+}
+
+/// Select the text that was just pasted, assuming the cursor sits at its end
+///
+/// Moves back one character at a time, so very long texts are left alone.
+fn reselect_pasted(text: &str) -> Result<()> {
+    let length = reselect_length(text);
+    if length > MAX_RESELECT_CHARS {
+        log::info!("Pasted text too long to reselect ({} characters)", length);
         return Ok(());
     }
 
-    log::info!("Captured {} characters of selected text", selected_text.len());
+    // Let the app insert the text before moving the cursor over it
+    thread::sleep(Duration::from_millis(100));
+    keystroke::simulate_select_left(length).context("Failed to reselect the pasted text")
+}
 
-    // Step 4: Create temp file with the selected text
-    let mut temp_file = NamedTempFile::with_suffix(".txt")
-        .context("Failed to create temp file")?;
+/// Edit text in the configured terminal and editor
+///
+/// Writes the text to a temp file, opens it in the editor and waits for the
+/// user to finish. Returns the edited text, or `None` if it was left unchanged
+/// (e.g. the user quit without saving). Doesn't touch the clipboard, so it can
+/// be used by integrations that provide the text themselves.
+///
+/// `extension` sets the temp file's extension so the editor can pick the
+/// right syntax highlighting; it defaults to `editor.extension`, or `txt`.
+pub fn edit_text(config: &Config, text: &str, extension: Option<&str>) -> Result<Option<String>> {
+    edit_text_watching(config, text, extension, None, None)
+}
 
-    temp_file
-        .write_all(selected_text.as_bytes())
-        .context("Failed to write to temp file")?;
+/// Edit text like [`edit_text`], calling `on_save` with the text each time it's saved
+///
+/// `source_app` is the name of the app the text came from, for the window title.
+fn edit_text_watching(
+    config: &Config,
+    text: &str,
+    extension: Option<&str>,
+    source_app: Option<&str>,
+    on_save: Option<&mut dyn FnMut(&str)>,
+) -> Result<Option<String>> {
+    // Step 4: Create temp file with the text
+    // The extension comes from window titles and URLs, so keep file names sane
+    let extension = extension
+        .or_else(|| config.editor.extension.as_deref().map(|ext| ext.trim_start_matches('.')))
+        .filter(|ext| ext.len() <= MAX_EXTENSION_LEN && ext.chars().all(|c| c.is_ascii_alphanumeric()));
+    let suffix = format!(".{}", extension.unwrap_or("txt"));
+    let longest_line = text.lines().map(str::len).max().unwrap_or(0);
+    if longest_line > LONG_LINE_BYTES {
+        log::info!("Selection has a {} byte line; it is passed to the editor as a file", longest_line);
+    }
+    let mut temp_file = create_temp_file(&suffix)?;
 
-    temp_file
-        .flush()
-        .context("Failed to flush temp file")?;
+    write_temp_file(&mut temp_file, text)?;
+
+    // The editor is a separate process; make sure it sees the whole text
+    if fs::read(temp_file.path()).ok().as_deref() != Some(text.as_bytes()) {
+        log::warn!("Temp file content doesn't match the selection, writing it again");
+        write_temp_file(&mut temp_file, text)?;
+        if fs::read(temp_file.path()).ok().as_deref() != Some(text.as_bytes()) {
+            bail!("Failed to write the selection to the temp file");
+        }
+    }
 
     let temp_path = temp_file.path().to_path_buf();
     log::info!("Created temp file: {:?}", temp_path);
+    let _active = ActiveSession::start(&temp_path);
+
+    let mut options = LaunchOptions::from_config(config);
+    if config.terminal.auto_size {
+        // A fitted window isn't a size to remember
+        let (width, height) = terminal::window_size_for(text, &config.terminal);
+        (options.width, options.height) = terminal::clamp_window_size(width, height);
+        log::debug!("Sized the window to the text: {}x{}", options.width, options.height);
+    } else if config.terminal.remember_size {
+        options.size_report = Some(temp_path.with_extension("size"));
+    }
+    if config.terminal.set_window_title {
+        options.window_title = Some(terminal::window_title(source_app, &local_time()));
+    }
+
+    // Steps 5-6: Launch the terminal and wait for the editor to finish
+    let launch_error = match on_save {
+        Some(on_save) => open_and_watch(config, &temp_path, text, options, on_save)?,
+        None => open_and_wait(config, &temp_path, options)?,
+    };
+
+    // Step 7: Read the edited content
+    let edited_text = read_edited_file(&temp_path)?;
+
+    // Helix adds a trailing newline when saving
+    let edited_text = apply_trailing_newline(&edited_text, text, config.session.trailing_newline);
+
+    // Step 8: Check if content changed
+    if !content_changed(text, &edited_text, &config.session) {
+        // An unchanged file after a failed terminal most likely means it never opened
+        if let Some(error) = launch_error {
+            bail!(error);
+        }
+        return Ok(None);
+    }
+
+    Ok(Some(edited_text))
+}
+
+/// The local time of day as `HH:MM`
+fn local_time() -> String {
+    let now = SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs()) as libc::time_t;
+    let tm = unsafe {
+        let mut tm: libc::tm = std::mem::zeroed();
+        libc::localtime_r(&now, &mut tm);
+        tm
+    };
+    format!("{:02}:{:02}", tm.tm_hour, tm.tm_min)
+}
+
+/// Open a file like [`open_and_wait`], calling `on_save` with its text after every save
+///
+/// `original` is the text the file started with, used to apply the
+/// trailing newline setting and to skip saves that changed nothing.
+fn open_and_watch(
+    config: &Config,
+    path: &Path,
+    original: &str,
+    options: LaunchOptions,
+    on_save: &mut dyn FnMut(&str),
+) -> Result<Option<String>> {
+    let poll_interval = Duration::from_millis(config.timing.file_poll_ms);
+    thread::scope(|scope| {
+        let editor = scope.spawn(|| open_and_wait(config, path, options));
+
+        let mut last_stamp = FileStamp::of(path);
+        let mut last_text = original.to_string();
+        loop {
+            let finished = editor.is_finished();
+
+            let stamp = FileStamp::of(path);
+            if stamp.is_some() && stamp != last_stamp {
+                last_stamp = stamp;
+                if let Ok(saved) = read_edited_file(path) {
+                    let saved = apply_trailing_newline(&saved, original, config.session.trailing_newline);
+                    if content_changed(&last_text, &saved, &config.session) {
+                        on_save(&saved);
+                        last_text = saved;
+                    }
+                }
+            }
 
-    // Store original content hash for comparison
-    let original_hash = hash_content(&selected_text);
+            if finished {
+                break;
+            }
+            file_watch::wait_for_change(&watched_paths(path, None), poll_interval);
+        }
+
+        editor
+            .join()
+            .unwrap_or_else(|_| bail!("Editor wait thread panicked"))
+    })
+}
 
+/// Open a file in the configured terminal and editor and wait until the user is done
+///
+/// Returns the terminal's error output if it exited with an error, which
+/// usually means the editor never opened.
+pub fn open_and_wait(
+    config: &Config,
+    path: &Path,
+    mut options: LaunchOptions,
+) -> Result<Option<String>> {
     // Step 5: Launch terminal with helix
     let terminal = Terminal::from_name(&config.terminal.name)
         .context("Invalid terminal name in config")?;
@@ -114,98 +1250,312 @@ pub fn run_edit_session(config: &Config) -> Result<()> {
         );
     }
 
-    log::info!("Launching {} with helix", terminal.display_name());
+    log::info!("Launching {} with {}", terminal.display_name(), editor_name(&options));
+
+    // Get the file's version before launch (for polling-based terminals)
+    let original_stamp = FileStamp::of(path);
 
-    // Get file modification time before launch (for polling-based terminals)
-    let original_mtime = fs::metadata(&temp_path)
-        .and_then(|m| m.modified())
-        .unwrap_or_else(|_| SystemTime::now());
+    if !terminal.supports_size_report() {
+        options.size_report = None;
+    }
 
-    let mut child = terminal
-        .launch(&temp_path, config.terminal.width, config.terminal.height)
+    // Polled terminals can't be waited on; the editor's command creates this
+    // marker when it exits, so quitting without saving is noticed too
+    let needs_polling = terminal_probe::needs_polling(config, terminal);
+    if needs_polling && options.exit_marker.is_none() {
+        options.exit_marker = Some(new_exit_marker()?);
+    }
+
+    // Helix reads its config at startup, so the copy can go once the session ends
+    let _wrap_config = if config.editor.wrap_to_window {
+        wrap_config(config, &mut options)
+    } else {
+        None
+    };
+
+    // Ghostty's script stays until the editor is done, and goes on every return
+    let LaunchedTerminal { mut child, script: _script } = terminal
+        .launch(path, &options)
         .context("Failed to launch terminal")?;
 
+    // Stderr of a terminal that exited with an error, reported if nothing was edited
+    let mut launch_error = None;
+
     // Step 6: Wait for terminal/helix to exit
-    if terminal.needs_polling() {
+    if needs_polling {
         // For terminals launched via AppleScript or `open`, we can't wait on the child
         // Instead, poll the file for changes
         log::info!("Using file polling to detect edit completion (terminal uses AppleScript/open)");
-        wait_for_file_change(&temp_path, original_mtime)?;
-        log::info!("File change detected, edit session complete");
+        let launch_timeout = Duration::from_millis(config.timing.launch_timeout_ms);
+        terminal::wait_for_launcher(&mut child, launch_timeout)?;
+        let poll_interval = Duration::from_millis(config.timing.file_poll_ms);
+        let marker = options.exit_marker.as_deref();
+        let result = match marker {
+            // Saves are picked up as they happen, only the editor closing ends the wait
+            Some(marker) if config.session.paste_on_save => wait_for_exit_marker(marker, poll_interval),
+            _ => wait_for_file_change(path, original_stamp, marker, poll_interval),
+        };
+        if let Some(marker) = marker {
+            let _ = fs::remove_file(marker);
+        }
+        result?;
+        log::info!("Edit session complete");
     } else {
         // For terminals with proper CLI support, we can wait on the child process
-        let status = child.wait().context("Failed to wait for terminal")?;
-        log::info!("Terminal exited with status: {:?}", status);
+        let output = child
+            .wait_with_output()
+            .context("Failed to wait for terminal")?;
+        log::info!("Terminal exited with status: {:?}", output.status);
+
+        let stderr = stderr_summary(&output.stderr);
+        if !output.status.success() {
+            log::warn!("{} failed: {}", terminal.display_name(), stderr);
+            launch_error = Some(format!(
+                "{} exited with {}: {}",
+                terminal.display_name(),
+                output.status,
+                stderr
+            ));
+        } else if !stderr.is_empty() {
+            log::debug!("{} stderr: {}", terminal.display_name(), stderr);
+        }
+
+        if let Some(ref report) = options.size_report {
+            remember_window_size(config, report);
+        }
     }
 
-    // Step 7: Read the edited content
-    let edited_text = fs::read_to_string(&temp_path)
-        .context("Failed to read edited file")?;
+    Ok(launch_error)
+}
 
-    // Trim trailing newline that Helix adds when saving
-    let edited_text = edited_text.trim_end_matches('\n').to_string();
+/// Generate a Helix config that soft-wraps at the window width and pass it with `-c`
+///
+/// The file is removed when the returned handle is dropped. Nothing is
+/// generated for other editors or when `startup_args` already pick a config.
+fn wrap_config(config: &Config, options: &mut LaunchOptions) -> Option<NamedTempFile> {
+    let is_helix = terminal::resolve_editor(&options.editors).is_ok_and(|editor| terminal::is_helix(&editor));
+    if !is_helix {
+        log::debug!("Not wrapping to the window: the editor isn't Helix");
+        return None;
+    }
+    if options.editor_args.iter().any(|arg| arg == "-c" || arg == "--config") {
+        log::debug!("Not wrapping to the window: startup_args already set a Helix config");
+        return None;
+    }
 
-    let edited_hash = hash_content(&edited_text);
+    let user_path = config
+        .editor
+        .config_path
+        .clone()
+        .or_else(|| helix_config::user_config_path(config.editor.helix_config_dir.as_deref()));
+    let user_config = user_path
+        .and_then(|path| fs::read_to_string(path).ok())
+        .unwrap_or_default();
 
-    // Step 8: Check if content changed
-    if original_hash == edited_hash {
-        log::info!("Content unchanged, not pasting back (user likely aborted)");
-        // Restore original clipboard
-        if let Some(orig) = original_clipboard {
-            let _ = clipboard::set_text(&orig);
+    let generated = match helix_config::with_soft_wrap(&user_config, options.width) {
+        Ok(generated) => generated,
+        Err(e) => {
+            log::warn!("Not wrapping to the window: {:#}", e);
+            return None;
         }
-        return Ok(());
-    }
+    };
 
-    log::info!("Content changed, pasting back {} characters", edited_text.len());
+    let file = tempfile::Builder::new()
+        .prefix(temp_files::PREFIX)
+        .suffix(".toml")
+        .tempfile()
+        .and_then(|mut file| file.write_all(generated.as_bytes()).map(|_| file));
+    match file {
+        Ok(file) => {
+            log::debug!("Helix config for this session: {:?}", file.path());
+            // In place of editor.config_path, which it's a copy of
+            options.helix_config = Some(file.path().to_path_buf());
+            Some(file)
+        }
+        Err(e) => {
+            log::warn!("Failed to write the Helix config for this session: {}", e);
+            None
+        }
+    }
+}
 
-    // Step 9: Put edited text in clipboard
-    clipboard::set_text(&edited_text)
-        .context("Failed to set clipboard with edited text")?;
+/// Create the temp file to edit, readable and writable by the user only
+///
+/// Fails if the editor couldn't save to it: it would open the file read-only,
+/// and the session would end as if nothing was changed.
+fn create_temp_file(suffix: &str) -> Result<NamedTempFile> {
+    let temp_file = tempfile::Builder::new()
+        .prefix(temp_files::PREFIX)
+        .suffix(suffix)
+        .permissions(fs::Permissions::from_mode(0o600))
+        .tempfile()
+        .context("Failed to create temp file")?;
+    ensure_writable(temp_file.path())?;
+    Ok(temp_file)
+}
 
-    // Step 10: Return focus to the original app
-    if let Some(ref app_id) = original_app {
-        log::info!("Restoring focus to original app: {}", app_id);
-        activate_app(app_id)?;
-    } else {
-        // Fallback: small delay hoping focus returns naturally
-        thread::sleep(Duration::from_millis(100));
+/// Check that the user can write to a file
+fn ensure_writable(path: &Path) -> Result<()> {
+    let mode = fs::metadata(path)
+        .with_context(|| format!("Failed to read the permissions of {:?}", path))?
+        .permissions()
+        .mode();
+    if mode & 0o200 == 0 {
+        bail!("The temp file {:?} isn't writable (mode {:o}), the editor couldn't save it", path, mode & 0o777);
     }
+    fs::OpenOptions::new()
+        .write(true)
+        .open(path)
+        .with_context(|| format!("The temp file {:?} isn't writable, the editor couldn't save it", path))?;
+    Ok(())
+}
 
-    // Step 11: Simulate Cmd+V to paste
-    keystroke::simulate_paste()
-        .context("Failed to simulate paste")?;
+/// Write the text to the temp file (replacing any content) and sync it to disk
+fn write_temp_file(temp_file: &mut NamedTempFile, text: &str) -> Result<()> {
+    let file = temp_file.as_file_mut();
+    file.set_len(0).context("Failed to truncate temp file")?;
+    file.seek(SeekFrom::Start(0)).context("Failed to rewind temp file")?;
 
-    log::info!("Edit session completed successfully");
+    file.write_all(text.as_bytes())
+        .context("Failed to write to temp file")?;
+
+    file.flush()
+        .context("Failed to flush temp file")?;
+
+    file.sync_all()
+        .context("Failed to sync temp file")?;
     Ok(())
 }
 
-/// Simple hash function for content comparison
-fn hash_content(content: &str) -> u64 {
-    use std::collections::hash_map::DefaultHasher;
-    use std::hash::{Hash, Hasher};
+/// Save the window size reported by the terminal if the user resized it
+fn remember_window_size(config: &Config, report: &Path) {
+    let size = terminal::read_size_report(report);
+    let _ = fs::remove_file(report);
 
-    let mut hasher = DefaultHasher::new();
-    content.hash(&mut hasher);
-    hasher.finish()
+    let Some((width, height)) = size else {
+        log::debug!("Terminal didn't report its window size");
+        return;
+    };
+    if (width, height) != (config.terminal.width, config.terminal.height) {
+        log::info!("Edit window resized to {}x{}, saving", width, height);
+        menu_bar::save_window_size(width, height);
+    }
 }
 
-/// Check if any process has the file open (using lsof)
-fn is_file_open(path: &Path) -> bool {
-    Command::new("lsof")
-        .arg(path)
-        .output()
-        .map(|o| o.status.success())
-        .unwrap_or(false)
+/// Last few lines of a process's stderr, for error messages
+fn stderr_summary(stderr: &[u8]) -> String {
+    const MAX_LINES: usize = 10;
+
+    let stderr = String::from_utf8_lossy(stderr);
+    let lines: Vec<&str> = stderr.trim().lines().collect();
+    lines[lines.len().saturating_sub(MAX_LINES)..].join("\n")
+}
+
+/// Put the clipboard content saved before the session back, if there was any
+pub fn restore_clipboard(clipboard: &dyn ClipboardAccess, original: Option<String>) {
+    if let Some(orig) = original {
+        let _ = clipboard.set_text(&orig);
+    }
+}
+
+/// Puts the clipboard content saved before a session back when dropped, so
+/// an error (or panic) partway through doesn't leave the selection on it
+pub struct ClipboardGuard<'a> {
+    clipboard: &'a dyn ClipboardAccess,
+    original: Option<String>,
+}
+
+impl<'a> ClipboardGuard<'a> {
+    pub fn new(clipboard: &'a dyn ClipboardAccess, original: Option<String>) -> Self {
+        Self { clipboard, original }
+    }
+
+    /// The saved clipboard content
+    pub fn original(&self) -> Option<&str> {
+        self.original.as_deref()
+    }
+
+    /// Put the saved content back now
+    pub fn restore(self) {}
+
+    /// Keep the clipboard as it is, handing back the saved content
+    pub fn disarm(mut self) -> Option<String> {
+        self.original.take()
+    }
+}
+
+impl Drop for ClipboardGuard<'_> {
+    fn drop(&mut self) {
+        restore_clipboard(self.clipboard, self.original.take());
+    }
+}
+
+/// Unique path in the temp dir for an exit marker (the file isn't created)
+fn new_exit_marker() -> Result<PathBuf> {
+    let marker = tempfile::Builder::new()
+        .prefix(temp_files::PREFIX)
+        .suffix(".done")
+        .tempfile()
+        .context("Failed to create exit marker")?;
+    let path = marker.path().to_path_buf();
+    marker.close().context("Failed to create exit marker")?;
+    Ok(path)
+}
+
+/// A version of a file, to notice saves
+///
+/// Besides the modification time, the inode is compared: editors saving
+/// atomically and sync clients (iCloud Drive, Dropbox) replace the file
+/// instead of writing to it, and the replacement may carry an older mtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    device: u64,
+    inode: u64,
+    mtime: Option<SystemTime>,
+    len: u64,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> Option<Self> {
+        use std::os::unix::fs::MetadataExt;
+
+        let metadata = fs::metadata(path).ok()?;
+        Some(FileStamp {
+            device: metadata.dev(),
+            inode: metadata.ino(),
+            mtime: metadata.modified().ok(),
+            len: metadata.len(),
+        })
+    }
+}
+
+/// File name of the editor a session will run, for logs
+fn editor_name(options: &LaunchOptions) -> String {
+    terminal::resolve_editor(&options.editors)
+        .ok()
+        .and_then(|editor| editor.file_name().map(|name| name.to_string_lossy().to_string()))
+        .unwrap_or_else(|| "the editor".to_string())
 }
 
 /// Wait for the file to be modified or for the editor to close
 /// This is used for terminals that can't be waited on directly (Ghostty, iTerm, Terminal.app)
-fn wait_for_file_change(path: &Path, original_mtime: SystemTime) -> Result<()> {
-    const POLL_INTERVAL: Duration = Duration::from_millis(100);
+///
+/// The editor closing is detected through `exit_marker`, which the launch
+/// command creates once the editor exits. A file that disappears is given
+/// `REPLACE_GRACE` to come back, as it does while being replaced.
+fn wait_for_file_change(
+    path: &Path,
+    original: Option<FileStamp>,
+    exit_marker: Option<&Path>,
+    poll_interval: Duration,
+) -> Result<()> {
     const TIMEOUT: Duration = Duration::from_secs(3600); // 1 hour timeout
+    const REPLACE_GRACE: Duration = Duration::from_secs(2);
 
     let start = std::time::Instant::now();
+    let mut missing_since = None;
+    let watched = watched_paths(path, exit_marker);
+    let check_interval = file_watch::check_interval(poll_interval);
 
     // Small delay to let the terminal open and helix to start
     thread::sleep(Duration::from_millis(500));
@@ -216,30 +1566,159 @@ fn wait_for_file_change(path: &Path, original_mtime: SystemTime) -> Result<()> {
             bail!("Timeout waiting for edit to complete (1 hour)");
         }
 
-        // Check if file was modified
-        match fs::metadata(path) {
-            Ok(metadata) => {
-                if let Ok(mtime) = metadata.modified() {
-                    if mtime > original_mtime {
-                        // File was modified - user saved
-                        return Ok(());
-                    }
+        // Check if file was modified or replaced
+        match FileStamp::of(path) {
+            Some(stamp) => {
+                missing_since = None;
+                if Some(stamp) != original {
+                    // File was modified - user saved
+                    return Ok(());
                 }
             }
-            Err(_) => {
-                // File was deleted - user quit without saving or something went wrong
+            None => {
+                // File is gone - user quit without saving or something went wrong
                 // We'll let the caller handle this (it will fail to read the file)
-                return Ok(());
+                let since = *missing_since.get_or_insert_with(Instant::now);
+                if since.elapsed() >= REPLACE_GRACE {
+                    return Ok(());
+                }
             }
         }
 
-        // Check if helix/editor still has the file open
-        // If not, the user closed the editor without saving (:q!)
-        if !is_file_open(path) {
+        // The editor exited without modifying the file (user likely used :q!)
+        if exit_marker.is_some_and(|marker| marker.exists()) {
             log::info!("Editor closed without modifying file (user likely used :q!)");
             return Ok(());
         }
 
-        thread::sleep(POLL_INTERVAL);
+        file_watch::wait_for_change(&watched, check_interval);
+    }
+}
+
+/// Paths whose changes reveal a save or the editor closing: the file and
+/// its directory (for saves replacing it), and the exit marker's directory
+fn watched_paths<'a>(path: &'a Path, exit_marker: Option<&'a Path>) -> Vec<&'a Path> {
+    std::iter::once(path)
+        .chain(path.parent())
+        .chain(exit_marker.and_then(Path::parent))
+        .collect()
+}
+
+/// Wait until the editor's command creates its exit marker
+pub(crate) fn wait_for_exit_marker(marker: &Path, poll_interval: Duration) -> Result<()> {
+    const TIMEOUT: Duration = Duration::from_secs(3600); // 1 hour timeout
+
+    let start = std::time::Instant::now();
+    let watched: Vec<&Path> = marker.parent().into_iter().collect();
+    while !marker.exists() {
+        if start.elapsed() > TIMEOUT {
+            bail!("Timeout waiting for the editor to close (1 hour)");
+        }
+        file_watch::wait_for_change(&watched, file_watch::check_interval(poll_interval));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clipboard::mock::MemoryClipboard;
+
+    #[test]
+    fn restore_clipboard_puts_original_back() {
+        let clipboard = MemoryClipboard::with_text("selection");
+        restore_clipboard(&clipboard, Some("original".to_string()));
+        assert_eq!(clipboard.contents().as_deref(), Some("original"));
+    }
+
+    #[test]
+    fn clipboard_is_restored_when_the_session_fails() {
+        // The copy put the selection on the clipboard, then the editor can't be launched
+        let mut config = Config::default();
+        config.terminal.name = "no-such-terminal".to_string();
+        let clipboard = MemoryClipboard::with_text("selection");
+        let original_clipboard = ClipboardGuard::new(&clipboard, Some("original".to_string()));
+        let selected_text = "selection".to_string();
+        assert!(edit_copied_selection(&config, &clipboard, None, original_clipboard, selected_text).is_err());
+        assert_eq!(clipboard.contents().as_deref(), Some("original"));
+
+        let guard = ClipboardGuard::new(&clipboard, Some("original".to_string()));
+        clipboard.set_text("result").unwrap();
+        assert_eq!(guard.disarm().as_deref(), Some("original"));
+        assert_eq!(clipboard.contents().as_deref(), Some("result"));
+    }
+
+    #[test]
+    fn replacing_a_file_changes_its_stamp() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("edit.txt");
+        fs::write(&path, "before").unwrap();
+        let original = FileStamp::of(&path);
+        let mtime = fs::metadata(&path).unwrap().modified().unwrap();
+
+        // As a sync client does: same length and mtime, but a new file
+        let replacement = dir.path().join("edit.txt.sync");
+        fs::write(&replacement, "after!").unwrap();
+        fs::File::options().write(true).open(&replacement).unwrap().set_modified(mtime).unwrap();
+        fs::rename(&replacement, &path).unwrap();
+
+        assert!(original.is_some());
+        assert_ne!(FileStamp::of(&path), original);
+        wait_for_file_change(&path, original, None, Duration::from_millis(10)).unwrap();
+        assert_eq!(FileStamp::of(&dir.path().join("gone.txt")), None);
+    }
+
+    #[test]
+    fn temp_files_are_created_writable() {
+        let temp_file = create_temp_file(".txt").unwrap();
+        let mode = fs::metadata(temp_file.path()).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        fs::set_permissions(temp_file.path(), fs::Permissions::from_mode(0o400)).unwrap();
+        assert!(ensure_writable(temp_file.path()).is_err());
+    }
+
+    #[test]
+    fn extension_is_found_in_window_titles() {
+        assert_eq!(extension_from_title("main.rs — helix-anywhere").as_deref(), Some("rs"));
+        assert_eq!(
+            extension_from_title("● config.toml - project - Visual Studio Code").as_deref(),
+            Some("toml")
+        );
+        assert_eq!(extension_from_title("README.md (~/code)").as_deref(), Some("md"));
+        assert_eq!(extension_from_title("Inbox - Mail"), None);
+        assert_eq!(extension_from_title("Version 1.2"), None);
+        assert_eq!(extension_from_title("Loading..."), None);
+    }
+
+    #[test]
+    fn restore_clipboard_without_original_leaves_content() {
+        let clipboard = MemoryClipboard::with_text("selection");
+        restore_clipboard(&clipboard, None);
+        assert_eq!(clipboard.contents().as_deref(), Some("selection"));
+    }
+
+    #[test]
+    fn reselect_length_counts_line_breaks_once() {
+        assert_eq!(reselect_length("ab\r\ncd\né"), 7);
+    }
+
+    #[test]
+    fn smart_target_detects_urls_and_existing_files() {
+        assert_eq!(
+            smart_target(" https://example.com/a.rs\n"),
+            Some(SmartTarget::Url("https://example.com/a.rs".to_string()))
+        );
+        assert_eq!(smart_target("see https://example.com"), None);
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let path = file.path().to_path_buf();
+        assert_eq!(
+            smart_target(path.to_str().unwrap()),
+            Some(SmartTarget::File(path.clone()))
+        );
+        assert_eq!(smart_target(&format!("{}\nmore", path.display())), None);
+        assert_eq!(smart_target("/no/such/file"), None);
+        assert_eq!(smart_target("relative/path"), None);
     }
 }
@@ -0,0 +1,93 @@
+//! Waiting for files to change without polling them
+//!
+//! On macOS, a kqueue reports writes to the watched paths. Watching a file's
+//! directory as well catches saves that replace the file (editors writing a
+//! temp file and renaming it over, sync clients), which leave a watch on the
+//! file itself pointing at the old one. Elsewhere this just sleeps, so
+//! callers keep checking the files themselves after each wait.
+
+use std::path::Path;
+use std::time::Duration;
+
+/// Longest wait between checks when changes wake the waiter up
+#[cfg(target_os = "macos")]
+const WATCHED_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Time to wait for a change before checking again anyway
+///
+/// Watched changes wake the waiter right away, so checks only need to be as
+/// frequent as `poll_interval` without a watcher.
+#[cfg(target_os = "macos")]
+pub fn check_interval(poll_interval: Duration) -> Duration {
+    poll_interval.max(WATCHED_CHECK_INTERVAL)
+}
+
+/// Time to wait for a change before checking again anyway
+#[cfg(not(target_os = "macos"))]
+pub fn check_interval(poll_interval: Duration) -> Duration {
+    poll_interval
+}
+
+/// Block until one of `paths` (files or directories) changes, or `timeout` passes
+///
+/// Paths that don't exist are skipped. Changes made before the call aren't
+/// reported, so check the files before waiting.
+#[cfg(target_os = "macos")]
+pub fn wait_for_change(paths: &[&Path], timeout: Duration) {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    unsafe {
+        let kq = libc::kqueue();
+        if kq < 0 {
+            log::debug!("kqueue unavailable, sleeping instead");
+            std::thread::sleep(timeout);
+            return;
+        }
+
+        let mut fds = Vec::new();
+        let mut changes = Vec::new();
+        for path in paths {
+            let Ok(c_path) = CString::new(path.as_os_str().as_bytes()) else {
+                continue;
+            };
+            let fd = libc::open(c_path.as_ptr(), libc::O_EVTONLY);
+            if fd < 0 {
+                continue;
+            }
+            fds.push(fd);
+            changes.push(libc::kevent {
+                ident: fd as libc::uintptr_t,
+                filter: libc::EVFILT_VNODE,
+                flags: libc::EV_ADD | libc::EV_CLEAR,
+                fflags: libc::NOTE_WRITE
+                    | libc::NOTE_EXTEND
+                    | libc::NOTE_ATTRIB
+                    | libc::NOTE_DELETE
+                    | libc::NOTE_RENAME,
+                data: 0,
+                udata: std::ptr::null_mut(),
+            });
+        }
+
+        let timeout = libc::timespec {
+            tv_sec: timeout.as_secs() as _,
+            tv_nsec: timeout.subsec_nanos() as _,
+        };
+        let mut event: libc::kevent = std::mem::zeroed();
+        libc::kevent(kq, changes.as_ptr(), changes.len() as libc::c_int, &mut event, 1, &timeout);
+
+        for fd in fds {
+            libc::close(fd);
+        }
+        libc::close(kq);
+    }
+}
+
+/// Block until one of `paths` changes, or `timeout` passes
+///
+/// Without kqueue, this sleeps for `timeout`.
+#[cfg(not(target_os = "macos"))]
+pub fn wait_for_change(_paths: &[&Path], timeout: Duration) {
+    std::thread::sleep(timeout);
+}
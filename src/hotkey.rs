@@ -1,103 +1,297 @@
-use crate::config::HotkeyConfig;
+use crate::config::{HotkeyConfig, ModifierMatch, TimingConfig};
+use crate::keys::{hotkey_chords, variant_modifiers, HotkeyAction};
 use anyhow::{Context, Result};
 use core_foundation::runloop::{kCFRunLoopCommonModes, kCFRunLoopDefaultMode, CFRunLoop};
 use core_graphics::event::{CGEventTapLocation, CGEventType};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::mpsc::{channel, Sender};
-use std::sync::Arc;
-
-// macOS virtual key codes for common keys
-pub fn key_code_from_string(key: &str) -> Option<u16> {
-    match key.to_lowercase().as_str() {
-        "a" => Some(0x00),
-        "s" => Some(0x01),
-        "d" => Some(0x02),
-        "f" => Some(0x03),
-        "h" => Some(0x04),
-        "g" => Some(0x05),
-        "z" => Some(0x06),
-        "x" => Some(0x07),
-        "c" => Some(0x08),
-        "v" => Some(0x09),
-        "b" => Some(0x0B),
-        "q" => Some(0x0C),
-        "w" => Some(0x0D),
-        "e" => Some(0x0E),
-        "r" => Some(0x0F),
-        "y" => Some(0x10),
-        "t" => Some(0x11),
-        "1" => Some(0x12),
-        "2" => Some(0x13),
-        "3" => Some(0x14),
-        "4" => Some(0x15),
-        "6" => Some(0x16),
-        "5" => Some(0x17),
-        "=" => Some(0x18),
-        "9" => Some(0x19),
-        "7" => Some(0x1A),
-        "-" => Some(0x1B),
-        "8" => Some(0x1C),
-        "0" => Some(0x1D),
-        "]" => Some(0x1E),
-        "o" => Some(0x1F),
-        "u" => Some(0x20),
-        "[" => Some(0x21),
-        "i" => Some(0x22),
-        "p" => Some(0x23),
-        "l" => Some(0x25),
-        "j" => Some(0x26),
-        "'" | "quote" => Some(0x27),
-        "k" => Some(0x28),
-        ";" | "semicolon" => Some(0x29),
-        "\\" | "backslash" => Some(0x2A),
-        "," | "comma" => Some(0x2B),
-        "/" | "slash" => Some(0x2C),
-        "n" => Some(0x2D),
-        "m" => Some(0x2E),
-        "." | "period" => Some(0x2F),
-        "`" | "grave" | "backtick" => Some(0x32),
-        "space" => Some(0x31),
-        "return" | "enter" => Some(0x24),
-        "tab" => Some(0x30),
-        "delete" | "backspace" => Some(0x33),
-        "escape" | "esc" => Some(0x35),
-        _ => None,
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::cell::Cell;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Check whether a KeyDown event was generated by holding the key (auto-repeat)
+pub fn is_autorepeat(event: &core_graphics::event::CGEvent) -> bool {
+    event.get_integer_value_field(core_graphics::event::EventField::KEYBOARD_EVENT_AUTOREPEAT)
+        != 0
+}
+
+/// Default time a listener run loop sleeps before waking on its own
+const DEFAULT_RUN_LOOP_WAIT: Duration = Duration::from_secs(1);
+
+/// Default minimum time between two triggers
+const DEFAULT_TRIGGER_COOLDOWN: Duration = Duration::from_millis(300);
+
+/// Default time allowed between the chords of a hotkey sequence
+const DEFAULT_SEQUENCE_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// Suppresses triggers that arrive within a cooldown of the previous one.
+///
+/// Holding the chord makes the tap deliver repeated KeyDown events; without
+/// this, each of them queues another edit session.
+struct TriggerDebounce {
+    cooldown: Duration,
+    last_trigger: Cell<Option<Instant>>,
+}
+
+impl TriggerDebounce {
+    fn new(cooldown: Duration) -> Self {
+        Self {
+            cooldown,
+            last_trigger: Cell::new(None),
+        }
+    }
+
+    /// Record a trigger, returning false if it falls within the cooldown
+    fn allow(&self) -> bool {
+        let now = Instant::now();
+        let allowed = self
+            .last_trigger
+            .get()
+            .is_none_or(|last| now.duration_since(last) >= self.cooldown);
+        self.last_trigger.set(Some(now));
+        allowed
+    }
+}
+
+/// What an event tap should do with a key event
+enum TapAction {
+    /// Not part of the hotkey, let it through
+    PassThrough,
+    /// Part of the hotkey, swallow it without triggering
+    Consume,
+    /// The hotkey was completed, swallow it and trigger
+    Trigger,
+}
+
+/// Matches KeyDown events against a hotkey's chords inside an event tap.
+///
+/// For leader sequences, progress advances on each matching chord and resets
+/// on a mismatch or when the next chord doesn't arrive within the timeout.
+///
+/// With a long press, completing the hotkey only arms it: it fires once the
+/// last chord has been held for `long_press`, seen through the key's
+/// auto-repeat or, with key repeat off, its KeyUp.
+///
+/// Modifiers that select a variant of the hotkey may be held in addition to
+/// the last chord's own.
+struct TriggerMatcher {
+    action: HotkeyAction,
+    chords: Vec<(u16, u64)>,
+    variant_modifiers: u64,
+    modifier_match: ModifierMatch,
+    sequence_timeout: Duration,
+    long_press: Option<Duration>,
+    progress: Cell<usize>,
+    last_chord: Cell<Option<Instant>>,
+    /// When the last chord of a long-press hotkey went down
+    pressed_at: Cell<Option<Instant>>,
+    debounce: TriggerDebounce,
+}
+
+impl TriggerMatcher {
+    fn new(
+        action: HotkeyAction,
+        chords: Vec<(u16, u64)>,
+        variant_modifiers: u64,
+        modifier_match: ModifierMatch,
+        sequence_timeout: Duration,
+        cooldown: Duration,
+        long_press: Option<Duration>,
+    ) -> Self {
+        Self {
+            action,
+            chords,
+            variant_modifiers,
+            modifier_match,
+            sequence_timeout,
+            long_press,
+            progress: Cell::new(0),
+            last_chord: Cell::new(None),
+            pressed_at: Cell::new(None),
+            debounce: TriggerDebounce::new(cooldown),
+        }
+    }
+
+    /// Whether a long press has been held past its threshold
+    fn held_long_enough(&self, pressed_at: Instant) -> bool {
+        self.long_press
+            .is_some_and(|long_press| pressed_at.elapsed() >= long_press)
+    }
+
+    /// Trigger, unless within the cooldown of the previous trigger
+    fn fire(&self) -> TapAction {
+        if self.debounce.allow() {
+            TapAction::Trigger
+        } else {
+            log::debug!("Hotkey repeat within cooldown, ignoring");
+            TapAction::Consume
+        }
+    }
+
+    fn chord_matches(&self, index: usize, key_code: u16, flags: u64) -> bool {
+        let (chord_key, chord_mods) = self.chords[index];
+        let flags = if index + 1 == self.chords.len() {
+            flags & !(self.variant_modifiers & !chord_mods)
+        } else {
+            flags
+        };
+        key_code == chord_key && self.modifier_match.matches(flags, chord_mods)
+    }
+
+    /// Decide what to do with a KeyDown event
+    fn key_down(&self, key_code: u16, flags: u64, autorepeat: bool) -> TapAction {
+        // Holding a chord down must not re-trigger or advance the sequence
+        if autorepeat {
+            if let Some(pressed_at) = self.pressed_at.get() {
+                if !self.chord_matches(self.chords.len() - 1, key_code, flags) {
+                    log::debug!("Long-press hotkey let go of, not triggering");
+                    self.pressed_at.set(None);
+                } else if self.held_long_enough(pressed_at) {
+                    self.pressed_at.set(None);
+                    return self.fire();
+                }
+            }
+            let held = (0..self.chords.len()).any(|i| self.chord_matches(i, key_code, flags));
+            return if held { TapAction::Consume } else { TapAction::PassThrough };
+        }
+        self.pressed_at.set(None);
+
+        let now = Instant::now();
+        let expired = self
+            .last_chord
+            .get()
+            .is_some_and(|last| now.duration_since(last) > self.sequence_timeout);
+        if expired && self.progress.get() > 0 {
+            log::debug!("Hotkey sequence timed out");
+            self.progress.set(0);
+        }
+
+        let mut progress = self.progress.get();
+        if !self.chord_matches(progress, key_code, flags) {
+            // A mismatch restarts the sequence, possibly with this chord as the leader
+            progress = 0;
+            if !self.chord_matches(0, key_code, flags) {
+                self.progress.set(0);
+                return TapAction::PassThrough;
+            }
+        }
+
+        self.last_chord.set(Some(now));
+        if progress + 1 < self.chords.len() {
+            self.progress.set(progress + 1);
+            return TapAction::Consume;
+        }
+
+        self.progress.set(0);
+        if self.long_press.is_some() {
+            self.pressed_at.set(Some(now));
+            return TapAction::Consume;
+        }
+        self.fire()
+    }
+
+    /// Decide what to do with a KeyUp event
+    ///
+    /// Releasing the last chord of an armed long-press hotkey fires it if it
+    /// was held long enough, which covers key repeat being turned off.
+    fn key_up(&self, key_code: u16) -> TapAction {
+        let Some(pressed_at) = self.pressed_at.get() else {
+            return TapAction::PassThrough;
+        };
+        if key_code != self.chords[self.chords.len() - 1].0 {
+            return TapAction::PassThrough;
+        }
+
+        self.pressed_at.set(None);
+        if self.held_long_enough(pressed_at) {
+            self.fire()
+        } else {
+            log::debug!("Long-press hotkey released after {:?}, not triggering", pressed_at.elapsed());
+            TapAction::Consume
+        }
     }
 }
 
-// Raw modifier flag values (from CGEvent.h)
-const FLAG_COMMAND: u64 = 0x00100000;
-const FLAG_SHIFT: u64 = 0x00020000;
-const FLAG_ALTERNATE: u64 = 0x00080000;
-const FLAG_CONTROL: u64 = 0x00040000;
-
-/// Mask for relevant modifier flags
-const MODIFIER_MASK: u64 = FLAG_COMMAND | FLAG_SHIFT | FLAG_ALTERNATE | FLAG_CONTROL;
-
-/// Convert modifier strings to raw flag bits
-pub fn modifiers_from_config(modifiers: &[String]) -> u64 {
-    let mut flags: u64 = 0;
-
-    for modifier in modifiers {
-        match modifier.to_lowercase().as_str() {
-            "cmd" | "command" => flags |= FLAG_COMMAND,
-            "shift" => flags |= FLAG_SHIFT,
-            "alt" | "option" => flags |= FLAG_ALTERNATE,
-            "ctrl" | "control" => flags |= FLAG_CONTROL,
-            _ => log::warn!("Unknown modifier: {}", modifier),
+/// Handle a KeyDown or KeyUp event from the tap, sending the action and the
+/// event's modifier flags on `tx` when a hotkey is completed
+///
+/// Every matcher sees the event, so hotkeys sharing a leader chord each keep
+/// track of their own sequence.
+fn handle_key_event(
+    matchers: &[TriggerMatcher],
+    event_type: CGEventType,
+    event: &core_graphics::event::CGEvent,
+    tx: &Sender<(HotkeyAction, u64)>,
+) -> Option<core_graphics::event::CGEvent> {
+    let key_code = event.get_integer_value_field(
+        core_graphics::event::EventField::KEYBOARD_EVENT_KEYCODE,
+    ) as u16;
+
+    // Get flags and extract the raw bits
+    let event_flags = event.get_flags();
+    let event_flags_raw: u64 = unsafe { std::mem::transmute(event_flags) };
+    let autorepeat = is_autorepeat(event);
+
+    let mut consume = false;
+    let mut triggered = None;
+    for matcher in matchers {
+        let action = match event_type {
+            CGEventType::KeyUp => matcher.key_up(key_code),
+            _ => matcher.key_down(key_code, event_flags_raw, autorepeat),
+        };
+        match action {
+            TapAction::PassThrough => {}
+            TapAction::Consume => consume = true,
+            TapAction::Trigger => triggered = triggered.or(Some(matcher.action)),
         }
     }
 
-    flags
+    if let Some(action) = triggered {
+        log::info!("Hotkey triggered! ({:?})", action);
+        let _ = tx.send((action, event_flags_raw));
+        // Consume the event (don't pass it to other apps)
+        return None;
+    }
+    if consume {
+        None
+    } else {
+        Some(event.clone())
+    }
+}
+
+/// Handle that lets another thread wake a listener's run loop.
+///
+/// This lets listeners block in the run loop for long periods while idle and
+/// still react to commands immediately, instead of waking up on a short timer.
+#[derive(Clone, Default)]
+struct RunLoopWaker(Arc<Mutex<Option<CFRunLoop>>>);
+
+impl RunLoopWaker {
+    /// Remember the calling thread's run loop as the one to wake
+    fn register_current(&self) {
+        *self.0.lock().unwrap() = Some(CFRunLoop::get_current());
+    }
+
+    /// Make the registered run loop return from its current wait
+    fn wake(&self) {
+        if let Some(run_loop) = self.0.lock().unwrap().as_ref() {
+            run_loop.stop();
+        }
+    }
 }
 
 /// Represents a registered hotkey
 #[allow(dead_code)]
 pub struct HotkeyListener {
-    key_code: u16,
-    modifiers: u64,
-    callback: Box<dyn Fn() + Send + Sync>,
+    chords: Vec<(u16, u64)>,
+    variant_modifiers: u64,
+    modifier_match: ModifierMatch,
+    callback: Box<dyn Fn(u64) + Send + Sync>,
     running: Arc<AtomicBool>,
+    run_loop_wait: Duration,
+    cooldown: Duration,
+    sequence_timeout: Duration,
+    waker: RunLoopWaker,
 }
 
 #[allow(dead_code)]
@@ -107,19 +301,38 @@ impl HotkeyListener {
     where
         F: Fn() + Send + Sync + 'static,
     {
-        let key_code = key_code_from_string(&config.key)
-            .with_context(|| format!("Unknown key: {}", config.key))?;
+        Self::from_config_with_modifiers(config, move |_| callback())
+    }
 
-        let modifiers = modifiers_from_config(&config.modifiers);
+    /// Create a new hotkey listener from config, whose callback receives the
+    /// modifier flags held when the hotkey was triggered
+    pub fn from_config_with_modifiers<F>(config: &HotkeyConfig, callback: F) -> Result<Self>
+    where
+        F: Fn(u64) + Send + Sync + 'static,
+    {
+        let chords = hotkey_chords(config)?;
 
         Ok(Self {
-            key_code,
-            modifiers,
+            chords,
+            variant_modifiers: variant_modifiers(config)?,
+            modifier_match: config.modifier_match,
             callback: Box::new(callback),
             running: Arc::new(AtomicBool::new(false)),
+            run_loop_wait: DEFAULT_RUN_LOOP_WAIT,
+            cooldown: DEFAULT_TRIGGER_COOLDOWN,
+            sequence_timeout: DEFAULT_SEQUENCE_TIMEOUT,
+            waker: RunLoopWaker::default(),
         })
     }
 
+    /// Use the run loop wait, cooldown and sequence timeout from the config
+    pub fn with_timing(mut self, timing: &TimingConfig) -> Self {
+        self.run_loop_wait = Duration::from_millis(timing.listener_wait_ms);
+        self.cooldown = Duration::from_millis(timing.trigger_cooldown_ms);
+        self.sequence_timeout = Duration::from_millis(timing.sequence_timeout_ms);
+        self
+    }
+
     /// Start listening for the hotkey (blocking)
     /// This should be called from a dedicated thread
     pub fn start(&self) -> Result<()> {
@@ -127,53 +340,43 @@ impl HotkeyListener {
 
         self.running.store(true, Ordering::SeqCst);
 
-        let key_code = self.key_code;
-        let target_modifiers = self.modifiers;
         let running = self.running.clone();
 
         // Create a channel to send hotkey events
-        let (tx, rx) = std::sync::mpsc::channel::<()>();
+        let (tx, rx) = std::sync::mpsc::channel::<(HotkeyAction, u64)>();
 
         // Spawn the callback handler thread
         let callback = unsafe {
             // This is safe because we ensure the listener outlives the thread
-            std::mem::transmute::<&(dyn Fn() + Send + Sync), &'static (dyn Fn() + Send + Sync)>(
+            std::mem::transmute::<&(dyn Fn(u64) + Send + Sync), &'static (dyn Fn(u64) + Send + Sync)>(
                 self.callback.as_ref(),
             )
         };
 
         std::thread::spawn(move || {
-            while let Ok(()) = rx.recv() {
-                callback();
+            while let Ok((_, modifiers)) = rx.recv() {
+                callback(modifiers);
             }
         });
 
         // Create event tap callback
         let tx_clone = tx.clone();
+        let matchers = [TriggerMatcher::new(
+            HotkeyAction::EditSelection,
+            self.chords.clone(),
+            self.variant_modifiers,
+            self.modifier_match,
+            self.sequence_timeout,
+            self.cooldown,
+            None,
+        )];
         let callback = move |_proxy: core_graphics::event::CGEventTapProxy,
                              event_type: CGEventType,
                              event: &core_graphics::event::CGEvent|
               -> Option<core_graphics::event::CGEvent> {
             // KeyDown = 10
             if matches!(event_type, CGEventType::KeyDown) {
-                let event_key_code = event.get_integer_value_field(
-                    core_graphics::event::EventField::KEYBOARD_EVENT_KEYCODE,
-                ) as u16;
-
-                // Get flags and extract the raw bits
-                let event_flags = event.get_flags();
-                let event_flags_raw: u64 = unsafe { std::mem::transmute(event_flags) };
-
-                // Mask to only relevant modifier flags
-                let event_mods = event_flags_raw & MODIFIER_MASK;
-                let target_mods = target_modifiers & MODIFIER_MASK;
-
-                if event_key_code == key_code && event_mods == target_mods {
-                    log::info!("Hotkey triggered!");
-                    let _ = tx_clone.send(());
-                    // Consume the event (don't pass it to other apps)
-                    return None;
-                }
+                return handle_key_event(&matchers, event_type, event, &tx_clone);
             }
             Some(event.clone())
         };
@@ -201,18 +404,15 @@ impl HotkeyListener {
 
         let run_loop = CFRunLoop::get_current();
         run_loop.add_source(&source, unsafe { kCFRunLoopCommonModes });
+        self.waker.register_current();
 
-        log::info!(
-            "Hotkey listener started (key_code: 0x{:02X}, modifiers: 0x{:08X})",
-            self.key_code,
-            self.modifiers
-        );
+        log::info!("Hotkey listener started (chords: {:02X?})", self.chords);
 
-        // Run the loop
+        // Run the loop (stop() wakes it early)
         while running.load(Ordering::SeqCst) {
             CFRunLoop::run_in_mode(
                 unsafe { kCFRunLoopDefaultMode },
-                std::time::Duration::from_secs(1),
+                self.run_loop_wait,
                 false,
             );
         }
@@ -224,6 +424,7 @@ impl HotkeyListener {
     #[allow(dead_code)]
     pub fn stop(&self) {
         self.running.store(false, Ordering::SeqCst);
+        self.waker.wake();
     }
 
     /// Get a reference to the running flag
@@ -239,29 +440,37 @@ impl HotkeyListener {
 /// Command type for controlling the hotkey listener
 pub enum HotkeyCommand {
     Stop,
-    Restart(HotkeyConfig),
+    Restart(Vec<(HotkeyAction, HotkeyConfig)>),
 }
 
 /// Controller for the hotkey listener that allows runtime updates
 pub struct HotkeyController {
     command_tx: Sender<HotkeyCommand>,
+    waker: RunLoopWaker,
+    /// Signalled by the listener thread when it exits
+    exited_rx: Receiver<()>,
 }
 
 impl HotkeyController {
-    /// Update the hotkey configuration (will restart the listener)
-    pub fn update_hotkey(&self, config: HotkeyConfig) {
-        log::info!("Updating hotkey to: {:?}", config);
-        if let Err(e) = self.command_tx.send(HotkeyCommand::Restart(config)) {
+    /// Update the hotkeys and their actions (will restart the listener)
+    pub fn update_bindings(&self, bindings: Vec<(HotkeyAction, HotkeyConfig)>) {
+        log::info!("Updating hotkeys to: {:?}", bindings);
+        if let Err(e) = self.command_tx.send(HotkeyCommand::Restart(bindings)) {
             log::error!("Failed to send hotkey update command: {}", e);
         }
+        self.waker.wake();
     }
 
-    /// Stop the hotkey listener
-    #[allow(dead_code)]
-    pub fn stop(&self) {
+    /// Stop the hotkey listener, waiting up to `timeout` for its thread to exit
+    ///
+    /// Returns false if the thread was still running at the timeout.
+    pub fn stop(&self, timeout: Duration) -> bool {
         if let Err(e) = self.command_tx.send(HotkeyCommand::Stop) {
             log::error!("Failed to send stop command: {}", e);
         }
+        self.waker.wake();
+        // A closed channel means the thread is gone already
+        !matches!(self.exited_rx.recv_timeout(timeout), Err(RecvTimeoutError::Timeout))
     }
 }
 
@@ -271,48 +480,71 @@ impl HotkeyController {
 /// when the hotkey configuration changes.
 ///
 /// # Arguments
-/// * `initial_config` - The initial hotkey configuration
-/// * `callback` - The callback to run when the hotkey is triggered
+/// * `initial_bindings` - The initial hotkeys, each with the action it triggers
+/// * `timing` - Run loop wait, trigger cooldown and sequence timeout settings
+/// * `tap_location` - Where the event tap sees keyboard events (normally the session)
+/// * `callback` - The callback to run with the action of a triggered hotkey and
+///   the modifier flags held when it was triggered
 ///
 /// # Returns
 /// A HotkeyController that can be used to update or stop the listener
 pub fn start_hotkey_listener_with_controller<F>(
-    initial_config: HotkeyConfig,
+    initial_bindings: Vec<(HotkeyAction, HotkeyConfig)>,
+    timing: &TimingConfig,
+    tap_location: CGEventTapLocation,
     callback: F,
 ) -> HotkeyController
 where
-    F: Fn() + Send + Sync + Clone + 'static,
+    F: Fn(HotkeyAction, u64) + Send + Sync + Clone + 'static,
 {
     let (tx, rx) = channel::<HotkeyCommand>();
+    let run_loop_wait = Duration::from_millis(timing.listener_wait_ms);
+    let cooldown = Duration::from_millis(timing.trigger_cooldown_ms);
+    let sequence_timeout = Duration::from_millis(timing.sequence_timeout_ms);
+    let waker = RunLoopWaker::default();
+    let thread_waker = waker.clone();
+    let (exited_tx, exited_rx) = channel::<()>();
 
     std::thread::spawn(move || {
-        let mut current_config = initial_config;
+        let mut current_bindings = initial_bindings;
+        thread_waker.register_current();
 
         'outer: loop {
             log::info!(
-                "Starting hotkey listener with config: {:?}",
-                current_config
+                "Starting hotkey listener with hotkeys: {:?}",
+                current_bindings
             );
 
             // Set up the listener components manually to integrate command checking
-            let key_code = match key_code_from_string(&current_config.key) {
-                Some(k) => k,
-                None => {
-                    log::error!("Unknown key: {}", current_config.key);
-                    std::thread::sleep(std::time::Duration::from_secs(1));
-                    continue;
-                }
-            };
-            let target_modifiers = modifiers_from_config(&current_config.modifiers);
+            // (an invalid hotkey is skipped so the others keep working)
+            let matchers: Vec<TriggerMatcher> = current_bindings
+                .iter()
+                .filter_map(|(action, config)| match hotkey_chords(config) {
+                    Ok(chords) => Some(TriggerMatcher::new(
+                        *action,
+                        chords,
+                        variant_modifiers(config).unwrap_or(0),
+                        config.modifier_match,
+                        sequence_timeout,
+                        cooldown,
+                        config.long_press_ms.map(Duration::from_millis),
+                    )),
+                    Err(e) => {
+                        log::error!("Invalid hotkey for {:?}: {}", action, e);
+                        None
+                    }
+                })
+                .collect();
+            let chords: Vec<_> = matchers.iter().map(|matcher| matcher.chords.clone()).collect();
 
             // Create channel for hotkey events
-            let (hotkey_tx, hotkey_rx) = channel::<()>();
+            let (hotkey_tx, hotkey_rx) = channel::<(HotkeyAction, u64)>();
 
             // Spawn callback handler thread
             let callback_clone = callback.clone();
             std::thread::spawn(move || {
-                while let Ok(()) = hotkey_rx.recv() {
-                    callback_clone();
+                while let Ok((action, modifiers)) = hotkey_rx.recv() {
+                    callback_clone(action, modifiers);
                 }
             });
 
@@ -324,30 +556,18 @@ where
                                      event_type: CGEventType,
                                      event: &core_graphics::event::CGEvent|
                   -> Option<core_graphics::event::CGEvent> {
-                if matches!(event_type, CGEventType::KeyDown) {
-                    let event_key_code = event.get_integer_value_field(
-                        core_graphics::event::EventField::KEYBOARD_EVENT_KEYCODE,
-                    ) as u16;
-
-                    let event_flags = event.get_flags();
-                    let event_flags_raw: u64 = unsafe { std::mem::transmute(event_flags) };
-                    let event_mods = event_flags_raw & MODIFIER_MASK;
-                    let target_mods = target_modifiers & MODIFIER_MASK;
-
-                    if event_key_code == key_code && event_mods == target_mods {
-                        log::info!("Hotkey triggered!");
-                        let _ = hotkey_tx_clone.send(());
-                        return None;
-                    }
+                // KeyUp is only needed to end long presses
+                if matches!(event_type, CGEventType::KeyDown | CGEventType::KeyUp) {
+                    return handle_key_event(&matchers, event_type, event, &hotkey_tx_clone);
                 }
                 Some(event.clone())
             };
 
             let tap = match CGEventTap::new(
-                CGEventTapLocation::Session,
+                tap_location,
                 CGEventTapPlacement::HeadInsertEventTap,
                 CGEventTapOptions::Default,
-                vec![CGEventType::KeyDown],
+                vec![CGEventType::KeyDown, CGEventType::KeyUp],
                 tap_callback,
             )
             .ok()
@@ -374,18 +594,13 @@ where
             let run_loop = CFRunLoop::get_current();
             run_loop.add_source(&source, unsafe { kCFRunLoopCommonModes });
 
-            log::info!(
-                "Hotkey listener started (key_code: 0x{:02X}, modifiers: 0x{:08X})",
-                key_code,
-                target_modifiers
-            );
+            log::info!("Hotkey listener started (chords: {:02X?})", chords);
 
-            // Run loop with periodic command checking
+            // Run loop with command checking (the controller wakes us on new commands)
             loop {
-                // Run the event loop for a short time
                 CFRunLoop::run_in_mode(
                     unsafe { kCFRunLoopDefaultMode },
-                    std::time::Duration::from_millis(100),
+                    run_loop_wait,
                     false,
                 );
 
@@ -395,9 +610,9 @@ where
                         log::info!("Stopping hotkey listener");
                         break 'outer;
                     }
-                    Ok(HotkeyCommand::Restart(new_config)) => {
+                    Ok(HotkeyCommand::Restart(new_bindings)) => {
                         log::info!("Restarting hotkey listener with new config");
-                        current_config = new_config;
+                        current_bindings = new_bindings;
                         break; // Break inner loop to restart with new config
                     }
                     Err(std::sync::mpsc::TryRecvError::Empty) => {
@@ -412,204 +627,12 @@ where
         }
 
         log::info!("Hotkey management thread exiting");
+        let _ = exited_tx.send(());
     });
 
-    HotkeyController { command_tx: tx }
-}
-
-// ============================================================================
-// Display formatting functions
-// ============================================================================
-
-/// Convert a key code back to a display string
-#[allow(dead_code)]
-pub fn key_code_to_display(key_code: u16) -> Option<String> {
-    match key_code {
-        0x00 => Some("A".to_string()),
-        0x01 => Some("S".to_string()),
-        0x02 => Some("D".to_string()),
-        0x03 => Some("F".to_string()),
-        0x04 => Some("H".to_string()),
-        0x05 => Some("G".to_string()),
-        0x06 => Some("Z".to_string()),
-        0x07 => Some("X".to_string()),
-        0x08 => Some("C".to_string()),
-        0x09 => Some("V".to_string()),
-        0x0B => Some("B".to_string()),
-        0x0C => Some("Q".to_string()),
-        0x0D => Some("W".to_string()),
-        0x0E => Some("E".to_string()),
-        0x0F => Some("R".to_string()),
-        0x10 => Some("Y".to_string()),
-        0x11 => Some("T".to_string()),
-        0x12 => Some("1".to_string()),
-        0x13 => Some("2".to_string()),
-        0x14 => Some("3".to_string()),
-        0x15 => Some("4".to_string()),
-        0x16 => Some("6".to_string()),
-        0x17 => Some("5".to_string()),
-        0x18 => Some("=".to_string()),
-        0x19 => Some("9".to_string()),
-        0x1A => Some("7".to_string()),
-        0x1B => Some("-".to_string()),
-        0x1C => Some("8".to_string()),
-        0x1D => Some("0".to_string()),
-        0x1E => Some("]".to_string()),
-        0x1F => Some("O".to_string()),
-        0x20 => Some("U".to_string()),
-        0x21 => Some("[".to_string()),
-        0x22 => Some("I".to_string()),
-        0x23 => Some("P".to_string()),
-        0x25 => Some("L".to_string()),
-        0x26 => Some("J".to_string()),
-        0x27 => Some("'".to_string()),
-        0x28 => Some("K".to_string()),
-        0x29 => Some(";".to_string()),
-        0x2A => Some("\\".to_string()),
-        0x2B => Some(",".to_string()),
-        0x2C => Some("/".to_string()),
-        0x2D => Some("N".to_string()),
-        0x2E => Some("M".to_string()),
-        0x2F => Some(".".to_string()),
-        0x32 => Some("`".to_string()),
-        0x31 => Some("Space".to_string()),
-        0x24 => Some("↵".to_string()),
-        0x30 => Some("⇥".to_string()),
-        0x33 => Some("⌫".to_string()),
-        0x35 => Some("⎋".to_string()),
-        _ => None,
-    }
-}
-
-/// Convert a key name to display symbol
-pub fn key_name_to_display(key: &str) -> String {
-    match key.to_lowercase().as_str() {
-        "semicolon" | ";" => ";".to_string(),
-        "comma" | "," => ",".to_string(),
-        "period" | "." => ".".to_string(),
-        "slash" | "/" => "/".to_string(),
-        "backslash" | "\\" => "\\".to_string(),
-        "quote" | "'" => "'".to_string(),
-        "grave" | "backtick" | "`" => "`".to_string(),
-        "space" => "Space".to_string(),
-        "return" | "enter" => "↵".to_string(),
-        "tab" => "⇥".to_string(),
-        "delete" | "backspace" => "⌫".to_string(),
-        "escape" | "esc" => "⎋".to_string(),
-        other => other.to_uppercase(),
-    }
-}
-
-/// Convert modifier flags to display string with Unicode symbols
-pub fn modifiers_to_display(modifiers: u64) -> String {
-    let mut result = String::new();
-    // Order: Control, Option, Shift, Command (standard macOS order)
-    if modifiers & FLAG_CONTROL != 0 {
-        result.push('⌃');
-    }
-    if modifiers & FLAG_ALTERNATE != 0 {
-        result.push('⌥');
-    }
-    if modifiers & FLAG_SHIFT != 0 {
-        result.push('⇧');
-    }
-    if modifiers & FLAG_COMMAND != 0 {
-        result.push('⌘');
-    }
-    result
-}
-
-/// Convert modifier config strings to display string
-pub fn modifiers_config_to_display(modifiers: &[String]) -> String {
-    let flags = modifiers_from_config(modifiers);
-    modifiers_to_display(flags)
-}
-
-/// Format a HotkeyConfig for display (e.g., "⌘⇧;")
-pub fn format_hotkey_display(config: &HotkeyConfig) -> String {
-    let mod_str = modifiers_config_to_display(&config.modifiers);
-    let key_str = key_name_to_display(&config.key);
-    format!("{}{}", mod_str, key_str)
-}
-
-/// Convert modifier flags back to config strings
-pub fn modifiers_to_config(modifiers: u64) -> Vec<String> {
-    let mut result = Vec::new();
-    if modifiers & FLAG_COMMAND != 0 {
-        result.push("cmd".to_string());
-    }
-    if modifiers & FLAG_SHIFT != 0 {
-        result.push("shift".to_string());
-    }
-    if modifiers & FLAG_ALTERNATE != 0 {
-        result.push("alt".to_string());
+    HotkeyController {
+        command_tx: tx,
+        waker,
+        exited_rx,
     }
-    if modifiers & FLAG_CONTROL != 0 {
-        result.push("ctrl".to_string());
-    }
-    result
-}
-
-/// Convert a key code back to config string
-pub fn key_code_to_config(key_code: u16) -> Option<String> {
-    match key_code {
-        0x00 => Some("a".to_string()),
-        0x01 => Some("s".to_string()),
-        0x02 => Some("d".to_string()),
-        0x03 => Some("f".to_string()),
-        0x04 => Some("h".to_string()),
-        0x05 => Some("g".to_string()),
-        0x06 => Some("z".to_string()),
-        0x07 => Some("x".to_string()),
-        0x08 => Some("c".to_string()),
-        0x09 => Some("v".to_string()),
-        0x0B => Some("b".to_string()),
-        0x0C => Some("q".to_string()),
-        0x0D => Some("w".to_string()),
-        0x0E => Some("e".to_string()),
-        0x0F => Some("r".to_string()),
-        0x10 => Some("y".to_string()),
-        0x11 => Some("t".to_string()),
-        0x12 => Some("1".to_string()),
-        0x13 => Some("2".to_string()),
-        0x14 => Some("3".to_string()),
-        0x15 => Some("4".to_string()),
-        0x16 => Some("6".to_string()),
-        0x17 => Some("5".to_string()),
-        0x18 => Some("=".to_string()),
-        0x19 => Some("9".to_string()),
-        0x1A => Some("7".to_string()),
-        0x1B => Some("-".to_string()),
-        0x1C => Some("8".to_string()),
-        0x1D => Some("0".to_string()),
-        0x1E => Some("]".to_string()),
-        0x1F => Some("o".to_string()),
-        0x20 => Some("u".to_string()),
-        0x21 => Some("[".to_string()),
-        0x22 => Some("i".to_string()),
-        0x23 => Some("p".to_string()),
-        0x25 => Some("l".to_string()),
-        0x26 => Some("j".to_string()),
-        0x27 => Some("'".to_string()),
-        0x28 => Some("k".to_string()),
-        0x29 => Some("semicolon".to_string()),
-        0x2A => Some("backslash".to_string()),
-        0x2B => Some("comma".to_string()),
-        0x2C => Some("slash".to_string()),
-        0x2D => Some("n".to_string()),
-        0x2E => Some("m".to_string()),
-        0x2F => Some("period".to_string()),
-        0x32 => Some("grave".to_string()),
-        0x31 => Some("space".to_string()),
-        0x24 => Some("return".to_string()),
-        0x30 => Some("tab".to_string()),
-        0x33 => Some("backspace".to_string()),
-        0x35 => Some("escape".to_string()),
-        _ => None,
-    }
-}
-
-/// Get the modifier mask constant (for use in recorder)
-pub const fn get_modifier_mask() -> u64 {
-    MODIFIER_MASK
 }
@@ -5,46 +5,92 @@
 //! will be captured and returned via a callback.
 
 use crate::config::HotkeyConfig;
-use crate::hotkey::{get_modifier_mask, key_code_to_config, modifiers_to_config};
+use crate::hotkey::is_autorepeat;
+use crate::keys::{
+    format_hotkey_display, get_modifier_mask, modifiers_to_config, recorded_hotkey,
+    recorded_modifiers, recording_mismatch, same_hotkey,
+};
 use core_foundation::runloop::{kCFRunLoopCommonModes, kCFRunLoopDefaultMode, CFRunLoop};
 use core_graphics::event::{CGEventTapLocation, CGEventType};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-/// Timeout for recording (10 seconds)
-const RECORDING_TIMEOUT: Duration = Duration::from_secs(10);
-
 /// Start recording the next hotkey combination.
 ///
 /// This function spawns a temporary event tap thread that captures the next
 /// key press with modifiers. Once captured, the callback is called with the
 /// resulting HotkeyConfig.
 ///
-/// The recording will timeout after 10 seconds if no key is pressed.
+/// The recording times out after `timeout` if no key is pressed.
 ///
 /// # Arguments
+/// * `app_shortcuts` - The app's own menu shortcuts (name, hotkey), which are rejected
+///   so they stay reachable
+/// * `timeout` - How long to wait for a key press (`timing.recording_timeout_secs`)
 /// * `on_recorded` - Callback called with the recorded HotkeyConfig
 /// * `on_timeout` - Callback called if recording times out
 /// * `on_error` - Callback called if recording fails (e.g., invalid key)
-pub fn record_next_hotkey<F, T, E>(on_recorded: F, on_timeout: T, on_error: E)
-where
+/// * `on_reserved` - Callback called with the reason when a reserved combo is
+///   pressed; the key press reaches the system and recording goes on
+pub fn record_next_hotkey<F, T, E, R>(
+    app_shortcuts: Vec<(String, HotkeyConfig)>,
+    timeout: Duration,
+    on_recorded: F,
+    on_timeout: T,
+    on_error: E,
+    on_reserved: R,
+) where
     F: FnOnce(HotkeyConfig) + Send + 'static,
     T: FnOnce() + Send + 'static,
     E: FnOnce(String) + Send + 'static,
+    R: Fn(&str) + Send + 'static,
 {
     std::thread::spawn(move || {
-        if let Err(e) = record_hotkey_blocking(on_recorded, on_timeout) {
+        let on_recorded = move |config: HotkeyConfig| {
+            match check_app_shortcuts(&config, &app_shortcuts) {
+                Some(reason) => Err(reason),
+                None => {
+                    on_recorded(config);
+                    Ok(())
+                }
+            }
+        };
+        if let Err(e) = record_hotkey_blocking(timeout, on_recorded, on_timeout, on_reserved) {
             on_error(e);
         }
     });
 }
 
+/// Check if a hotkey would shadow one of the app's own menu shortcuts
+/// Returns Some(reason) if it does
+fn check_app_shortcuts(
+    config: &HotkeyConfig,
+    app_shortcuts: &[(String, HotkeyConfig)],
+) -> Option<String> {
+    app_shortcuts
+        .iter()
+        .find(|(_, shortcut)| same_hotkey(config, shortcut))
+        .map(|(name, shortcut)| {
+            format!(
+                "{} is the shortcut for {}",
+                format_hotkey_display(shortcut),
+                name
+            )
+        })
+}
+
 /// Internal blocking implementation of hotkey recording
-fn record_hotkey_blocking<F, T>(on_recorded: F, on_timeout: T) -> Result<(), String>
+fn record_hotkey_blocking<F, T, R>(
+    timeout: Duration,
+    on_recorded: F,
+    on_timeout: T,
+    on_reserved: R,
+) -> Result<(), String>
 where
-    F: FnOnce(HotkeyConfig) + Send + 'static,
+    F: FnOnce(HotkeyConfig) -> Result<(), String> + Send + 'static,
     T: FnOnce() + Send + 'static,
+    R: Fn(&str) + Send + 'static,
 {
     use core_graphics::event::{CGEventTap, CGEventTapOptions, CGEventTapPlacement};
 
@@ -70,6 +116,11 @@ where
             return Some(event.clone());
         }
 
+        // Ignore keys held down from before recording started
+        if is_autorepeat(event) {
+            return Some(event.clone());
+        }
+
         // Get key code
         let key_code = event.get_integer_value_field(
             core_graphics::event::EventField::KEYBOARD_EVENT_KEYCODE,
@@ -78,10 +129,11 @@ where
         // Get modifier flags
         let event_flags = event.get_flags();
         let event_flags_raw: u64 = unsafe { std::mem::transmute(event_flags) };
-        let modifiers = event_flags_raw & get_modifier_mask();
+        let modifiers = recorded_modifiers(key_code, event_flags_raw & get_modifier_mask());
 
         // Ignore pure modifier key presses (no actual key)
-        // Modifier-only key codes: Shift=56/60, Control=59/62, Option=58/61, Command=55/54
+        // Modifier-only key codes: Shift=56/60, Control=59/62, Option=58/61, Command=55/54,
+        // Caps Lock=57
         let is_modifier_only = matches!(
             key_code,
             54 | 55 | 56 | 57 | 58 | 59 | 60 | 61 | 62 | 63
@@ -90,30 +142,30 @@ where
             return Some(event.clone());
         }
 
-        // Convert to config format
-        if let Some(key_name) = key_code_to_config(key_code) {
-            let modifier_strings = modifiers_to_config(modifiers);
-
-            // Require at least one modifier
-            if modifier_strings.is_empty() {
-                log::warn!("Hotkey recording: no modifiers pressed, ignoring");
-                return Some(event.clone());
-            }
-
-            let config = HotkeyConfig {
-                modifiers: modifier_strings,
-                key: key_name,
-            };
+        // Require at least one modifier
+        if modifiers_to_config(modifiers).is_empty() {
+            log::warn!("Hotkey recording: no modifiers pressed, ignoring");
+            return Some(event.clone());
+        }
 
-            recorded_clone.store(true, Ordering::SeqCst);
-            let _ = tx.send(Some(config));
+        let config = recorded_hotkey(key_code, modifiers);
+        // A mismatch means the key tables disagree, and the hotkey would never fire
+        if let Some(mismatch) = recording_mismatch(&config, key_code, modifiers) {
+            log::warn!("Hotkey recording: {}", mismatch);
+        }
 
-            // Consume the event
-            return None;
+        // Let the system act on reserved combos, and keep waiting for another one
+        if let Some(reason) = is_reserved_hotkey(&config) {
+            log::info!("Hotkey recording: {}, passing it through", reason);
+            on_reserved(reason);
+            return Some(event.clone());
         }
 
-        // Unknown key code, let it pass through
-        Some(event.clone())
+        recorded_clone.store(true, Ordering::SeqCst);
+        let _ = tx.send(Some(config));
+
+        // Consume the event
+        None
     };
 
     // Create the event tap
@@ -147,7 +199,7 @@ where
     // Run the loop with timeout checking
     while !recorded.load(Ordering::SeqCst) {
         // Check timeout
-        if start_time.elapsed() > RECORDING_TIMEOUT {
+        if start_time.elapsed() > timeout {
             log::info!("Hotkey recording timed out");
             on_timeout();
             return Ok(());
@@ -164,7 +216,7 @@ where
     // Get the recorded hotkey
     if let Ok(Some(config)) = rx.try_recv() {
         log::info!("Hotkey recorded: {:?}", config);
-        on_recorded(config);
+        on_recorded(config)?;
     }
 
     Ok(())
@@ -172,22 +224,45 @@ where
 
 /// Check if a hotkey combination is reserved by the system
 /// Returns Some(reason) if reserved, None if available
-#[allow(dead_code)]
 pub fn is_reserved_hotkey(config: &HotkeyConfig) -> Option<&'static str> {
-    let has_cmd = config.modifiers.iter().any(|m| m == "cmd" || m == "command");
-    let only_cmd = config.modifiers.len() == 1 && has_cmd;
-
-    if only_cmd {
-        match config.key.to_lowercase().as_str() {
-            "q" => Some("Cmd+Q is reserved for Quit"),
-            "w" => Some("Cmd+W is reserved for Close Window"),
-            "h" => Some("Cmd+H is reserved for Hide"),
-            "m" => Some("Cmd+M is reserved for Minimize"),
-            "tab" => Some("Cmd+Tab is reserved for App Switcher"),
-            "space" => Some("Cmd+Space is reserved for Spotlight"),
-            _ => None,
-        }
-    } else {
-        None
+    let config = config.canonical();
+    if config.modifier_flags.is_some() {
+        return None;
+    }
+
+    // Caps Lock doesn't change which shortcut the system sees
+    let modifiers: Vec<&str> =
+        config.modifiers.iter().map(String::as_str).filter(|&name| name != "caps").collect();
+    match (modifiers.as_slice(), config.key.as_str()) {
+        (["cmd"], "q") => Some("Cmd+Q is reserved for Quit"),
+        (["cmd"], "w") => Some("Cmd+W is reserved for Close Window"),
+        (["cmd"], "h") => Some("Cmd+H is reserved for Hide"),
+        (["cmd"], "m") => Some("Cmd+M is reserved for Minimize"),
+        (["cmd"], "tab") => Some("Cmd+Tab is reserved for App Switcher"),
+        (["cmd"], "space") => Some("Cmd+Space is reserved for Spotlight"),
+        (["cmd", "shift"], "3" | "4" | "5") => Some("Cmd+Shift+3/4/5 are reserved for Screenshots"),
+        (["cmd", "alt"], "escape") => Some("Cmd+Option+Esc is reserved for Force Quit"),
+        (["cmd", "ctrl"], "q") => Some("Ctrl+Cmd+Q is reserved for Lock Screen"),
+        (["cmd", "ctrl"], "space") => Some("Ctrl+Cmd+Space is reserved for the Emoji picker"),
+        (["cmd", "ctrl"], "f") => Some("Ctrl+Cmd+F is reserved for Full Screen"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn system_shortcuts_are_reserved() {
+        let hotkey = |modifiers: &[&str], key: &str| {
+            toml::from_str::<HotkeyConfig>(&format!("modifiers = {:?}\nkey = {:?}", modifiers, key)).unwrap()
+        };
+        assert!(is_reserved_hotkey(&hotkey(&["cmd"], "Q")).is_some());
+        assert!(is_reserved_hotkey(&hotkey(&["shift", "command"], "4")).is_some());
+        assert!(is_reserved_hotkey(&hotkey(&["ctrl", "cmd"], "space")).is_some());
+        assert!(is_reserved_hotkey(&hotkey(&["cmd", "shift"], "semicolon")).is_none());
+        assert!(is_reserved_hotkey(&hotkey(&["cmd", "alt"], "q")).is_none());
+        assert!(is_reserved_hotkey(&hotkey(&["caps", "cmd"], "q")).is_some());
     }
 }
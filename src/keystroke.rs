@@ -1,15 +1,98 @@
-use anyhow::{Context, Result};
+use crate::config::HotkeyConfig;
+use crate::keys::{applescript_key_code, chord_flags, chord_key_code, typed_key};
+use anyhow::{bail, Context, Result};
 use core_graphics::event::{CGEvent, CGEventFlags, CGEventTapLocation, CGKeyCode};
 use core_graphics::event_source::{CGEventSource, CGEventSourceStateID};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Whether posted key events are logged (`debug.trace_keystrokes`)
+static TRACE_KEYSTROKES: AtomicBool = AtomicBool::new(false);
+
+/// Log every key event posted from now on, or stop doing so
+pub fn set_tracing(enabled: bool) {
+    TRACE_KEYSTROKES.store(enabled, Ordering::Relaxed);
+}
+
+/// Log a posted key event, if tracing is on
+fn trace_event(key_code: CGKeyCode, key_down: bool, flags: CGEventFlags) {
+    if !TRACE_KEYSTROKES.load(Ordering::Relaxed) {
+        return;
+    }
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    log::info!(
+        "Posted key {} code={:#04x} flags={:#010x} at {}.{:03}",
+        if key_down { "down" } else { "up" },
+        key_code,
+        flags.bits(),
+        time.as_secs(),
+        time.subsec_millis()
+    );
+}
+
+#[link(name = "Carbon", kind = "framework")]
+extern "C" {
+    fn IsSecureEventInputEnabled() -> u8;
+}
+
+/// Check if secure event input is on (e.g. a password field is focused)
+///
+/// macOS then blocks synthetic copy/paste events from reaching the app.
+pub fn is_secure_input_enabled() -> bool {
+    unsafe { IsSecureEventInputEnabled() != 0 }
+}
+
+#[link(name = "ApplicationServices", kind = "framework")]
+extern "C" {
+    fn AXIsProcessTrusted() -> u8;
+}
+
+/// Check if the app has been granted Accessibility access
+///
+/// Without it, the hotkey can't be seen and copy/paste can't be simulated.
+pub fn is_accessibility_trusted() -> bool {
+    unsafe { AXIsProcessTrusted() != 0 }
+}
+
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    fn CGEventSourceFlagsState(state_id: CGEventSourceStateID) -> CGEventFlags;
+}
+
+/// Modifiers that would turn the synthesized copy into another combo if still held
+fn held_modifiers() -> CGEventFlags {
+    let flags = unsafe { CGEventSourceFlagsState(CGEventSourceStateID::HIDSystemState) };
+    flags
+        & (CGEventFlags::CGEventFlagCommand
+            | CGEventFlags::CGEventFlagShift
+            | CGEventFlags::CGEventFlagAlternate
+            | CGEventFlags::CGEventFlagControl)
+}
+
+/// Wait until the user has let go of all modifier keys, or until the timeout
+///
+/// Returns false if modifiers were still held when giving up.
+pub fn wait_for_modifiers_released(timeout: Duration) -> bool {
+    let start = Instant::now();
+    while !held_modifiers().is_empty() {
+        if start.elapsed() > timeout {
+            log::debug!("Modifiers still held after {:?}: {:?}", timeout, held_modifiers());
+            return false;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    true
+}
 
 // macOS virtual key codes
-const KEY_C: CGKeyCode = 0x08;
-const KEY_V: CGKeyCode = 0x09;
+const KEY_A: CGKeyCode = 0x00;
+const KEY_LEFT_ARROW: CGKeyCode = 0x7B;
+const KEY_RIGHT_ARROW: CGKeyCode = 0x7C;
 
-/// Simulate a key press with command modifier
-fn simulate_key_with_command(key_code: CGKeyCode) -> Result<()> {
+/// Simulate a key press with the given modifiers
+fn simulate_key_with_flags(key_code: CGKeyCode, flags: CGEventFlags) -> Result<()> {
     let source = CGEventSource::new(CGEventSourceStateID::HIDSystemState)
         .ok()
         .context("Failed to create event source")?;
@@ -18,8 +101,9 @@ fn simulate_key_with_command(key_code: CGKeyCode) -> Result<()> {
     let key_down = CGEvent::new_keyboard_event(source.clone(), key_code, true)
         .ok()
         .context("Failed to create key down event")?;
-    key_down.set_flags(CGEventFlags::CGEventFlagCommand);
+    key_down.set_flags(flags);
     key_down.post(CGEventTapLocation::HID);
+    trace_event(key_code, true, flags);
 
     // Small delay between down and up
     thread::sleep(Duration::from_millis(10));
@@ -28,24 +112,97 @@ fn simulate_key_with_command(key_code: CGKeyCode) -> Result<()> {
     let key_up = CGEvent::new_keyboard_event(source, key_code, false)
         .ok()
         .context("Failed to create key up event")?;
-    key_up.set_flags(CGEventFlags::CGEventFlagCommand);
+    key_up.set_flags(flags);
     key_up.post(CGEventTapLocation::HID);
+    trace_event(key_code, false, flags);
 
     Ok(())
 }
 
-/// Simulate Cmd+C (copy)
-pub fn simulate_copy() -> Result<()> {
-    log::debug!("Simulating Cmd+C");
-    simulate_key_with_command(KEY_C)?;
+/// Simulate a key combo from the config (e.g. Cmd+C)
+fn simulate_combo(combo: &HotkeyConfig) -> Result<()> {
+    let key_code = chord_key_code(combo)?;
+    let flags = CGEventFlags::from_bits_truncate(chord_flags(combo));
+    simulate_key_with_flags(key_code, flags)
+}
+
+/// Simulate the copy combo (Cmd+C by default)
+pub fn simulate_copy(combo: &HotkeyConfig) -> Result<()> {
+    log::debug!("Simulating copy ({:?} + {})", combo.modifiers, combo.key);
+    simulate_combo(combo)?;
     // Give the system time to process the copy
     thread::sleep(Duration::from_millis(100));
     Ok(())
 }
 
-/// Simulate Cmd+V (paste)
-pub fn simulate_paste() -> Result<()> {
-    log::debug!("Simulating Cmd+V");
-    simulate_key_with_command(KEY_V)?;
+/// Simulate the paste combo (Cmd+V by default)
+pub fn simulate_paste(combo: &HotkeyConfig) -> Result<()> {
+    log::debug!("Simulating paste ({:?} + {})", combo.modifiers, combo.key);
+    simulate_combo(combo)?;
+    Ok(())
+}
+
+/// Simulate the paste combo through System Events (`paste_method = "applescript"`)
+///
+/// For when key events are blocked but AppleScript isn't; needs Automation
+/// access to System Events instead of Accessibility.
+pub fn simulate_paste_applescript(combo: &HotkeyConfig) -> Result<()> {
+    log::debug!("Simulating paste through System Events ({:?} + {})", combo.modifiers, combo.key);
+    let script = format!(
+        "tell application \"System Events\" to {}",
+        applescript_key_code(combo)?
+    );
+    let output = Command::new("osascript")
+        .arg("-e")
+        .arg(&script)
+        .output()
+        .context("Failed to run osascript")?;
+    if !output.status.success() {
+        bail!(
+            "System Events didn't press the keys: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// Type `text` key by key, for apps that ignore a synthetic paste
+///
+/// Much slower than pasting. Fails on a character `keys::typed_key` can't
+/// type, so check the text with `keys::can_type` first.
+pub fn type_text(text: &str) -> Result<()> {
+    log::debug!("Typing {} characters", text.chars().count());
+    for c in text.chars() {
+        let (key_code, shift) = typed_key(c).with_context(|| format!("Can't type {:?}", c))?;
+        let flags = if shift { CGEventFlags::CGEventFlagShift } else { CGEventFlags::empty() };
+        simulate_key_with_flags(key_code, flags)?;
+    }
+    Ok(())
+}
+
+/// Simulate the Right arrow key (moves the cursor to the end of a selection)
+pub fn simulate_right_arrow() -> Result<()> {
+    log::debug!("Simulating Right arrow");
+    simulate_key_with_flags(KEY_RIGHT_ARROW, CGEventFlags::empty())?;
+    // Give the app time to move the cursor before pasting
+    thread::sleep(Duration::from_millis(20));
+    Ok(())
+}
+
+/// Simulate Cmd+A, selecting everything in the focused text field
+pub fn simulate_select_all() -> Result<()> {
+    log::debug!("Simulating Cmd+A");
+    simulate_key_with_flags(KEY_A, CGEventFlags::CGEventFlagCommand)?;
+    // Give the app time to select before copying
+    thread::sleep(Duration::from_millis(20));
+    Ok(())
+}
+
+/// Simulate Shift+Left `count` times, extending the selection backwards
+pub fn simulate_select_left(count: usize) -> Result<()> {
+    log::debug!("Simulating Shift+Left x{}", count);
+    for _ in 0..count {
+        simulate_key_with_flags(KEY_LEFT_ARROW, CGEventFlags::CGEventFlagShift)?;
+    }
     Ok(())
 }
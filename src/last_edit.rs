@@ -0,0 +1,112 @@
+//! The most recent edit, kept across restarts
+//!
+//! After each successful edit, the original text, the result and the app it
+//! came from are saved to a small state file next to the stats, so the
+//! result can be reopened in the editor later (Reopen Last Edit). Only the
+//! latest edit is kept.
+
+use crate::clipboard::ClipboardAccess;
+use crate::config::{Config, NotificationEvent};
+use crate::edit_session;
+use crate::menu_bar;
+use anyhow::{Context, Result};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// A finished edit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LastEdit {
+    /// The selection before editing
+    pub original: String,
+    /// The text that was pasted back
+    pub result: String,
+    /// Bundle identifier of the app the text came from
+    pub bundle_id: Option<String>,
+    /// Extension the temp file had, for syntax highlighting
+    pub extension: Option<String>,
+}
+
+impl LastEdit {
+    /// Get the state file path
+    pub fn path() -> Option<PathBuf> {
+        ProjectDirs::from("com", "helix-anywhere", "helix-anywhere")
+            .map(|dirs| dirs.data_dir().join("last_edit.toml"))
+    }
+
+    /// Load the last edit, if there is one
+    pub fn load() -> Result<Option<Self>> {
+        let path = Self::path().context("Could not determine data directory")?;
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read last edit: {:?}", path))?;
+        toml::from_str(&content)
+            .map(Some)
+            .context("Failed to parse last edit")
+    }
+
+    /// Save as the last edit, replacing the previous one
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path().context("Could not determine data directory")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create data directory: {:?}", dir))?;
+        }
+
+        let content = toml::to_string(self).context("Failed to serialize last edit")?;
+
+        // Write to a temp file and rename, so a crash can't leave a truncated file
+        let temp_path = path.with_extension("toml.tmp");
+        fs::write(&temp_path, content)
+            .with_context(|| format!("Failed to write last edit: {:?}", temp_path))?;
+
+        // The text may be private, keep it readable by the user only
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let _ = fs::set_permissions(&temp_path, fs::Permissions::from_mode(0o600));
+        }
+
+        fs::rename(&temp_path, &path)
+            .with_context(|| format!("Failed to replace last edit: {:?}", path))?;
+        Ok(())
+    }
+
+    /// Check whether there is a last edit to reopen
+    pub fn exists() -> bool {
+        Self::path().is_some_and(|path| path.exists())
+    }
+}
+
+/// Save a finished edit, logging rather than failing the session on errors
+pub fn remember(last_edit: &LastEdit) {
+    if let Err(e) = last_edit.save() {
+        log::warn!("Failed to save last edit: {}", e);
+    }
+}
+
+/// Open the result of the last edit in the editor again
+///
+/// The window it came from may be long gone, so the new result is only put
+/// on the clipboard. Returns whether the text was changed.
+pub fn reopen(config: &Config, clipboard: &dyn ClipboardAccess) -> Result<bool> {
+    let mut last_edit = LastEdit::load()?.context("There is no edit to reopen")?;
+    log::info!("Reopening last edit ({} characters)", last_edit.result.len());
+
+    let Some(edited) = edit_session::edit_text(config, &last_edit.result, last_edit.extension.as_deref())? else {
+        log::info!("Last edit reopened but left unchanged");
+        return Ok(false);
+    };
+
+    clipboard.set_text(&edited)
+        .context("Failed to set clipboard with edited text")?;
+    menu_bar::notify(NotificationEvent::EditDone, "Edited text copied to the clipboard");
+
+    last_edit.result = edited;
+    remember(&last_edit);
+    Ok(true)
+}
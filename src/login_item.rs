@@ -0,0 +1,155 @@
+//! Starting the app at login
+//!
+//! On macOS 13 and later, the app bundle registers itself as a login item
+//! through SMAppService, which also lists it under System Settings → General
+//! → Login Items. Older systems, and the binary run outside the bundle, get a
+//! LaunchAgent in `~/Library/LaunchAgents` instead. The system is the source
+//! of truth: nothing about this is kept in the config.
+
+use anyhow::{bail, Context, Result};
+use cocoa::base::{id, nil, NO};
+use cocoa::foundation::NSAutoreleasePool;
+use directories::BaseDirs;
+use objc::runtime::Class;
+use objc::{class, msg_send, sel, sel_impl};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Links the framework so SMAppService can be looked up at runtime
+#[link(name = "ServiceManagement", kind = "framework")]
+extern "C" {}
+
+/// Label of the LaunchAgent, the bundle identifier
+const LAUNCH_AGENT_LABEL: &str = "com.helix-anywhere.helix-anywhere";
+
+// SMAppServiceStatus values (from SMAppService.h)
+const SM_APP_SERVICE_STATUS_ENABLED: i64 = 1;
+const SM_APP_SERVICE_STATUS_REQUIRES_APPROVAL: i64 = 2;
+
+/// Check whether the app starts at login
+pub fn is_enabled() -> bool {
+    unsafe {
+        let _pool = NSAutoreleasePool::new(nil);
+        match main_app_service() {
+            Some(service) => {
+                let status: i64 = msg_send![service, status];
+                status == SM_APP_SERVICE_STATUS_ENABLED
+                    || status == SM_APP_SERVICE_STATUS_REQUIRES_APPROVAL
+            }
+            None => launch_agent_path().is_some_and(|path| path.exists()),
+        }
+    }
+}
+
+/// Start the app at login, or stop doing so
+pub fn set_enabled(enabled: bool) -> Result<()> {
+    unsafe {
+        let _pool = NSAutoreleasePool::new(nil);
+        if let Some(service) = main_app_service() {
+            let mut error: id = nil;
+            let ok: cocoa::base::BOOL = if enabled {
+                msg_send![service, registerAndReturnError: &mut error]
+            } else {
+                msg_send![service, unregisterAndReturnError: &mut error]
+            };
+            if ok == NO {
+                let description: id = if error != nil {
+                    msg_send![error, localizedDescription]
+                } else {
+                    nil
+                };
+                bail!("{}", ns_string_to_string(description));
+            }
+            let status: i64 = msg_send![service, status];
+            if status == SM_APP_SERVICE_STATUS_REQUIRES_APPROVAL {
+                log::warn!("Login item needs approval in System Settings → General → Login Items");
+            }
+            return Ok(());
+        }
+    }
+
+    let path = launch_agent_path().context("Could not determine home directory")?;
+    if enabled {
+        let exe = std::env::current_exe().context("Could not determine the app's path")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {:?}", dir))?;
+        }
+        fs::write(&path, launch_agent_plist(&exe))
+            .with_context(|| format!("Failed to write {:?}", path))?;
+    } else if path.exists() {
+        fs::remove_file(&path).with_context(|| format!("Failed to remove {:?}", path))?;
+    }
+    Ok(())
+}
+
+/// The app's SMAppService, when running from the bundle on macOS 13 or later
+unsafe fn main_app_service() -> Option<id> {
+    let bundle: id = msg_send![class!(NSBundle), mainBundle];
+    let identifier: id = msg_send![bundle, bundleIdentifier];
+    if identifier == nil {
+        return None;
+    }
+    let class = Class::get("SMAppService")?;
+    let service: id = msg_send![class, mainAppService];
+    (service != nil).then_some(service)
+}
+
+unsafe fn ns_string_to_string(string: id) -> String {
+    if string == nil {
+        return "Unknown error".to_string();
+    }
+    let bytes: *const std::os::raw::c_char = msg_send![string, UTF8String];
+    std::ffi::CStr::from_ptr(bytes).to_string_lossy().into_owned()
+}
+
+/// Path of the LaunchAgent used without SMAppService
+fn launch_agent_path() -> Option<PathBuf> {
+    BaseDirs::new().map(|dirs| {
+        dirs.home_dir()
+            .join("Library/LaunchAgents")
+            .join(format!("{}.plist", LAUNCH_AGENT_LABEL))
+    })
+}
+
+/// LaunchAgent that runs `exe` once at login
+fn launch_agent_plist(exe: &Path) -> String {
+    let exe = exe
+        .to_string_lossy()
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{}</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>{}</string>
+    </array>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>ProcessType</key>
+    <string>Interactive</string>
+</dict>
+</plist>
+"#,
+        LAUNCH_AGENT_LABEL, exe
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn launch_agent_runs_the_app_at_load() {
+        let plist = launch_agent_plist(Path::new("/Applications/A & B.app/Contents/MacOS/helix-anywhere"));
+        assert!(plist.contains("<string>/Applications/A &amp; B.app/Contents/MacOS/helix-anywhere</string>"));
+        assert!(plist.contains("<key>RunAtLoad</key>\n    <true/>"));
+        assert!(plist.contains(LAUNCH_AGENT_LABEL));
+    }
+}
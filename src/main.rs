@@ -3,20 +3,48 @@
 // Suppress cfg warnings from `objc` crate's msg_send! macro
 #![allow(unexpected_cfgs)]
 
+mod accessibility;
+mod cli;
 mod clipboard;
-mod config;
+mod collect;
+mod control_socket;
 mod edit_session;
+mod file_watch;
 mod hotkey;
 mod hotkey_recorder;
 mod keystroke;
+mod last_edit;
+mod login_item;
 mod menu_bar;
-mod terminal;
+mod notify;
+mod services;
+mod stats;
+mod temp_files;
+mod terminal_probe;
+mod url_scheme;
 
-use anyhow::Result;
-use config::Config;
+// The platform-independent parts live in the core crate
+use helix_anywhere_core::{config, control, edit, helix_config, keys, markdown, terminal};
+
+use anyhow::{anyhow, bail, Result};
+use clipboard::SystemClipboard;
+use config::{ActionSource, Config, ModifierVariant, NotificationEvent};
+use core_graphics::event::CGEventTapLocation;
+use keys::HotkeyAction;
+use stats::SessionOutcome;
 use std::sync::{Arc, Mutex};
 
 fn main() -> Result<()> {
+    // Handle subcommands like `config export` without starting the app
+    // (Older macOS versions pass a -psn_ argument to apps opened from Finder)
+    let args: Vec<String> = std::env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with("-psn_"))
+        .collect();
+    if let Some(result) = cli::run(&args) {
+        return result;
+    }
+
     // Initialize logging
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
         .format_timestamp_secs()
@@ -24,9 +52,15 @@ fn main() -> Result<()> {
 
     log::info!("Starting helix-anywhere");
 
+    // AppKit needs a window server; fail clearly instead of crashing in it
+    if !menu_bar::has_gui_session() {
+        bail!("helix-anywhere requires a GUI login session (it can't run over SSH or headless)");
+    }
+
     // Load configuration
-    let config = Config::load()?;
+    let (config, load_issues) = Config::load_recovering()?;
     log::info!("Config loaded: {:?}", config);
+    keystroke::set_tracing(config.debug.trace_keystrokes);
 
     // Wrap config in Arc<Mutex> for sharing
     let config = Arc::new(Mutex::new(config));
@@ -34,7 +68,14 @@ fn main() -> Result<()> {
     let config_for_menu = config.clone();
 
     // Initialize the macOS app
-    menu_bar::init_app();
+    let show_in_dock = config.lock().unwrap().menu.show_in_dock;
+    menu_bar::init_app(show_in_dock);
+
+    // Handle helix-anywhere:// URLs from other tools
+    url_scheme::register(config.clone());
+
+    // Offer "Edit in Helix" in the Services menu of other apps
+    services::register(config.clone());
 
     // Create status bar item
     let _status_item = menu_bar::create_status_item(config_for_menu.clone(), move |cfg| {
@@ -43,39 +84,133 @@ fn main() -> Result<()> {
         }
     })?;
 
+    if let Some(backup) = load_issues.backup {
+        menu_bar::notify(
+            NotificationEvent::EditError,
+            &format!(
+                "The config file couldn't be read, using the defaults. It was saved to {}",
+                backup.display()
+            ),
+        );
+    } else if !load_issues.replaced.is_empty() {
+        menu_bar::notify(
+            NotificationEvent::EditError,
+            &format!(
+                "Some settings in the config file are invalid, using their defaults: {}",
+                load_issues.replaced.join(", ")
+            ),
+        );
+    }
+
     // Start hotkey listener with controller (supports runtime updates)
-    let hotkey_config = {
+    let (hotkey_config, bindings, timing, hid_event_tap) = {
         let cfg = config_for_hotkey.lock().unwrap();
-        cfg.hotkey.clone()
+        (cfg.hotkey.clone(), cfg.hotkey_bindings(), cfg.timing.clone(), cfg.debug.hid_event_tap)
+    };
+
+    // Troubleshooting: see keys before other apps' session-level taps do
+    let tap_location = if hid_event_tap {
+        log::warn!(
+            "Listening for the hotkey at the HID level (debug.hid_event_tap); \
+             this sees the keyboard events of all users"
+        );
+        CGEventTapLocation::HID
+    } else {
+        CGEventTapLocation::Session
     };
 
     let config_for_callback = config_for_hotkey.clone();
     let hotkey_controller = hotkey::start_hotkey_listener_with_controller(
-        hotkey_config.clone(),
-        move || {
+        bindings,
+        &timing,
+        tap_location,
+        move |action, modifiers| {
             // Clone config data so we don't hold the lock during the edit session
             // This prevents deadlock when user tries to change settings while editing
             let config_snapshot = {
                 let config = config_for_callback.lock().unwrap();
                 config.clone()
             };
-            if let Err(e) = edit_session::run_edit_session(&config_snapshot) {
-                log::error!("Edit session failed: {}", e);
+            if edit_session::in_post_session_cooldown(&config_snapshot) {
+                log::info!("Ignoring {:?}, a session just ended", action);
+                return;
             }
+            let variants = config_snapshot
+                .hotkey_for(action)
+                .map(|hotkey| keys::held_variants(hotkey, modifiers))
+                .unwrap_or_default();
+            let _ = run_action(&config_snapshot, action, &variants);
         },
     );
 
     // Pass the controller to the menu system for hotkey updates
     menu_bar::set_hotkey_controller(hotkey_controller);
 
-    let hotkey_display = hotkey::format_hotkey_display(&hotkey_config);
-    log::info!(
-        "helix-anywhere is running. Press {} to edit selected text.",
-        hotkey_display
-    );
+    // Find out whether the terminal can be waited on (opens a window once)
+    if config.lock().unwrap().terminal.probe_waiting {
+        terminal_probe::start(&config.lock().unwrap());
+    }
+
+    // Let scripts drive the app through a local socket
+    if config.lock().unwrap().automation.control_socket {
+        control_socket::start(config.clone());
+    }
+
+    let hotkey_display = keys::format_hotkey_display(&hotkey_config);
+    if hotkey_config.enabled {
+        log::info!(
+            "helix-anywhere is running. Press {} to edit selected text.",
+            hotkey_display
+        );
+    } else {
+        log::info!("helix-anywhere is running. The hotkey ({}) is turned off.", hotkey_display);
+    }
 
     // Run the app event loop (blocking)
     menu_bar::run_app();
 
     Ok(())
 }
+
+/// Run the edit session of a hotkey action
+///
+/// Failures are shown as a notification, and the outcome is counted when
+/// `record_stats` is on. `variants` are those selected by modifiers held with
+/// the hotkey, if any.
+fn run_action(config: &Config, action: HotkeyAction, variants: &[ModifierVariant]) -> Result<SessionOutcome> {
+    let result = match action {
+        HotkeyAction::EditSelection => edit_session::run_edit_session(config, &SystemClipboard, variants),
+        HotkeyAction::EditClipboard => edit_session::run_clipboard_session(config, &SystemClipboard),
+        HotkeyAction::Named(index) => match config.actions.get(index) {
+            Some(named) => {
+                log::info!("Running action {:?}", named.name);
+                let config = &config.for_action(named);
+                match named.source {
+                    ActionSource::Selection => {
+                        edit_session::run_edit_session(config, &SystemClipboard, variants)
+                    }
+                    ActionSource::Clipboard => {
+                        edit_session::run_clipboard_session(config, &SystemClipboard)
+                    }
+                }
+            }
+            None => Err(anyhow!("No action {} in the config", index)),
+        },
+    };
+    edit_session::mark_session_end();
+    if let Err(ref e) = result {
+        log::error!("Edit session failed: {}", e);
+        menu_bar::notify_with_recovery(
+            NotificationEvent::EditError,
+            &format!("Edit failed: {}", e),
+            menu_bar::RecoveryAction::RetryEdit(action),
+        );
+    }
+    if config.session.record_stats {
+        match result {
+            Ok(outcome) => stats::record_outcome(outcome),
+            Err(ref e) => stats::record_error(e),
+        }
+    }
+    result
+}
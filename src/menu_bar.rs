@@ -1,18 +1,35 @@
-use crate::config::{Config, HotkeyConfig};
-use crate::hotkey::{format_hotkey_display, HotkeyController};
+use crate::clipboard::{ClipboardAccess, SystemClipboard};
+use crate::config::{Config, HotkeyConfig, NotificationEvent, NotificationsConfig, TimingConfig};
+use crate::edit_session;
+use crate::hotkey::HotkeyController;
+use crate::keys::{
+    format_hotkey_display, get_modifier_mask, hotkey_presets, modifier_flags,
+    modifiers_to_config, HotkeyAction,
+};
 use crate::hotkey_recorder;
-use crate::terminal::Terminal;
+use crate::keystroke;
+use crate::last_edit::{self, LastEdit};
+use crate::login_item;
+use crate::notify;
+use crate::stats::Stats;
+use crate::temp_files;
+use crate::terminal::{self, Terminal};
 use anyhow::Result;
 use cocoa::appkit::{
-    NSApp, NSApplication, NSApplicationActivationPolicyAccessory, NSMenu, NSMenuItem,
+    NSApp, NSApplication, NSApplicationActivationPolicyAccessory,
+    NSApplicationActivationPolicyRegular, NSMenu, NSMenuItem,
     NSSquareStatusItemLength, NSStatusBar, NSStatusItem,
 };
-use cocoa::base::{id, nil, NO, YES};
+use cocoa::base::{id, nil, BOOL, NO, YES};
 use cocoa::foundation::{NSAutoreleasePool, NSSize, NSString};
+use core_foundation::base::{CFRelease, CFTypeRef};
 use objc::declare::ClassDecl;
 use objc::runtime::{Class, Object, Sel};
 use objc::{class, msg_send, sel, sel_impl};
+use std::borrow::Cow;
+use std::ffi::c_void;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 // Embed the icon at compile time (36x36 for retina, will be displayed at 18x18 points)
 // This is a template image: pure black pixels with alpha channel for shape
@@ -21,21 +38,121 @@ static ICON_DATA: &[u8] = include_bytes!("../assets/logo_app.png");
 // Store config globally for menu callbacks
 static mut GLOBAL_CONFIG: Option<Arc<Mutex<Config>>> = None;
 static mut SAVE_CONFIG_CALLBACK: Option<Box<dyn Fn(&Config) + Send + Sync>> = None;
+// Store the status menu so we can look up its shortcuts
+static mut STATUS_MENU: Option<id> = None;
 // Store the terminal submenu so we can update checkmarks
 static mut TERMINAL_SUBMENU: Option<id> = None;
+// Store the window size submenu so we can update checkmarks
+static mut WINDOW_SIZE_SUBMENU: Option<id> = None;
 // Store the hotkey submenu so we can update the display
 static mut HOTKEY_SUBMENU: Option<id> = None;
+// Store the hotkey presets submenu so we can update checkmarks
+static mut PRESETS_SUBMENU: Option<id> = None;
+// Store the clipboard hotkey display item so we can update it
+static mut CLIPBOARD_HOTKEY_ITEM: Option<id> = None;
+
+/// Tags of the "Enabled" items in the Hotkey submenu
+const BINDING_TAG_HOTKEY: i64 = 0;
+const BINDING_TAG_CLIPBOARD_HOTKEY: i64 = 1;
+
+// Store the behavior submenu reference for updating checkmarks
+static mut BEHAVIOR_SUBMENU: Option<id> = None;
+
 // Store the hotkey controller for updating the listener
 static mut HOTKEY_CONTROLLER: Option<HotkeyController> = None;
 
+/// Longest wait for the hotkey listener to stop when quitting
+const LISTENER_STOP_TIMEOUT: Duration = Duration::from_secs(1);
+// What the button of the last failure notification does
+static PENDING_RECOVERY: Mutex<Option<RecoveryAction>> = Mutex::new(None);
+
+/// NSUserNotificationActivationTypeActionButtonClicked
+const NOTIFICATION_ACTION_BUTTON_CLICKED: i64 = 2;
+
+/// Recovery offered by a button on a failure notification
+pub enum RecoveryAction {
+    /// Paste the edited text into the original app again
+    RetryPaste {
+        app: Option<edit_session::FrontmostApp>,
+        text: String,
+    },
+    /// Run the failed edit session again
+    RetryEdit(HotkeyAction),
+}
+
+impl RecoveryAction {
+    fn button_title(&self) -> &'static str {
+        match self {
+            RecoveryAction::RetryPaste { .. } => "Retry Paste",
+            RecoveryAction::RetryEdit(_) => "Reopen in Helix",
+        }
+    }
+}
+
+/// Window sizes offered in the menu, in columns and lines
+const WINDOW_SIZE_PRESETS: &[(&str, u32, u32)] = &[
+    ("Small", 80, 24),
+    ("Medium", 100, 30),
+    ("Large", 140, 45),
+];
+
+/// Settings that can be turned on and off in the Behavior submenu
+const BEHAVIOR_TOGGLES: &[(&str, fn(&mut Config) -> &mut bool)] = &[
+    ("Return to the Original Window Only", |config| &mut config.session.verify_paste_target),
+    ("Reselect After Pasting", |config| &mut config.session.reselect_after_paste),
+    ("Restore Clipboard After Pasting", |config| &mut config.session.restore_clipboard_after_paste),
+    ("Paste on Every Save", |config| &mut config.session.paste_on_save),
+    ("Open Selected Paths and URLs", |config| &mut config.session.smart_open),
+    ("Edit Rich Text as Markdown", |config| &mut config.session.rich_text_as_markdown),
+    ("Wrap to the Window", |config| &mut config.editor.wrap_to_window),
+    ("Remember Last Edit", |config| &mut config.session.remember_last_edit),
+    ("Record Statistics", |config| &mut config.session.record_stats),
+    ("Show Notifications", |config| &mut config.notifications.show),
+    ("Notify When Done", |config| &mut config.notifications.edit_done),
+    ("Notify on Errors", |config| &mut config.notifications.edit_error),
+];
+
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    fn CGSessionCopyCurrentDictionary() -> CFTypeRef;
+}
+
+// libdispatch, part of libSystem
+extern "C" {
+    static _dispatch_main_q: c_void;
+    fn dispatch_sync_f(queue: *const c_void, context: *mut c_void, work: extern "C" fn(*mut c_void));
+}
+
+/// Check whether the process runs in a GUI login session
+///
+/// Over SSH or in other headless contexts there is no window server to
+/// connect to, and AppKit and event taps fail or crash.
+pub fn has_gui_session() -> bool {
+    unsafe {
+        let session = CGSessionCopyCurrentDictionary();
+        if session.is_null() {
+            return false;
+        }
+        CFRelease(session);
+        true
+    }
+}
+
 /// Initialize the menu bar app
-pub fn init_app() {
+///
+/// The app is menu bar only unless `show_in_dock` is set.
+pub fn init_app(show_in_dock: bool) {
     unsafe {
         let _pool = NSAutoreleasePool::new(nil);
 
         // Initialize the application
         let app = NSApp();
-        app.setActivationPolicy_(NSApplicationActivationPolicyAccessory);
+        let policy = if show_in_dock {
+            NSApplicationActivationPolicyRegular
+        } else {
+            NSApplicationActivationPolicyAccessory
+        };
+        app.setActivationPolicy_(policy);
     }
 }
 
@@ -55,7 +172,11 @@ pub fn create_status_item(config: Arc<Mutex<Config>>, on_save: impl Fn(&Config)
         let button: id = msg_send![status_item, button];
 
         // Try to load icon - first from embedded data, with fallback to text
-        let image: id = {
+        let text_icon = config.lock().unwrap().menu.text_icon;
+        let image: id = if text_icon {
+            log::info!("Text icon requested (menu.text_icon)");
+            nil
+        } else {
             // Create NSData from embedded icon bytes
             let ns_data: id = msg_send![class!(NSData), dataWithBytes:ICON_DATA.as_ptr() length:ICON_DATA.len()];
             if ns_data == nil {
@@ -67,8 +188,19 @@ pub fn create_status_item(config: Arc<Mutex<Config>>, on_save: impl Fn(&Config)
                 let img: id = msg_send![img, initWithData: ns_data];
                 if img == nil {
                     log::warn!("Failed to create NSImage from data");
+                    nil
+                } else {
+                    // A corrupt asset can still give an image, which would show a blank button
+                    let valid: BOOL = msg_send![img, isValid];
+                    let size: NSSize = msg_send![img, size];
+                    if valid == NO || size.width <= 0.0 || size.height <= 0.0 {
+                        log::warn!("Embedded icon is invalid ({}x{})", size.width, size.height);
+                        let _: () = msg_send![img, release];
+                        nil
+                    } else {
+                        img
+                    }
                 }
-                img
             }
         };
 
@@ -86,104 +218,129 @@ pub fn create_status_item(config: Arc<Mutex<Config>>, on_save: impl Fn(&Config)
         } else {
             // Fallback to text
             log::warn!("Using text fallback for menu bar");
-            let title = NSString::alloc(nil).init_str("H");
+            let title = ns_string("H");
             let _: () = msg_send![button, setTitle: title];
         }
 
         // Create menu
         let menu = NSMenu::new(nil).autorelease();
+        STATUS_MENU = Some(menu);
 
-        // Add "About" item
-        let about_title = NSString::alloc(nil).init_str("helix-anywhere v0.1.1");
-        let about_item = NSMenuItem::alloc(nil)
-            .initWithTitle_action_keyEquivalent_(about_title, Sel::from_ptr(std::ptr::null()), NSString::alloc(nil).init_str(""))
-            .autorelease();
-        let _: () = msg_send![about_item, setEnabled: NO];
-        menu.addItem_(about_item);
+        // Register the menu delegate class
+        register_menu_delegate_class();
 
-        // Add separator
-        let separator = NSMenuItem::separatorItem(nil);
-        menu.addItem_(separator);
+        // Restart the hotkey listener after the Mac wakes up
+        register_wake_observer();
+        register_notification_delegate();
+
+        let menu_config = {
+            let cfg = config.lock().unwrap();
+            cfg.menu.clone()
+        };
+
+        // Add "About" item (opens the project page when clicked)
+        if menu_config.show_about {
+            let about_label = menu_config.about_label.unwrap_or_else(|| {
+                format!("helix-anywhere v{}", env!("CARGO_PKG_VERSION"))
+            });
+            let about_title = ns_string(&about_label);
+            let about_item = NSMenuItem::alloc(nil)
+                .initWithTitle_action_keyEquivalent_(about_title, sel!(openAbout:), ns_string(""))
+                .autorelease();
+            let delegate: id = msg_send![Class::get("MenuDelegate").unwrap(), new];
+            let _: () = msg_send![about_item, setTarget: delegate];
+            menu.addItem_(about_item);
+
+            // Add separator
+            let separator = NSMenuItem::separatorItem(nil);
+            menu.addItem_(separator);
+        }
 
         // Add "Terminal" submenu
-        let terminal_title = NSString::alloc(nil).init_str("Terminal");
+        let terminal_title = ns_string("Terminal");
         let terminal_item = NSMenuItem::alloc(nil)
-            .initWithTitle_action_keyEquivalent_(terminal_title, Sel::from_ptr(std::ptr::null()), NSString::alloc(nil).init_str(""))
+            .initWithTitle_action_keyEquivalent_(terminal_title, Sel::from_ptr(std::ptr::null()), ns_string(""))
             .autorelease();
 
         let terminal_submenu = NSMenu::new(nil).autorelease();
-        let terminal_submenu_title = NSString::alloc(nil).init_str("Terminal");
+        let terminal_submenu_title = ns_string("Terminal");
         let _: () = msg_send![terminal_submenu, setTitle: terminal_submenu_title];
 
-        // Register the menu delegate class
-        register_menu_delegate_class();
-
         // Add terminal options
         let current_terminal = {
             let cfg = config.lock().unwrap();
             cfg.terminal.name.clone()
         };
+        populate_terminal_submenu(terminal_submenu, &current_terminal);
 
-        // NSOnState = 1, NSOffState = 0
-        const NS_ON_STATE: i64 = 1;
-        const NS_OFF_STATE: i64 = 0;
-
-        for terminal in Terminal::all() {
-            let is_installed = terminal.is_installed();
-            let is_current = terminal.config_name() == current_terminal;
+        // Rebuild the items whenever the submenu opens, so terminals installed
+        // or removed while the app runs show up right away
+        let terminal_delegate: id = msg_send![Class::get("MenuDelegate").unwrap(), new];
+        let _: () = msg_send![terminal_submenu, setDelegate: terminal_delegate];
 
-            let item = if is_installed {
-                let item_title = NSString::alloc(nil).init_str(terminal.display_name());
-                let selector = sel!(selectTerminal:);
-                let item = NSMenuItem::alloc(nil)
-                    .initWithTitle_action_keyEquivalent_(item_title, selector, NSString::alloc(nil).init_str(""))
-                    .autorelease();
-
-                // Set checkmark state
-                let state = if is_current { NS_ON_STATE } else { NS_OFF_STATE };
-                let _: () = msg_send![item, setState: state];
+        // Store submenu reference for later updates
+        TERMINAL_SUBMENU = Some(terminal_submenu);
 
-                item
-            } else {
-                let disabled_name = format!("{} (not installed)", terminal.display_name());
-                let disabled_title = NSString::alloc(nil).init_str(&disabled_name);
-                let item = NSMenuItem::alloc(nil)
-                    .initWithTitle_action_keyEquivalent_(disabled_title, Sel::from_ptr(std::ptr::null()), NSString::alloc(nil).init_str(""))
-                    .autorelease();
-                let _: () = msg_send![item, setEnabled: NO];
-                item
-            };
+        let _: () = msg_send![terminal_item, setSubmenu: terminal_submenu];
+        menu.addItem_(terminal_item);
 
-            // Store terminal name as represented object
-            let terminal_name_str = NSString::alloc(nil).init_str(terminal.config_name());
-            let _: () = msg_send![item, setRepresentedObject: terminal_name_str];
+        // Add "Window Size" submenu
+        let size_title = ns_string("Window Size");
+        let size_item = NSMenuItem::alloc(nil)
+            .initWithTitle_action_keyEquivalent_(size_title, Sel::from_ptr(std::ptr::null()), ns_string(""))
+            .autorelease();
+        let size_submenu = NSMenu::new(nil).autorelease();
 
-            // Set target to our delegate
-            let delegate_class = Class::get("MenuDelegate").unwrap();
-            let delegate: id = msg_send![delegate_class, new];
+        // Current size display item (disabled, also shows sizes set in the config)
+        let current_size_item = NSMenuItem::alloc(nil)
+            .initWithTitle_action_keyEquivalent_(
+                ns_string(""),
+                Sel::from_ptr(std::ptr::null()),
+                ns_string(""),
+            )
+            .autorelease();
+        let _: () = msg_send![current_size_item, setEnabled: NO];
+        size_submenu.addItem_(current_size_item);
+        size_submenu.addItem_(NSMenuItem::separatorItem(nil));
+
+        for (index, (name, width, height)) in WINDOW_SIZE_PRESETS.iter().enumerate() {
+            let title = ns_string(&format!("{} ({} × {})", name, width, height));
+            let item = NSMenuItem::alloc(nil)
+                .initWithTitle_action_keyEquivalent_(
+                    title,
+                    sel!(selectWindowSize:),
+                    ns_string(""),
+                )
+                .autorelease();
+
+            // Store the preset index to find it again when selected
+            let _: () = msg_send![item, setTag: index as i64];
+            let delegate: id = msg_send![Class::get("MenuDelegate").unwrap(), new];
             let _: () = msg_send![item, setTarget: delegate];
-
-            terminal_submenu.addItem_(item);
+            size_submenu.addItem_(item);
         }
 
-        // Store submenu reference for later updates
-        TERMINAL_SUBMENU = Some(terminal_submenu);
+        WINDOW_SIZE_SUBMENU = Some(size_submenu);
+        {
+            let cfg = config.lock().unwrap();
+            update_window_size_checkmarks(cfg.terminal.width, cfg.terminal.height);
+        }
 
-        let _: () = msg_send![terminal_item, setSubmenu: terminal_submenu];
-        menu.addItem_(terminal_item);
+        let _: () = msg_send![size_item, setSubmenu: size_submenu];
+        menu.addItem_(size_item);
 
         // Add "Hotkey" submenu
-        let hotkey_title = NSString::alloc(nil).init_str("Hotkey");
+        let hotkey_title = ns_string("Hotkey");
         let hotkey_item = NSMenuItem::alloc(nil)
             .initWithTitle_action_keyEquivalent_(
                 hotkey_title,
                 Sel::from_ptr(std::ptr::null()),
-                NSString::alloc(nil).init_str(""),
+                ns_string(""),
             )
             .autorelease();
 
         let hotkey_submenu = NSMenu::new(nil).autorelease();
-        let hotkey_submenu_title = NSString::alloc(nil).init_str("Hotkey");
+        let hotkey_submenu_title = ns_string("Hotkey");
         let _: () = msg_send![hotkey_submenu, setTitle: hotkey_submenu_title];
 
         // Current hotkey display item (disabled, just shows current setting)
@@ -191,62 +348,284 @@ pub fn create_status_item(config: Arc<Mutex<Config>>, on_save: impl Fn(&Config)
             let cfg = config.lock().unwrap();
             format_hotkey_display(&cfg.hotkey)
         };
-        let current_title = NSString::alloc(nil).init_str(&format!("Current: {}", current_hotkey));
+        let current_title = ns_string(&format!("Current: {}", current_hotkey));
         let current_item = NSMenuItem::alloc(nil)
             .initWithTitle_action_keyEquivalent_(
                 current_title,
                 Sel::from_ptr(std::ptr::null()),
-                NSString::alloc(nil).init_str(""),
+                ns_string(""),
             )
             .autorelease();
         let _: () = msg_send![current_item, setEnabled: NO];
         hotkey_submenu.addItem_(current_item);
 
+        // "Enabled" item, to turn the hotkey off without losing it
+        // (checkmark set when the menu is shown, see validateMenuItem:)
+        let delegate_class = Class::get("MenuDelegate").unwrap();
+        let enabled_item = NSMenuItem::alloc(nil)
+            .initWithTitle_action_keyEquivalent_(
+                ns_string("Enabled"),
+                sel!(toggleHotkeyEnabled:),
+                ns_string(""),
+            )
+            .autorelease();
+        let _: () = msg_send![enabled_item, setTag: BINDING_TAG_HOTKEY];
+        let delegate_enabled: id = msg_send![delegate_class, new];
+        let _: () = msg_send![enabled_item, setTarget: delegate_enabled];
+        hotkey_submenu.addItem_(enabled_item);
+
         // Separator
         hotkey_submenu.addItem_(NSMenuItem::separatorItem(nil));
 
         // "Record New Hotkey..." item
-        let record_title = NSString::alloc(nil).init_str("Record New Hotkey...");
+        let record_title = ns_string("Record New Hotkey...");
         let record_item = NSMenuItem::alloc(nil)
             .initWithTitle_action_keyEquivalent_(
                 record_title,
                 sel!(recordHotkey:),
-                NSString::alloc(nil).init_str(""),
+                ns_string(""),
             )
             .autorelease();
-        let delegate_class = Class::get("MenuDelegate").unwrap();
         let delegate: id = msg_send![delegate_class, new];
         let _: () = msg_send![record_item, setTarget: delegate];
         hotkey_submenu.addItem_(record_item);
 
         // "Reset to Default" item
-        let reset_title = NSString::alloc(nil).init_str("Reset to Default");
+        let reset_title = ns_string("Reset to Default");
         let reset_item = NSMenuItem::alloc(nil)
             .initWithTitle_action_keyEquivalent_(
                 reset_title,
                 sel!(resetHotkey:),
-                NSString::alloc(nil).init_str(""),
+                ns_string(""),
             )
             .autorelease();
         let delegate2: id = msg_send![delegate_class, new];
         let _: () = msg_send![reset_item, setTarget: delegate2];
         hotkey_submenu.addItem_(reset_item);
 
+        // "Presets" submenu with known-good hotkeys
+        let presets_title = ns_string("Presets");
+        let presets_item = NSMenuItem::alloc(nil)
+            .initWithTitle_action_keyEquivalent_(
+                presets_title,
+                Sel::from_ptr(std::ptr::null()),
+                ns_string(""),
+            )
+            .autorelease();
+        let presets_submenu = NSMenu::new(nil).autorelease();
+
+        for (index, preset) in hotkey_presets().iter().enumerate() {
+            if hotkey_recorder::is_reserved_hotkey(preset).is_some() {
+                continue;
+            }
+            let title = ns_string(&format_hotkey_display(preset));
+            let item = NSMenuItem::alloc(nil)
+                .initWithTitle_action_keyEquivalent_(
+                    title,
+                    sel!(selectHotkeyPreset:),
+                    ns_string(""),
+                )
+                .autorelease();
+
+            // Store the preset index to find it again when selected
+            let _: () = msg_send![item, setTag: index as i64];
+            let delegate: id = msg_send![delegate_class, new];
+            let _: () = msg_send![item, setTarget: delegate];
+            presets_submenu.addItem_(item);
+        }
+
+        PRESETS_SUBMENU = Some(presets_submenu);
+        let _: () = msg_send![presets_item, setSubmenu: presets_submenu];
+        hotkey_submenu.addItem_(presets_item);
+
+        // Separator
+        hotkey_submenu.addItem_(NSMenuItem::separatorItem(nil));
+
+        // Clipboard hotkey display item (disabled, just shows current setting)
+        let clipboard_hotkey = {
+            let cfg = config.lock().unwrap();
+            cfg.clipboard_hotkey.clone()
+        };
+        let clipboard_title =
+            ns_string(&clipboard_hotkey_title(clipboard_hotkey.as_ref()));
+        let clipboard_item = NSMenuItem::alloc(nil)
+            .initWithTitle_action_keyEquivalent_(
+                clipboard_title,
+                Sel::from_ptr(std::ptr::null()),
+                ns_string(""),
+            )
+            .autorelease();
+        let _: () = msg_send![clipboard_item, setEnabled: NO];
+        hotkey_submenu.addItem_(clipboard_item);
+        CLIPBOARD_HOTKEY_ITEM = Some(clipboard_item);
+
+        // "Enabled" item for the clipboard hotkey (only enabled while one is set)
+        let clipboard_enabled_item = NSMenuItem::alloc(nil)
+            .initWithTitle_action_keyEquivalent_(
+                ns_string("Enabled"),
+                sel!(toggleHotkeyEnabled:),
+                ns_string(""),
+            )
+            .autorelease();
+        let _: () = msg_send![clipboard_enabled_item, setTag: BINDING_TAG_CLIPBOARD_HOTKEY];
+        let delegate_clipboard_enabled: id = msg_send![delegate_class, new];
+        let _: () = msg_send![clipboard_enabled_item, setTarget: delegate_clipboard_enabled];
+        hotkey_submenu.addItem_(clipboard_enabled_item);
+
+        // "Record Clipboard Hotkey..." item
+        let record_clipboard_title = ns_string("Record Clipboard Hotkey...");
+        let record_clipboard_item = NSMenuItem::alloc(nil)
+            .initWithTitle_action_keyEquivalent_(
+                record_clipboard_title,
+                sel!(recordClipboardHotkey:),
+                ns_string(""),
+            )
+            .autorelease();
+        let delegate_record_clipboard: id = msg_send![delegate_class, new];
+        let _: () = msg_send![record_clipboard_item, setTarget: delegate_record_clipboard];
+        hotkey_submenu.addItem_(record_clipboard_item);
+
+        // "Remove Clipboard Hotkey" item (only enabled while one is set)
+        let remove_clipboard_title = ns_string("Remove Clipboard Hotkey");
+        let remove_clipboard_item = NSMenuItem::alloc(nil)
+            .initWithTitle_action_keyEquivalent_(
+                remove_clipboard_title,
+                sel!(removeClipboardHotkey:),
+                ns_string(""),
+            )
+            .autorelease();
+        let delegate_remove_clipboard: id = msg_send![delegate_class, new];
+        let _: () = msg_send![remove_clipboard_item, setTarget: delegate_remove_clipboard];
+        hotkey_submenu.addItem_(remove_clipboard_item);
+
         // Store submenu reference for later updates
         HOTKEY_SUBMENU = Some(hotkey_submenu);
+        {
+            let cfg = config.lock().unwrap();
+            update_preset_checkmarks(&cfg.hotkey);
+        }
 
         let _: () = msg_send![hotkey_item, setSubmenu: hotkey_submenu];
         menu.addItem_(hotkey_item);
 
+        // Add "Behavior" submenu
+        let behavior_title = ns_string("Behavior");
+        let behavior_item = NSMenuItem::alloc(nil)
+            .initWithTitle_action_keyEquivalent_(behavior_title, Sel::from_ptr(std::ptr::null()), ns_string(""))
+            .autorelease();
+        let behavior_submenu = NSMenu::new(nil).autorelease();
+
+        for (index, (title, _)) in BEHAVIOR_TOGGLES.iter().enumerate() {
+            let item = NSMenuItem::alloc(nil)
+                .initWithTitle_action_keyEquivalent_(
+                    ns_string(title),
+                    sel!(toggleBehavior:),
+                    ns_string(""),
+                )
+                .autorelease();
+
+            // Store the toggle index to find it again when selected
+            let _: () = msg_send![item, setTag: index as i64];
+            let delegate: id = msg_send![Class::get("MenuDelegate").unwrap(), new];
+            let _: () = msg_send![item, setTarget: delegate];
+            behavior_submenu.addItem_(item);
+        }
+
+        BEHAVIOR_SUBMENU = Some(behavior_submenu);
+        update_behavior_checkmarks(&config.lock().unwrap());
+
+        let _: () = msg_send![behavior_item, setSubmenu: behavior_submenu];
+        menu.addItem_(behavior_item);
+
+        // Add "Reveal Temp File" item (only enabled while an edit is in progress)
+        let reveal_title = ns_string("Reveal Temp File");
+        let reveal_item = NSMenuItem::alloc(nil)
+            .initWithTitle_action_keyEquivalent_(
+                reveal_title,
+                sel!(revealTempFile:),
+                ns_string(""),
+            )
+            .autorelease();
+        let delegate_reveal: id = msg_send![delegate_class, new];
+        let _: () = msg_send![reveal_item, setTarget: delegate_reveal];
+        menu.addItem_(reveal_item);
+
+        // Add "Reopen Last Edit" item (only enabled once something was edited)
+        let reopen_title = ns_string("Reopen Last Edit");
+        let reopen_item = NSMenuItem::alloc(nil)
+            .initWithTitle_action_keyEquivalent_(
+                reopen_title,
+                sel!(reopenLastEdit:),
+                ns_string(""),
+            )
+            .autorelease();
+        let delegate_reopen: id = msg_send![delegate_class, new];
+        let _: () = msg_send![reopen_item, setTarget: delegate_reopen];
+        menu.addItem_(reopen_item);
+
+        // Add "Clean Temp Files…" item
+        let clean_title = ns_string("Clean Temp Files…");
+        let clean_item = NSMenuItem::alloc(nil)
+            .initWithTitle_action_keyEquivalent_(
+                clean_title,
+                sel!(cleanTempFiles:),
+                ns_string(""),
+            )
+            .autorelease();
+        let delegate_clean: id = msg_send![delegate_class, new];
+        let _: () = msg_send![clean_item, setTarget: delegate_clean];
+        menu.addItem_(clean_item);
+
+        // Add "Statistics…" item
+        let stats_title = ns_string("Statistics…");
+        let stats_item = NSMenuItem::alloc(nil)
+            .initWithTitle_action_keyEquivalent_(
+                stats_title,
+                sel!(showStatistics:),
+                ns_string(""),
+            )
+            .autorelease();
+        let delegate_stats: id = msg_send![delegate_class, new];
+        let _: () = msg_send![stats_item, setTarget: delegate_stats];
+        menu.addItem_(stats_item);
+
+        // Add "Launch at Login" item (checkmark set when the menu is shown,
+        // from the system's login items, see validateMenuItem:)
+        let login_item = NSMenuItem::alloc(nil)
+            .initWithTitle_action_keyEquivalent_(
+                ns_string("Launch at Login"),
+                sel!(toggleLaunchAtLogin:),
+                ns_string(""),
+            )
+            .autorelease();
+        let delegate_login: id = msg_send![delegate_class, new];
+        let _: () = msg_send![login_item, setTarget: delegate_login];
+        menu.addItem_(login_item);
+
+        // Add "Reset All Settings…" item
+        let reset_all_title = ns_string("Reset All Settings…");
+        let reset_all_item = NSMenuItem::alloc(nil)
+            .initWithTitle_action_keyEquivalent_(
+                reset_all_title,
+                sel!(resetAllSettings:),
+                ns_string(""),
+            )
+            .autorelease();
+        let delegate3: id = msg_send![delegate_class, new];
+        let _: () = msg_send![reset_all_item, setTarget: delegate3];
+        menu.addItem_(reset_all_item);
+
         // Add separator
         let separator2 = NSMenuItem::separatorItem(nil);
         menu.addItem_(separator2);
 
         // Add "Quit" item
-        let quit_title = NSString::alloc(nil).init_str("Quit");
+        let quit_title = ns_string("Quit");
         let quit_item = NSMenuItem::alloc(nil)
-            .initWithTitle_action_keyEquivalent_(quit_title, sel!(terminate:), NSString::alloc(nil).init_str("q"))
+            .initWithTitle_action_keyEquivalent_(quit_title, sel!(quitApp:), ns_string("q"))
             .autorelease();
+        let delegate_quit: id = msg_send![delegate_class, new];
+        let _: () = msg_send![quit_item, setTarget: delegate_quit];
         menu.addItem_(quit_item);
 
         // Set the menu
@@ -296,68 +675,372 @@ fn register_menu_delegate_class() {
         }
     }
 
+    // Add the selectWindowSize: method
+    extern "C" fn select_window_size(_this: &Object, _cmd: Sel, sender: id) {
+        let index: i64 = unsafe { msg_send![sender, tag] };
+        let Some(&(name, width, height)) = WINDOW_SIZE_PRESETS.get(index as usize) else {
+            return;
+        };
+
+        log::info!("Selected window size: {} ({}x{})", name, width, height);
+        save_window_size(width, height);
+        warn_if_size_ignored();
+    }
+
+    // Add the toggleBehavior: method
+    extern "C" fn toggle_behavior(_this: &Object, _cmd: Sel, sender: id) {
+        let index: i64 = unsafe { msg_send![sender, tag] };
+        let Some(&(title, field)) = BEHAVIOR_TOGGLES.get(index as usize) else {
+            return;
+        };
+
+        unsafe {
+            let config = match GLOBAL_CONFIG {
+                Some(ref config) => config,
+                None => return,
+            };
+            let mut cfg = config.lock().unwrap();
+            let value = field(&mut cfg);
+            *value = !*value;
+            log::info!("{}: {}", title, if *value { "on" } else { "off" });
+
+            if let Some(ref save_fn) = SAVE_CONFIG_CALLBACK {
+                save_fn(&cfg);
+            }
+            update_behavior_checkmarks(&cfg);
+        }
+    }
+
     // Add the recordHotkey: method
     extern "C" fn record_hotkey(_this: &Object, _cmd: Sel, _sender: id) {
         log::info!("Starting hotkey recording...");
-        show_notification("Helix Anywhere", "Press your new hotkey combination...");
+        notify(NotificationEvent::RecordStarted, "Press your new hotkey combination...");
+
+        // The app's own shortcuts (like Cmd+Q for Quit) must stay usable
+        let mut app_shortcuts = unsafe {
+            match STATUS_MENU {
+                Some(menu) => menu_shortcuts(menu),
+                None => Vec::new(),
+            }
+        };
+        if let Some(clipboard_hotkey) = current_clipboard_hotkey() {
+            app_shortcuts.push(("Edit Clipboard".to_string(), clipboard_hotkey));
+        }
 
+        let timeout = recording_timeout();
         hotkey_recorder::record_next_hotkey(
+            app_shortcuts,
+            timeout,
             // On recorded
-            |new_hotkey| {
+            |mut new_hotkey| {
                 log::info!("Recorded new hotkey: {:?}", new_hotkey);
-
-                // Update config
-                unsafe {
-                    if let Some(ref config) = GLOBAL_CONFIG {
-                        let mut cfg = config.lock().unwrap();
-                        cfg.hotkey = new_hotkey.clone();
-
-                        // Save config
-                        if let Some(ref save_fn) = SAVE_CONFIG_CALLBACK {
-                            save_fn(&cfg);
-                        }
-                    }
-
-                    // Update hotkey listener
-                    if let Some(ref controller) = HOTKEY_CONTROLLER {
-                        controller.update_hotkey(new_hotkey.clone());
-                    }
-
-                    // Update menu display
-                    update_hotkey_display(&new_hotkey);
+                // Recording changes the keys, not whether the hotkey is turned on
+                let current = current_hotkey();
+                if let Some(ref current) = current {
+                    new_hotkey.enabled = current.enabled;
                 }
+                // Re-recording the active hotkey would only rebuild the event tap
+                if current.is_some_and(|current| current == new_hotkey) {
+                    notify(NotificationEvent::RecordDone, "Hotkey unchanged");
+                    return;
+                }
+                apply_hotkey(new_hotkey.clone());
 
                 // Show confirmation
                 let display = format_hotkey_display(&new_hotkey);
-                show_notification("Helix Anywhere", &format!("Hotkey set to {}", display));
+                notify(NotificationEvent::RecordDone, &format!("Hotkey set to {}", display));
             },
             // On timeout
-            || {
+            move || {
                 log::info!("Hotkey recording timed out");
-                show_notification("Helix Anywhere", "Hotkey recording timed out");
+                notify(NotificationEvent::RecordDone, &recording_timed_out_message(timeout));
             },
             // On error
             |error| {
                 log::error!("Hotkey recording error: {}", error);
-                show_notification("Helix Anywhere", &format!("Error: {}", error));
+                notify(NotificationEvent::RecordDone, &format!("Error: {}", error));
+            },
+            // On a reserved combo, which is passed through
+            |reason| {
+                notify(NotificationEvent::RecordStarted, &format!("{}, press another combination", reason));
+            },
+        );
+    }
+
+    // Add the recordClipboardHotkey: method
+    extern "C" fn record_clipboard_hotkey(_this: &Object, _cmd: Sel, _sender: id) {
+        log::info!("Starting clipboard hotkey recording...");
+        notify(NotificationEvent::RecordStarted, "Press your new clipboard hotkey combination...");
+
+        // Neither the app's own shortcuts nor the selection hotkey can be reused
+        let mut app_shortcuts = unsafe {
+            match STATUS_MENU {
+                Some(menu) => menu_shortcuts(menu),
+                None => Vec::new(),
+            }
+        };
+        if let Some(hotkey) = current_hotkey() {
+            app_shortcuts.push(("Edit Selection".to_string(), hotkey));
+        }
+
+        let timeout = recording_timeout();
+        hotkey_recorder::record_next_hotkey(
+            app_shortcuts,
+            timeout,
+            // On recorded
+            |mut new_hotkey| {
+                log::info!("Recorded new clipboard hotkey: {:?}", new_hotkey);
+                let current = current_clipboard_hotkey();
+                if let Some(ref current) = current {
+                    new_hotkey.enabled = current.enabled;
+                }
+                if current.is_some_and(|current| current == new_hotkey) {
+                    notify(NotificationEvent::RecordDone, "Clipboard hotkey unchanged");
+                    return;
+                }
+                apply_clipboard_hotkey(Some(new_hotkey.clone()));
+
+                let display = format_hotkey_display(&new_hotkey);
+                notify(NotificationEvent::RecordDone, &format!("Clipboard hotkey set to {}", display));
+            },
+            // On timeout
+            move || {
+                log::info!("Clipboard hotkey recording timed out");
+                notify(NotificationEvent::RecordDone, &recording_timed_out_message(timeout));
+            },
+            // On error
+            |error| {
+                log::error!("Clipboard hotkey recording error: {}", error);
+                notify(NotificationEvent::RecordDone, &format!("Error: {}", error));
+            },
+            // On a reserved combo, which is passed through
+            |reason| {
+                notify(NotificationEvent::RecordStarted, &format!("{}, press another combination", reason));
             },
         );
     }
 
+    // Add the removeClipboardHotkey: method
+    extern "C" fn remove_clipboard_hotkey(_this: &Object, _cmd: Sel, _sender: id) {
+        log::info!("Removing clipboard hotkey");
+        apply_clipboard_hotkey(None);
+        notify(NotificationEvent::RecordDone, "Clipboard hotkey removed");
+    }
+
+    // Add the toggleHotkeyEnabled: method
+    extern "C" fn toggle_hotkey_enabled(_this: &Object, _cmd: Sel, sender: id) {
+        let tag: i64 = unsafe { msg_send![sender, tag] };
+        let action = binding_for_tag(tag);
+        let Some(enabled) = binding_enabled(action) else {
+            return;
+        };
+
+        log::info!("Turning {:?} hotkey {}", action, if enabled { "off" } else { "on" });
+        set_binding_enabled(action, !enabled);
+    }
+
+    // Add the selectHotkeyPreset: method
+    extern "C" fn select_hotkey_preset(_this: &Object, _cmd: Sel, sender: id) {
+        let index: i64 = unsafe { msg_send![sender, tag] };
+        let Some(preset) = hotkey_presets().into_iter().nth(index as usize) else {
+            return;
+        };
+
+        log::info!("Selected hotkey preset: {:?}", preset);
+        apply_hotkey(preset.clone());
+
+        let display = format_hotkey_display(&preset);
+        notify(NotificationEvent::RecordDone, &format!("Hotkey set to {}", display));
+    }
+
     // Add the resetHotkey: method
     extern "C" fn reset_hotkey(_this: &Object, _cmd: Sel, _sender: id) {
         log::info!("Resetting hotkey to default");
 
-        let default_hotkey = HotkeyConfig {
-            modifiers: vec!["cmd".to_string(), "shift".to_string()],
-            key: "semicolon".to_string(),
+        let default_hotkey = Config::default().hotkey;
+        apply_hotkey(default_hotkey.clone());
+
+        let display = format_hotkey_display(&default_hotkey);
+        notify(NotificationEvent::RecordDone, &format!("Hotkey reset to {}", display));
+    }
+
+    // Add the systemWillSleep:/systemDidWake: methods (NSWorkspace notifications)
+    extern "C" fn system_will_sleep(_this: &Object, _cmd: Sel, _notification: id) {
+        log::info!("System going to sleep");
+    }
+
+    extern "C" fn system_did_wake(_this: &Object, _cmd: Sel, _notification: id) {
+        // The event tap can silently stop delivering events across sleep,
+        // so recreate it with the current hotkeys
+        let config = unsafe {
+            match GLOBAL_CONFIG {
+                Some(ref config) => config.lock().unwrap().clone(),
+                None => return,
+            }
+        };
+        log::info!("System woke up, restarting hotkey listener");
+        restart_hotkey_listener(&config);
+    }
+
+    // Add the revealTempFile: method
+    extern "C" fn reveal_temp_file(_this: &Object, _cmd: Sel, _sender: id) {
+        match edit_session::active_temp_file() {
+            Some(path) => {
+                log::info!("Revealing temp file {:?}", path);
+                let _ = std::process::Command::new("open").arg("-R").arg(&path).spawn();
+            }
+            None => log::info!("No edit in progress, nothing to reveal"),
+        }
+    }
+
+    // Enable menu items based on the app state (called by NSMenu before showing)
+    extern "C" fn validate_menu_item(_this: &Object, _cmd: Sel, item: id) -> BOOL {
+        let action: Sel = unsafe { msg_send![item, action] };
+        if action == sel!(revealTempFile:) {
+            return if edit_session::active_temp_file().is_some() { YES } else { NO };
+        }
+        if action == sel!(reopenLastEdit:) {
+            return if LastEdit::exists() { YES } else { NO };
+        }
+        if action == sel!(removeClipboardHotkey:) {
+            return if current_clipboard_hotkey().is_some() { YES } else { NO };
+        }
+        if action == sel!(toggleLaunchAtLogin:) {
+            const NS_ON_STATE: i64 = 1;
+            const NS_OFF_STATE: i64 = 0;
+
+            let state = if login_item::is_enabled() { NS_ON_STATE } else { NS_OFF_STATE };
+            let _: () = unsafe { msg_send![item, setState: state] };
+            return YES;
+        }
+        if action == sel!(toggleHotkeyEnabled:) {
+            const NS_ON_STATE: i64 = 1;
+            const NS_OFF_STATE: i64 = 0;
+
+            let tag: i64 = unsafe { msg_send![item, tag] };
+            let enabled = binding_enabled(binding_for_tag(tag));
+            let state = if enabled == Some(true) { NS_ON_STATE } else { NS_OFF_STATE };
+            let _: () = unsafe { msg_send![item, setState: state] };
+            return if enabled.is_some() { YES } else { NO };
+        }
+        YES
+    }
+
+    // Add the reopenLastEdit: method
+    extern "C" fn reopen_last_edit(_this: &Object, _cmd: Sel, _sender: id) {
+        let config = unsafe {
+            match GLOBAL_CONFIG {
+                Some(ref config) => config.lock().unwrap().clone(),
+                None => return,
+            }
+        };
+
+        // Editing blocks until the editor closes, keep the menu responsive
+        std::thread::spawn(move || {
+            if let Err(e) = last_edit::reopen(&config, &SystemClipboard) {
+                log::error!("Failed to reopen last edit: {}", e);
+                notify(NotificationEvent::EditError, &format!("Edit failed: {}", e));
+            }
+        });
+    }
+
+    // Add the cleanTempFiles: method
+    extern "C" fn clean_temp_files(_this: &Object, _cmd: Sel, _sender: id) {
+        let leftovers = temp_files::find_leftovers();
+        if leftovers.paths.is_empty() {
+            unsafe { show_alert("No temp files to clean", "No files left over from earlier edits were found.") };
+            return;
+        }
+
+        // List the files so it's clear nothing else gets deleted
+        const MAX_LISTED: usize = 10;
+        let mut listing: Vec<String> = leftovers
+            .paths
+            .iter()
+            .take(MAX_LISTED)
+            .filter_map(|path| path.file_name())
+            .map(|name| name.to_string_lossy().to_string())
+            .collect();
+        if leftovers.paths.len() > MAX_LISTED {
+            listing.push(format!("and {} more", leftovers.paths.len() - MAX_LISTED));
+        }
+        let message = format!(
+            "{} files ({}) left over from earlier edits will be deleted:\n\n{}",
+            leftovers.paths.len(),
+            temp_files::format_size(leftovers.size),
+            listing.join("\n")
+        );
+
+        let confirmed = unsafe { confirm("Clean temp files?", &message, "Delete") };
+        if !confirmed {
+            return;
+        }
+
+        let (removed, freed) = temp_files::remove(&leftovers);
+        let summary = format!("Removed {} files, freeing {}.", removed, temp_files::format_size(freed));
+        unsafe { show_alert("Temp files cleaned", &summary) };
+    }
+
+    // Add the showStatistics: method
+    extern "C" fn show_statistics(_this: &Object, _cmd: Sel, _sender: id) {
+        let enabled = unsafe {
+            match GLOBAL_CONFIG {
+                Some(ref config) => config.lock().unwrap().session.record_stats,
+                None => false,
+            }
+        };
+
+        let text = match Stats::load() {
+            Ok(stats) if enabled => stats.summary(),
+            Ok(stats) => format!(
+                "{}\n\nRecording is off. Turn on Behavior > Record Statistics in the menu to record new edits.",
+                stats.summary()
+            ),
+            Err(e) => format!("Failed to load statistics: {}", e),
         };
+        unsafe { show_alert("Statistics", &text) };
+    }
+
+    // Add the toggleLaunchAtLogin: method
+    extern "C" fn toggle_launch_at_login(_this: &Object, _cmd: Sel, _sender: id) {
+        let enabled = !login_item::is_enabled();
+        log::info!("Turning launch at login {}", if enabled { "on" } else { "off" });
+        if let Err(e) = login_item::set_enabled(enabled) {
+            log::error!("Failed to change launch at login: {:#}", e);
+            notify(NotificationEvent::EditError, &format!("Couldn't change Launch at Login: {:#}", e));
+        }
+    }
+
+    // Add the quitApp: method
+    extern "C" fn quit_app(_this: &Object, _cmd: Sel, sender: id) {
+        log::info!("Quitting");
+        shut_down();
+        unsafe {
+            let _: () = msg_send![NSApp(), terminate: sender];
+        }
+    }
+
+    // Add the resetAllSettings: method
+    extern "C" fn reset_all_settings(_this: &Object, _cmd: Sel, _sender: id) {
+        let confirmed = unsafe {
+            confirm(
+                "Reset all settings?",
+                "The terminal, hotkey and all other options will be restored to their defaults.",
+                "Reset",
+            )
+        };
+        if !confirmed {
+            return;
+        }
+
+        log::info!("Resetting all settings to defaults");
+        let defaults = Config::default();
 
         unsafe {
             // Update config
             if let Some(ref config) = GLOBAL_CONFIG {
                 let mut cfg = config.lock().unwrap();
-                cfg.hotkey = default_hotkey.clone();
+                *cfg = defaults.clone();
 
                 // Save config
                 if let Some(ref save_fn) = SAVE_CONFIG_CALLBACK {
@@ -366,35 +1049,322 @@ fn register_menu_delegate_class() {
             }
 
             // Update listener
-            if let Some(ref controller) = HOTKEY_CONTROLLER {
-                controller.update_hotkey(default_hotkey.clone());
-            }
+            restart_hotkey_listener(&defaults);
 
             // Update menu
-            update_hotkey_display(&default_hotkey);
+            refresh_menu(&defaults);
         }
 
-        let display = format_hotkey_display(&default_hotkey);
-        show_notification("Helix Anywhere", &format!("Hotkey reset to {}", display));
-    }
-
-    unsafe {
-        decl.add_method(
-            sel!(selectTerminal:),
-            select_terminal as extern "C" fn(&Object, Sel, id),
-        );
-        decl.add_method(
-            sel!(recordHotkey:),
-            record_hotkey as extern "C" fn(&Object, Sel, id),
-        );
-        decl.add_method(
-            sel!(resetHotkey:),
-            reset_hotkey as extern "C" fn(&Object, Sel, id),
-        );
+        notify(NotificationEvent::SettingsReset, "All settings reset to defaults");
     }
 
-    decl.register();
-}
+    // Add the userNotificationCenter:didActivateNotification: method (NSUserNotificationCenterDelegate)
+    extern "C" fn notification_activated(_this: &Object, _cmd: Sel, _center: id, notification: id) {
+        let activation_type: i64 = unsafe { msg_send![notification, activationType] };
+        if activation_type != NOTIFICATION_ACTION_BUTTON_CLICKED {
+            return;
+        }
+        let Some(action) = PENDING_RECOVERY.lock().unwrap().take() else {
+            return;
+        };
+        let config = unsafe {
+            match GLOBAL_CONFIG {
+                Some(ref config) => config.lock().unwrap().clone(),
+                None => return,
+            }
+        };
+
+        // Clicking the notification may have brought this app to the front;
+        // give focus back so the selection and paste target are the user's app
+        unsafe {
+            let _: () = msg_send![NSApp(), hide: nil];
+        }
+
+        // Both block until the editor closes or the paste is done
+        std::thread::spawn(move || match action {
+            RecoveryAction::RetryPaste { app, text } => {
+                log::info!("Retrying paste from the notification");
+                if let Err(e) = edit_session::retry_paste(&config, app.as_ref(), &text) {
+                    log::error!("Retrying paste failed: {}", e);
+                    notify(NotificationEvent::EditError, &format!("Paste failed: {}", e));
+                }
+            }
+            RecoveryAction::RetryEdit(action) => {
+                log::info!("Retrying {:?} from the notification", action);
+                let _ = crate::run_action(&config, action, &[]);
+            }
+        });
+    }
+
+    // Add the userNotificationCenter:shouldPresentNotification: method, so
+    // notifications show even though a menu bar app always counts as active
+    extern "C" fn should_present_notification(_this: &Object, _cmd: Sel, _center: id, _notification: id) -> BOOL {
+        YES
+    }
+
+    // Add the menuNeedsUpdate: method (NSMenuDelegate of the Terminal submenu)
+    extern "C" fn menu_needs_update(_this: &Object, _cmd: Sel, menu: id) {
+        unsafe {
+            let Some(submenu) = TERMINAL_SUBMENU else {
+                return;
+            };
+            if menu != submenu {
+                return;
+            }
+            let current_terminal = match GLOBAL_CONFIG {
+                Some(ref config) => config.lock().unwrap().terminal.name.clone(),
+                None => return,
+            };
+            populate_terminal_submenu(submenu, &current_terminal);
+        }
+    }
+
+    // Add the openTerminalInstallPage: method
+    extern "C" fn open_terminal_install_page(_this: &Object, _cmd: Sel, sender: id) {
+        let name = unsafe {
+            let represented_object: id = msg_send![sender, representedObject];
+            if represented_object == nil {
+                return;
+            }
+            ns_string_to_string(represented_object)
+        };
+        let Some(url) = Terminal::from_name(&name).and_then(|t| t.install_url()) else {
+            return;
+        };
+        log::info!("Opening install page for {}: {}", name, url);
+        let _ = std::process::Command::new("open").arg(url).spawn();
+    }
+
+    // Add the openAbout: method (shows version and diagnostics for support requests)
+    extern "C" fn open_about(_this: &Object, _cmd: Sel, _sender: id) {
+        let config = unsafe {
+            match GLOBAL_CONFIG {
+                Some(ref config) => config.lock().unwrap().clone(),
+                None => return,
+            }
+        };
+        let report = diagnostics(&config);
+
+        let title = format!("helix-anywhere v{}", env!("CARGO_PKG_VERSION"));
+        let buttons = ["OK", "Copy Diagnostics", "Open Website"];
+        match unsafe { alert_with_buttons(&title, &report, &buttons) } {
+            1 => {
+                if let Err(e) = SystemClipboard.set_text(&report) {
+                    log::error!("Failed to copy diagnostics: {}", e);
+                }
+            }
+            2 => {
+                log::info!("Opening {}", config.menu.about_url);
+                let _ = std::process::Command::new("open").arg(&config.menu.about_url).spawn();
+            }
+            _ => {}
+        }
+    }
+
+    unsafe {
+        decl.add_method(
+            sel!(selectHotkeyPreset:),
+            select_hotkey_preset as extern "C" fn(&Object, Sel, id),
+        );
+        decl.add_method(
+            sel!(systemWillSleep:),
+            system_will_sleep as extern "C" fn(&Object, Sel, id),
+        );
+        decl.add_method(
+            sel!(systemDidWake:),
+            system_did_wake as extern "C" fn(&Object, Sel, id),
+        );
+        decl.add_method(
+            sel!(revealTempFile:),
+            reveal_temp_file as extern "C" fn(&Object, Sel, id),
+        );
+        decl.add_method(
+            sel!(validateMenuItem:),
+            validate_menu_item as extern "C" fn(&Object, Sel, id) -> BOOL,
+        );
+        decl.add_method(
+            sel!(reopenLastEdit:),
+            reopen_last_edit as extern "C" fn(&Object, Sel, id),
+        );
+        decl.add_method(
+            sel!(cleanTempFiles:),
+            clean_temp_files as extern "C" fn(&Object, Sel, id),
+        );
+        decl.add_method(
+            sel!(showStatistics:),
+            show_statistics as extern "C" fn(&Object, Sel, id),
+        );
+        decl.add_method(
+            sel!(resetAllSettings:),
+            reset_all_settings as extern "C" fn(&Object, Sel, id),
+        );
+        decl.add_method(
+            sel!(openAbout:),
+            open_about as extern "C" fn(&Object, Sel, id),
+        );
+        decl.add_method(
+            sel!(userNotificationCenter:didActivateNotification:),
+            notification_activated as extern "C" fn(&Object, Sel, id, id),
+        );
+        decl.add_method(
+            sel!(userNotificationCenter:shouldPresentNotification:),
+            should_present_notification as extern "C" fn(&Object, Sel, id, id) -> BOOL,
+        );
+        decl.add_method(
+            sel!(menuNeedsUpdate:),
+            menu_needs_update as extern "C" fn(&Object, Sel, id),
+        );
+        decl.add_method(
+            sel!(openTerminalInstallPage:),
+            open_terminal_install_page as extern "C" fn(&Object, Sel, id),
+        );
+        decl.add_method(
+            sel!(selectTerminal:),
+            select_terminal as extern "C" fn(&Object, Sel, id),
+        );
+        decl.add_method(
+            sel!(toggleBehavior:),
+            toggle_behavior as extern "C" fn(&Object, Sel, id),
+        );
+        decl.add_method(
+            sel!(selectWindowSize:),
+            select_window_size as extern "C" fn(&Object, Sel, id),
+        );
+        decl.add_method(
+            sel!(recordHotkey:),
+            record_hotkey as extern "C" fn(&Object, Sel, id),
+        );
+        decl.add_method(
+            sel!(recordClipboardHotkey:),
+            record_clipboard_hotkey as extern "C" fn(&Object, Sel, id),
+        );
+        decl.add_method(
+            sel!(removeClipboardHotkey:),
+            remove_clipboard_hotkey as extern "C" fn(&Object, Sel, id),
+        );
+        decl.add_method(
+            sel!(toggleHotkeyEnabled:),
+            toggle_hotkey_enabled as extern "C" fn(&Object, Sel, id),
+        );
+        decl.add_method(
+            sel!(toggleLaunchAtLogin:),
+            toggle_launch_at_login as extern "C" fn(&Object, Sel, id),
+        );
+        decl.add_method(
+            sel!(quitApp:),
+            quit_app as extern "C" fn(&Object, Sel, id),
+        );
+        decl.add_method(
+            sel!(resetHotkey:),
+            reset_hotkey as extern "C" fn(&Object, Sel, id),
+        );
+    }
+
+    decl.register();
+}
+
+/// Collect the key equivalents of a menu and its submenus as (item title, hotkey)
+unsafe fn menu_shortcuts(menu: id) -> Vec<(String, HotkeyConfig)> {
+    let mut shortcuts = Vec::new();
+    let count: i64 = msg_send![menu, numberOfItems];
+    for i in 0..count {
+        let item: id = msg_send![menu, itemAtIndex: i];
+        if item == nil {
+            continue;
+        }
+
+        let submenu: id = msg_send![item, submenu];
+        if submenu != nil {
+            shortcuts.extend(menu_shortcuts(submenu));
+        }
+
+        let key: id = msg_send![item, keyEquivalent];
+        let key = ns_string_to_string(key);
+        if key.is_empty() {
+            continue;
+        }
+
+        // An uppercase key equivalent implies Shift; the modifier mask uses
+        // the same bits as the event flags
+        let mask: u64 = msg_send![item, keyEquivalentModifierMask];
+        let mut modifiers = mask & get_modifier_mask();
+        if key.chars().any(char::is_uppercase) {
+            modifiers |= modifier_flags("shift").unwrap_or(0);
+        }
+
+        let title: id = msg_send![item, title];
+        shortcuts.push((
+            ns_string_to_string(title),
+            HotkeyConfig {
+                modifiers: modifiers_to_config(modifiers),
+                key: key.to_lowercase(),
+                ..HotkeyConfig::default()
+            },
+        ));
+    }
+    shortcuts
+}
+
+/// Create an NSString for menu items, alerts and notifications
+///
+/// Control characters other than newlines and tabs (e.g. from a config value
+/// or a file name) are replaced with U+FFFD so they can't garble the menu,
+/// and the result is never nil.
+pub unsafe fn ns_string(s: &str) -> id {
+    let is_unsafe = |c: char| c.is_control() && c != '\n' && c != '\t';
+    let sanitized: Cow<str> = if s.chars().any(is_unsafe) {
+        Cow::Owned(s.chars().map(|c| if is_unsafe(c) { '\u{FFFD}' } else { c }).collect())
+    } else {
+        Cow::Borrowed(s)
+    };
+
+    let string = NSString::alloc(nil).init_str(&sanitized);
+    if string == nil {
+        log::warn!("Failed to create a string for {:?}", sanitized);
+        return msg_send![class!(NSString), string];
+    }
+    string
+}
+
+/// Copy an NSString into a Rust String (empty for nil)
+unsafe fn ns_string_to_string(string: id) -> String {
+    if string == nil {
+        return String::new();
+    }
+    let chars: *const i8 = msg_send![string, UTF8String];
+    if chars.is_null() {
+        return String::new();
+    }
+    std::ffi::CStr::from_ptr(chars).to_string_lossy().to_string()
+}
+
+/// Handle clicks on notification buttons (see `notify_with_recovery`)
+unsafe fn register_notification_delegate() {
+    let center = notify::user_notification_center();
+    if center == nil {
+        return;
+    }
+    let delegate: id = msg_send![Class::get("MenuDelegate").unwrap(), new];
+    let _: () = msg_send![center, setDelegate: delegate];
+}
+
+/// Observe NSWorkspace sleep/wake notifications
+unsafe fn register_wake_observer() {
+    let observer: id = msg_send![Class::get("MenuDelegate").unwrap(), new];
+    let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+    let center: id = msg_send![workspace, notificationCenter];
+
+    let _: () = msg_send![center,
+        addObserver: observer
+        selector: sel!(systemWillSleep:)
+        name: ns_string("NSWorkspaceWillSleepNotification")
+        object: nil];
+    let _: () = msg_send![center,
+        addObserver: observer
+        selector: sel!(systemDidWake:)
+        name: ns_string("NSWorkspaceDidWakeNotification")
+        object: nil];
+}
 
 /// Run the application event loop
 pub fn run_app() {
@@ -404,6 +1374,89 @@ pub fn run_app() {
     }
 }
 
+/// Run `f` on the main thread and wait for its result
+///
+/// AppKit (the menu items in particular) may only be used from the main
+/// thread, so other threads changing settings go through this.
+pub fn on_main_thread<T: Send, F: FnOnce() -> T + Send>(f: F) -> T {
+    struct Task<T, F> {
+        f: Option<F>,
+        result: Option<T>,
+    }
+
+    extern "C" fn run<T, F: FnOnce() -> T>(context: *mut c_void) {
+        let task = unsafe { &mut *(context as *mut Task<T, F>) };
+        task.result = task.f.take().map(|f| f());
+    }
+
+    let is_main: BOOL = unsafe { msg_send![class!(NSThread), isMainThread] };
+    if is_main == YES {
+        return f();
+    }
+    let mut task = Task { f: Some(f), result: None };
+    unsafe {
+        dispatch_sync_f(
+            &_dispatch_main_q,
+            &mut task as *mut Task<T, F> as *mut c_void,
+            run::<T, F>,
+        );
+    }
+    task.result.expect("main thread task didn't run")
+}
+
+/// Fill the Terminal submenu with one item per terminal, checking the current one
+///
+/// Installed state is checked again on every call.
+unsafe fn populate_terminal_submenu(submenu: id, current_terminal: &str) {
+    let _: () = msg_send![submenu, removeAllItems];
+
+    // NSOnState = 1, NSOffState = 0
+    const NS_ON_STATE: i64 = 1;
+    const NS_OFF_STATE: i64 = 0;
+
+    for terminal in Terminal::all() {
+        let is_installed = terminal.is_installed();
+        let is_current = terminal.config_name() == current_terminal;
+
+        let item = if is_installed {
+            let item_title = ns_string(terminal.display_name());
+            let selector = sel!(selectTerminal:);
+            let item = NSMenuItem::alloc(nil)
+                .initWithTitle_action_keyEquivalent_(item_title, selector, ns_string(""))
+                .autorelease();
+
+            // Set checkmark state
+            let state = if is_current { NS_ON_STATE } else { NS_OFF_STATE };
+            let _: () = msg_send![item, setState: state];
+
+            item
+        } else {
+            // Clicking a missing terminal opens its download page
+            let missing_name = format!("{} (not installed)", terminal.display_name());
+            let missing_title = ns_string(&missing_name);
+            let item = NSMenuItem::alloc(nil)
+                .initWithTitle_action_keyEquivalent_(missing_title, sel!(openTerminalInstallPage:), ns_string(""))
+                .autorelease();
+            if let Some(cask) = terminal.brew_cask() {
+                let tooltip = format!("Open the download page, or run: brew install --cask {}", cask);
+                let _: () = msg_send![item, setToolTip: ns_string(&tooltip)];
+            }
+            item
+        };
+
+        // Store terminal name as represented object
+        let terminal_name_str = ns_string(terminal.config_name());
+        let _: () = msg_send![item, setRepresentedObject: terminal_name_str];
+
+        // Set target to our delegate
+        let delegate_class = Class::get("MenuDelegate").unwrap();
+        let delegate: id = msg_send![delegate_class, new];
+        let _: () = msg_send![item, setTarget: delegate];
+
+        submenu.addItem_(item);
+    }
+}
+
 /// Update checkmarks in the terminal submenu
 unsafe fn update_terminal_checkmarks(selected_name: &str) {
     const NS_ON_STATE: i64 = 1;
@@ -442,6 +1495,64 @@ unsafe fn update_terminal_checkmarks(selected_name: &str) {
     }
 }
 
+/// Update the current size and the checkmark in the window size submenu
+unsafe fn update_window_size_checkmarks(width: u32, height: u32) {
+    const NS_ON_STATE: i64 = 1;
+    const NS_OFF_STATE: i64 = 0;
+
+    if let Some(submenu) = WINDOW_SIZE_SUBMENU {
+        // The first item (index 0) is the "Current: ..." display item
+        let current_item: id = msg_send![submenu, itemAtIndex: 0_i64];
+        if current_item != nil {
+            let title = ns_string(&format!("Current: {} × {}", width, height));
+            let _: () = msg_send![current_item, setTitle: title];
+        }
+
+        let count: i64 = msg_send![submenu, numberOfItems];
+        for i in 0..count {
+            let item: id = msg_send![submenu, itemAtIndex: i];
+            if item == nil {
+                continue;
+            }
+            let action: Sel = msg_send![item, action];
+            if action != sel!(selectWindowSize:) {
+                continue;
+            }
+
+            let index: i64 = msg_send![item, tag];
+            let selected = WINDOW_SIZE_PRESETS
+                .get(index as usize)
+                .is_some_and(|&(_, w, h)| w == width && h == height);
+            let state = if selected { NS_ON_STATE } else { NS_OFF_STATE };
+            let _: () = msg_send![item, setState: state];
+        }
+    }
+}
+
+/// Update the checkmarks in the behavior submenu
+unsafe fn update_behavior_checkmarks(config: &Config) {
+    const NS_ON_STATE: i64 = 1;
+    const NS_OFF_STATE: i64 = 0;
+
+    if let Some(submenu) = BEHAVIOR_SUBMENU {
+        // The toggles take a `&mut Config`, so read them from a copy
+        let mut config = config.clone();
+        let count: i64 = msg_send![submenu, numberOfItems];
+        for i in 0..count {
+            let item: id = msg_send![submenu, itemAtIndex: i];
+            if item == nil {
+                continue;
+            }
+            let index: i64 = msg_send![item, tag];
+            let Some(&(_, field)) = BEHAVIOR_TOGGLES.get(index as usize) else {
+                continue;
+            };
+            let state = if *field(&mut config) { NS_ON_STATE } else { NS_OFF_STATE };
+            let _: () = msg_send![item, setState: state];
+        }
+    }
+}
+
 /// Update the hotkey display in the submenu
 unsafe fn update_hotkey_display(hotkey: &HotkeyConfig) {
     if let Some(submenu) = HOTKEY_SUBMENU {
@@ -449,24 +1560,403 @@ unsafe fn update_hotkey_display(hotkey: &HotkeyConfig) {
         let item: id = msg_send![submenu, itemAtIndex: 0_i64];
         if item != nil {
             let display = format_hotkey_display(hotkey);
-            let title = NSString::alloc(nil).init_str(&format!("Current: {}", display));
+            let title = ns_string(&format!("Current: {}", display));
             let _: () = msg_send![item, setTitle: title];
         }
     }
+    update_preset_checkmarks(hotkey);
+}
+
+/// Title of the clipboard hotkey display item
+fn clipboard_hotkey_title(hotkey: Option<&HotkeyConfig>) -> String {
+    match hotkey {
+        Some(hotkey) => format!("Clipboard: {}", format_hotkey_display(hotkey)),
+        None => "Clipboard: Not set".to_string(),
+    }
+}
+
+/// Update the clipboard hotkey display in the submenu
+unsafe fn update_clipboard_hotkey_display(hotkey: Option<&HotkeyConfig>) {
+    if let Some(item) = CLIPBOARD_HOTKEY_ITEM {
+        let title = ns_string(&clipboard_hotkey_title(hotkey));
+        let _: () = msg_send![item, setTitle: title];
+    }
+}
+
+/// Update the checkmark in the hotkey presets submenu
+unsafe fn update_preset_checkmarks(hotkey: &HotkeyConfig) {
+    const NS_ON_STATE: i64 = 1;
+    const NS_OFF_STATE: i64 = 0;
+
+    if let Some(submenu) = PRESETS_SUBMENU {
+        let presets = hotkey_presets();
+        let count: i64 = msg_send![submenu, numberOfItems];
+        for i in 0..count {
+            let item: id = msg_send![submenu, itemAtIndex: i];
+            if item == nil {
+                continue;
+            }
+
+            let index: i64 = msg_send![item, tag];
+            let selected = presets
+                .get(index as usize)
+                .is_some_and(|preset| preset == hotkey);
+            let state = if selected { NS_ON_STATE } else { NS_OFF_STATE };
+            let _: () = msg_send![item, setState: state];
+        }
+    }
+}
+
+/// Ask the user to confirm an action with a modal alert
+///
+/// Returns true if the confirm button was clicked.
+unsafe fn confirm(message: &str, informative: &str, confirm_title: &str) -> bool {
+    // NSAlertFirstButtonReturn
+    const FIRST_BUTTON_RETURN: i64 = 1000;
+
+    let alert: id = msg_send![class!(NSAlert), new];
+    let _: () = msg_send![alert, setMessageText: ns_string(message)];
+    let _: () = msg_send![alert, setInformativeText: ns_string(informative)];
+    let _: id = msg_send![alert, addButtonWithTitle: ns_string(confirm_title)];
+    let _: id = msg_send![alert, addButtonWithTitle: ns_string("Cancel")];
+
+    // Menu bar apps aren't active, bring the alert to the front
+    let app = NSApp();
+    let _: () = msg_send![app, activateIgnoringOtherApps: YES];
+
+    let response: i64 = msg_send![alert, runModal];
+    let _: () = msg_send![alert, release];
+    response == FIRST_BUTTON_RETURN
+}
+
+/// Show an alert with the given buttons, returning the index of the one clicked
+unsafe fn alert_with_buttons(message: &str, informative: &str, buttons: &[&str]) -> usize {
+    // NSAlertFirstButtonReturn
+    const FIRST_BUTTON_RETURN: i64 = 1000;
+
+    let alert: id = msg_send![class!(NSAlert), new];
+    let _: () = msg_send![alert, setMessageText: ns_string(message)];
+    let _: () = msg_send![alert, setInformativeText: ns_string(informative)];
+    for button in buttons {
+        let _: id = msg_send![alert, addButtonWithTitle: ns_string(button)];
+    }
+
+    // Menu bar apps aren't active, bring the alert to the front
+    let app = NSApp();
+    let _: () = msg_send![app, activateIgnoringOtherApps: YES];
+
+    let response: i64 = msg_send![alert, runModal];
+    let _: () = msg_send![alert, release];
+    (response - FIRST_BUTTON_RETURN).max(0) as usize
+}
+
+/// Version, editor, terminal and permission state, for the About panel and bug reports
+fn diagnostics(config: &Config) -> String {
+    let macos_version = std::process::Command::new("sw_vers")
+        .arg("-productVersion")
+        .output()
+        .ok()
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|version| !version.is_empty())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    let editor = match terminal::resolve_editor(&config.editor.editors) {
+        Ok(path) => path.display().to_string(),
+        Err(e) => format!("not found ({})", e),
+    };
+
+    let terminal = match Terminal::from_name(&config.terminal.name) {
+        Some(terminal) if terminal.is_installed() => format!("{} (installed)", terminal.display_name()),
+        Some(terminal) => format!("{} (not installed)", terminal.display_name()),
+        None => format!("{} (unknown)", config.terminal.name),
+    };
+
+    let accessibility = if keystroke::is_accessibility_trusted() {
+        "granted"
+    } else {
+        "not granted"
+    };
+
+    let config_path = Config::config_path()
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    [
+        format!("Version: {}", env!("CARGO_PKG_VERSION")),
+        format!("macOS: {}", macos_version),
+        format!("Editor: {}", editor),
+        format!("Terminal: {}", terminal),
+        format!("Hotkey: {}", format_hotkey_display(&config.hotkey)),
+        format!("Accessibility: {}", accessibility),
+        format!("Config: {}", config_path),
+    ]
+    .join("\n")
+}
+
+/// Show an informational alert with an OK button
+unsafe fn show_alert(message: &str, informative: &str) {
+    let alert: id = msg_send![class!(NSAlert), new];
+    let _: () = msg_send![alert, setMessageText: ns_string(message)];
+    let _: () = msg_send![alert, setInformativeText: ns_string(informative)];
+
+    // Menu bar apps aren't active, bring the alert to the front
+    let app = NSApp();
+    let _: () = msg_send![app, activateIgnoringOtherApps: YES];
+
+    let _: i64 = msg_send![alert, runModal];
+    let _: () = msg_send![alert, release];
+}
+
+/// Show a notification if the config enables it for this kind of event
+pub fn notify(event: NotificationEvent, message: &str) {
+    let enabled = unsafe {
+        match GLOBAL_CONFIG {
+            Some(ref config) => config.lock().unwrap().notifications.enabled(event),
+            None => NotificationsConfig::default().enabled(event),
+        }
+    };
+    if enabled {
+        notify::notify("Helix Anywhere", message);
+    }
+}
+
+/// Show a failure notification with a button that recovers from it
+///
+/// Only the latest notification's button does anything. Without native
+/// notifications (not running from the app bundle), the message is shown
+/// without a button.
+pub fn notify_with_recovery(event: NotificationEvent, message: &str, action: RecoveryAction) {
+    let enabled = unsafe {
+        match GLOBAL_CONFIG {
+            Some(ref config) => config.lock().unwrap().notifications.enabled(event),
+            None => NotificationsConfig::default().enabled(event),
+        }
+    };
+    if !enabled {
+        return;
+    }
+
+    let button_title = action.button_title();
+    *PENDING_RECOVERY.lock().unwrap() = Some(action);
+    if !notify::post("Helix Anywhere", message, Some(button_title)) {
+        *PENDING_RECOVERY.lock().unwrap() = None;
+        notify::notify("Helix Anywhere", message);
+    }
+}
+
+/// Get the hotkey currently in use
+pub fn current_hotkey() -> Option<HotkeyConfig> {
+    unsafe {
+        if let Some(ref config) = GLOBAL_CONFIG {
+            return Some(config.lock().unwrap().hotkey.clone());
+        }
+    }
+    None
+}
+
+/// How long recording a hotkey waits for a key press
+fn recording_timeout() -> Duration {
+    let secs = unsafe {
+        match GLOBAL_CONFIG {
+            Some(ref config) => config.lock().unwrap().timing.recording_timeout_secs,
+            None => TimingConfig::default().recording_timeout_secs,
+        }
+    };
+    Duration::from_secs(secs)
+}
+
+fn recording_timed_out_message(timeout: Duration) -> String {
+    format!("No key pressed within {} seconds, hotkey unchanged", timeout.as_secs())
+}
+
+/// Get the clipboard hotkey currently in use, if one is set
+fn current_clipboard_hotkey() -> Option<HotkeyConfig> {
+    unsafe {
+        match GLOBAL_CONFIG {
+            Some(ref config) => config.lock().unwrap().clipboard_hotkey.clone(),
+            None => None,
+        }
+    }
+}
+
+/// The hotkey an "Enabled" menu item is for, by its tag
+fn binding_for_tag(tag: i64) -> HotkeyAction {
+    if tag == BINDING_TAG_CLIPBOARD_HOTKEY {
+        HotkeyAction::EditClipboard
+    } else {
+        HotkeyAction::EditSelection
+    }
+}
+
+/// Whether the hotkey for an action is enabled, if there is one
+fn binding_enabled(action: HotkeyAction) -> Option<bool> {
+    unsafe {
+        match GLOBAL_CONFIG {
+            Some(ref config) => config.lock().unwrap().hotkey_for(action).map(|hotkey| hotkey.enabled),
+            None => None,
+        }
+    }
+}
+
+/// Turn the hotkey for an action on or off, keeping it in the config
+///
+/// Saves the config and restarts the listener with the enabled hotkeys.
+pub fn set_binding_enabled(action: HotkeyAction, enabled: bool) {
+    unsafe {
+        if let Some(ref config) = GLOBAL_CONFIG {
+            let mut cfg = config.lock().unwrap();
+            let Some(hotkey) = cfg.hotkey_for_mut(action) else {
+                return;
+            };
+            hotkey.enabled = enabled;
+
+            if let Some(ref save_fn) = SAVE_CONFIG_CALLBACK {
+                save_fn(&cfg);
+            }
+
+            restart_hotkey_listener(&cfg);
+        }
+    }
+}
+
+/// Restart the hotkey listener with all hotkeys from the config
+fn restart_hotkey_listener(config: &Config) {
+    unsafe {
+        if let Some(ref controller) = HOTKEY_CONTROLLER {
+            controller.update_bindings(config.hotkey_bindings());
+        }
+    }
+}
+
+/// Switch to a new hotkey
+///
+/// Updates and saves the config, restarts the listener with the new hotkey
+/// and refreshes the menu.
+pub fn apply_hotkey(new_hotkey: HotkeyConfig) {
+    unsafe {
+        // Update config
+        if let Some(ref config) = GLOBAL_CONFIG {
+            let mut cfg = config.lock().unwrap();
+            cfg.hotkey = new_hotkey.clone();
+
+            // Save config
+            if let Some(ref save_fn) = SAVE_CONFIG_CALLBACK {
+                save_fn(&cfg);
+            }
+
+            // Update hotkey listener
+            restart_hotkey_listener(&cfg);
+        }
+
+        // Update menu display
+        update_hotkey_display(&new_hotkey);
+    }
 }
 
-/// Show a macOS notification using osascript
-fn show_notification(title: &str, message: &str) {
-    use std::process::Command;
-    let script = format!(
-        r#"display notification "{}" with title "{}""#,
-        message.replace('\"', "\\\""),
-        title.replace('\"', "\\\"")
-    );
-    let _ = Command::new("osascript")
-        .arg("-e")
-        .arg(&script)
-        .spawn();
+/// Set or remove the hotkey that edits the clipboard
+///
+/// Updates and saves the config, restarts the listener and refreshes the menu.
+pub fn apply_clipboard_hotkey(new_hotkey: Option<HotkeyConfig>) {
+    unsafe {
+        if let Some(ref config) = GLOBAL_CONFIG {
+            let mut cfg = config.lock().unwrap();
+            cfg.clipboard_hotkey = new_hotkey.clone();
+
+            if let Some(ref save_fn) = SAVE_CONFIG_CALLBACK {
+                save_fn(&cfg);
+            }
+
+            restart_hotkey_listener(&cfg);
+        }
+
+        update_clipboard_hotkey_display(new_hotkey.as_ref());
+    }
+}
+
+/// Load the config file again and apply it, e.g. after editing it by hand
+///
+/// Restarts the listener and refreshes the menu, without saving anything.
+pub fn reload_config() -> Result<()> {
+    let reloaded = Config::reload()?;
+    unsafe {
+        if let Some(ref config) = GLOBAL_CONFIG {
+            *config.lock().unwrap() = reloaded.clone();
+        }
+        restart_hotkey_listener(&reloaded);
+        refresh_menu(&reloaded);
+        keystroke::set_tracing(reloaded.debug.trace_keystrokes);
+    }
+    log::info!("Config reloaded");
+    Ok(())
+}
+
+/// Update every menu item that reflects a setting
+unsafe fn refresh_menu(config: &Config) {
+    update_terminal_checkmarks(&config.terminal.name);
+    update_window_size_checkmarks(config.terminal.width, config.terminal.height);
+    update_hotkey_display(&config.hotkey);
+    update_clipboard_hotkey_display(config.clipboard_hotkey.as_ref());
+    update_behavior_checkmarks(config);
+}
+
+/// Log a warning if the configured terminal opens windows at its own size
+fn warn_if_size_ignored() {
+    unsafe {
+        let Some(ref config) = GLOBAL_CONFIG else {
+            return;
+        };
+        let name = config.lock().unwrap().terminal.name.clone();
+        if let Some(terminal) = Terminal::from_name(&name) {
+            if !terminal.supports_window_size() {
+                log::warn!(
+                    "{} doesn't take a window size, its windows open at its own default size",
+                    terminal.display_name()
+                );
+            }
+        }
+    }
+}
+
+/// Save a new terminal window size (in columns and lines) to the config
+pub fn save_window_size(width: u32, height: u32) {
+    unsafe {
+        if let Some(ref config) = GLOBAL_CONFIG {
+            let mut cfg = config.lock().unwrap();
+            cfg.terminal.width = width;
+            cfg.terminal.height = height;
+
+            if let Some(ref save_fn) = SAVE_CONFIG_CALLBACK {
+                save_fn(&cfg);
+            }
+        }
+
+        update_window_size_checkmarks(width, height);
+    }
+}
+
+/// Stop the hotkey listener and remove the temp file of an unfinished edit
+///
+/// The edit can't be pasted back once the app is gone.
+fn shut_down() {
+    unsafe {
+        if let Some(ref controller) = HOTKEY_CONTROLLER {
+            if !controller.stop(LISTENER_STOP_TIMEOUT) {
+                log::warn!("Hotkey listener didn't stop in time");
+            }
+        }
+    }
+
+    if let Some(path) = edit_session::owned_temp_file().filter(|path| temp_files::is_own(path)) {
+        log::info!("Edit in progress, removing {:?}", path);
+        let removed = if path.is_dir() {
+            std::fs::remove_dir_all(&path)
+        } else {
+            std::fs::remove_file(&path)
+        };
+        if let Err(e) = removed {
+            log::warn!("Failed to remove {:?}: {}", path, e);
+        }
+    }
 }
 
 /// Set the hotkey controller for use by menu actions
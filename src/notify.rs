@@ -0,0 +1,77 @@
+use crate::menu_bar::ns_string;
+use crate::terminal::applescript_escape;
+use cocoa::base::{id, nil, YES};
+use cocoa::foundation::NSAutoreleasePool;
+use objc::{class, msg_send, sel, sel_impl};
+use std::process::Command;
+
+/// Show a macOS notification
+///
+/// Posted through NSUserNotificationCenter so it carries the app's icon,
+/// or through osascript when not running from the app bundle.
+pub fn notify(title: &str, message: &str) {
+    if post(title, message, None) {
+        return;
+    }
+    if let Err(e) = Command::new("osascript").arg("-e").arg(notification_script(title, message)).spawn() {
+        log::warn!("Failed to show notification: {}", e);
+    }
+}
+
+/// Post a notification through NSUserNotificationCenter, with an action
+/// button if a title is given for it
+///
+/// Returns false when there's no notification center to post to.
+pub fn post(title: &str, message: &str, button_title: Option<&str>) -> bool {
+    unsafe {
+        let _pool = NSAutoreleasePool::new(nil);
+        let center = user_notification_center();
+        if center == nil {
+            return false;
+        }
+
+        let notification: id = msg_send![class!(NSUserNotification), new];
+        let _: () = msg_send![notification, setTitle: ns_string(title)];
+        let _: () = msg_send![notification, setInformativeText: ns_string(message)];
+        if let Some(button_title) = button_title {
+            let _: () = msg_send![notification, setHasActionButton: YES];
+            let _: () = msg_send![notification, setActionButtonTitle: ns_string(button_title)];
+        }
+        let _: () = msg_send![center, deliverNotification: notification];
+        let _: () = msg_send![notification, release];
+    }
+    true
+}
+
+/// The NSUserNotificationCenter, nil when not running from an app bundle
+pub unsafe fn user_notification_center() -> id {
+    let bundle: id = msg_send![class!(NSBundle), mainBundle];
+    let identifier: id = msg_send![bundle, bundleIdentifier];
+    if identifier == nil {
+        return nil;
+    }
+    msg_send![class!(NSUserNotificationCenter), defaultUserNotificationCenter]
+}
+
+/// AppleScript showing a notification
+fn notification_script(title: &str, message: &str) -> String {
+    format!(
+        r#"display notification "{}" with title "{}""#,
+        applescript_escape(message),
+        applescript_escape(title)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_and_backslashes_are_escaped() {
+        assert_eq!(
+            notification_script("Helix \"Anywhere\"", r#"Saved C:\temp\"a".txt"#),
+            r#"display notification "Saved C:\\temp\\\"a\".txt" with title "Helix \"Anywhere\"""#
+        );
+        assert_eq!(notification_script("", "\\"), r#"display notification "\\" with title """#);
+    }
+}
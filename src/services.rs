@@ -0,0 +1,124 @@
+//! macOS Services provider
+//!
+//! Adds "Edit in Helix" to the Services menu (and the right-click menu) of
+//! any app. macOS hands us the selected text on a pasteboard and replaces
+//! the selection with whatever we write back, so no simulated copy/paste or
+//! app switching is needed. The entry is declared in `assets/Services.plist`,
+//! which is merged into the bundle's Info.plist.
+
+use crate::clipboard::{self, ClipboardAccess};
+use crate::config::Config;
+use crate::edit_session;
+use cocoa::appkit::NSApp;
+use cocoa::base::{id, nil};
+use cocoa::foundation::NSString;
+use core_foundation::runloop::{kCFRunLoopDefaultMode, CFRunLoop};
+use objc::declare::ClassDecl;
+use objc::runtime::{Class, Object, Sel};
+use objc::{class, msg_send, sel, sel_impl};
+use std::ffi::c_void;
+use std::sync::mpsc::{channel, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// Store config globally for the service handler
+static mut SERVICES_CONFIG: Option<Arc<Mutex<Config>>> = None;
+
+/// Register the app as the provider of its Services
+pub fn register(config: Arc<Mutex<Config>>) {
+    unsafe {
+        SERVICES_CONFIG = Some(config);
+
+        register_services_provider_class();
+        let provider_class = Class::get("ServicesProvider").unwrap();
+        let provider: id = msg_send![provider_class, new];
+        let _: () = msg_send![NSApp(), setServicesProvider: provider];
+    }
+    log::info!("Registered Services provider");
+}
+
+/// Register the Objective-C class handling Services requests
+fn register_services_provider_class() {
+    if Class::get("ServicesProvider").is_some() {
+        return; // Already registered
+    }
+
+    let mut decl = ClassDecl::new("ServicesProvider", class!(NSObject)).unwrap();
+
+    // - (void)editInHelix:(NSPasteboard *)pboard userData:(NSString *)data error:(NSString **)error
+    extern "C" fn edit_in_helix(
+        _this: &Object,
+        _cmd: Sel,
+        pasteboard: id,
+        _user_data: id,
+        error: *mut c_void,
+    ) {
+        if let Err(e) = unsafe { handle_edit(pasteboard) } {
+            log::error!("Edit in Helix service failed: {}", e);
+            if !error.is_null() {
+                let error = error as *mut id;
+                unsafe { *error = NSString::alloc(nil).init_str(&e.to_string()) };
+            }
+        }
+    }
+
+    unsafe {
+        decl.add_method(
+            sel!(editInHelix:userData:error:),
+            edit_in_helix as extern "C" fn(&Object, Sel, id, id, *mut c_void),
+        );
+    }
+
+    decl.register();
+}
+
+/// Edit the text on the service pasteboard and write the result back
+///
+/// The service has to answer before returning, so the edit runs on a
+/// background thread while this keeps the main run loop (and the menu bar)
+/// responsive.
+unsafe fn handle_edit(pasteboard: id) -> anyhow::Result<()> {
+    let service_pasteboard = ServicePasteboard(pasteboard);
+    let text = service_pasteboard.get_text()?;
+
+    let config = match SERVICES_CONFIG {
+        Some(ref config) => config.lock().unwrap().clone(),
+        None => return Ok(()),
+    };
+
+    log::info!("Edit requested via Services ({} characters)", text.len());
+    let (tx, rx) = channel();
+    let original = text.clone();
+    std::thread::spawn(move || {
+        let _ = tx.send(edit_session::edit_text(&config, &original, None));
+    });
+
+    let edited = loop {
+        match rx.try_recv() {
+            Ok(result) => break result?,
+            Err(TryRecvError::Empty) => {
+                CFRunLoop::run_in_mode(kCFRunLoopDefaultMode, Duration::from_millis(100), false);
+            }
+            Err(TryRecvError::Disconnected) => anyhow::bail!("Edit thread stopped unexpectedly"),
+        }
+    };
+
+    // Leaving the pasteboard untouched keeps the selection as it was
+    if let Some(edited) = edited {
+        service_pasteboard.set_text(&edited)?;
+    }
+    Ok(())
+}
+
+/// The pasteboard macOS passes to a service
+struct ServicePasteboard(id);
+
+impl ClipboardAccess for ServicePasteboard {
+    fn get_text(&self) -> anyhow::Result<String> {
+        unsafe { clipboard::pasteboard_text(self.0) }
+    }
+
+    fn set_text(&self, text: &str) -> anyhow::Result<()> {
+        unsafe { clipboard::set_pasteboard_text(self.0, text) }
+    }
+}
@@ -0,0 +1,141 @@
+//! Local usage statistics
+//!
+//! Counts edit session outcomes in a small state file next to the config.
+//! Nothing is ever sent anywhere; the counts are only shown in the menu.
+
+use anyhow::{Context, Result};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Serializes read-modify-write cycles of the stats file
+static STATS_LOCK: Mutex<()> = Mutex::new(());
+
+/// How an edit session ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionOutcome {
+    /// The edited text was pasted back
+    Completed,
+    /// The text was left unchanged, so nothing was pasted
+    Unchanged,
+    /// Nothing was selected when the hotkey was pressed
+    NoSelection,
+    /// The original window wasn't focused again, the text was left on the clipboard
+    TargetLost,
+    /// A secure field (e.g. a password) was focused
+    SecureInput,
+    /// The selection was added to the snippet collection
+    Collected,
+    /// The frontmost app is in `ignored_apps`, nothing was done
+    IgnoredApp,
+}
+
+/// Counters of edit session outcomes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stats {
+    pub edits_completed: u64,
+    pub edits_aborted: u64,
+    pub no_selection: u64,
+    pub paste_target_lost: u64,
+    pub secure_input: u64,
+    pub snippets_collected: u64,
+    pub errors: u64,
+    /// Message of the most recent error
+    pub last_error: Option<String>,
+}
+
+impl Stats {
+    /// Get the stats file path
+    pub fn path() -> Option<PathBuf> {
+        ProjectDirs::from("com", "helix-anywhere", "helix-anywhere")
+            .map(|dirs| dirs.data_dir().join("stats.toml"))
+    }
+
+    /// Load stats from file, or start from zero if there are none yet
+    pub fn load() -> Result<Self> {
+        let path = Self::path().context("Could not determine data directory")?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read stats file: {:?}", path))?;
+        toml::from_str(&content).context("Failed to parse stats file")
+    }
+
+    /// Save stats to file
+    fn save(&self) -> Result<()> {
+        let path = Self::path().context("Could not determine data directory")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create data directory: {:?}", dir))?;
+        }
+
+        let content = toml::to_string_pretty(self).context("Failed to serialize stats")?;
+
+        // Write to a temp file and rename, so a crash can't leave a truncated file
+        let temp_path = path.with_extension("toml.tmp");
+        fs::write(&temp_path, content)
+            .with_context(|| format!("Failed to write stats file: {:?}", temp_path))?;
+        fs::rename(&temp_path, &path)
+            .with_context(|| format!("Failed to replace stats file: {:?}", path))?;
+        Ok(())
+    }
+
+    /// Summary shown in the Statistics window
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "Edits completed: {}\nEdits aborted: {}\nNo selection: {}\nPaste target lost: {}\nSecure field: {}\nSnippets collected: {}\nErrors: {}",
+            self.edits_completed,
+            self.edits_aborted,
+            self.no_selection,
+            self.paste_target_lost,
+            self.secure_input,
+            self.snippets_collected,
+            self.errors
+        );
+        if let Some(ref error) = self.last_error {
+            summary.push_str(&format!("\n\nLast error: {}", error));
+        }
+        summary
+    }
+}
+
+/// Load the stats, apply `update` and save them back
+fn update(update: impl FnOnce(&mut Stats)) {
+    let _guard = STATS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let mut stats = Stats::load().unwrap_or_else(|e| {
+        log::warn!("Failed to load stats, starting over: {}", e);
+        Stats::default()
+    });
+    update(&mut stats);
+    if let Err(e) = stats.save() {
+        log::warn!("Failed to save stats: {}", e);
+    }
+}
+
+/// Count the outcome of an edit session
+pub fn record_outcome(outcome: SessionOutcome) {
+    update(|stats| match outcome {
+        SessionOutcome::Completed => stats.edits_completed += 1,
+        SessionOutcome::Unchanged => stats.edits_aborted += 1,
+        SessionOutcome::NoSelection => stats.no_selection += 1,
+        SessionOutcome::TargetLost => stats.paste_target_lost += 1,
+        SessionOutcome::SecureInput => stats.secure_input += 1,
+        SessionOutcome::Collected => stats.snippets_collected += 1,
+        SessionOutcome::IgnoredApp => {}
+    });
+}
+
+/// Count a failed edit session
+pub fn record_error(error: &anyhow::Error) {
+    let message = error.to_string();
+    update(|stats| {
+        stats.errors += 1;
+        stats.last_error = Some(message);
+    });
+}
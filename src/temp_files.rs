@@ -0,0 +1,126 @@
+//! Temp files left behind by edit sessions
+//!
+//! Everything the app writes to the temp directory (the edited text, snippet
+//! directories, Ghostty launch scripts) starts with [`PREFIX`], so leftovers
+//! from crashed or interrupted sessions can be found and removed without
+//! touching anything else in there.
+
+use crate::collect;
+use crate::edit_session;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File name prefix of every temp file and directory the app creates
+pub use helix_anywhere_core::TEMP_FILE_PREFIX as PREFIX;
+
+/// Temp files found by [`find_leftovers`]
+pub struct Leftovers {
+    pub paths: Vec<PathBuf>,
+    /// Total size in bytes
+    pub size: u64,
+}
+
+/// Find the app's temp files that don't belong to an edit in progress
+pub fn find_leftovers() -> Leftovers {
+    let in_use: Vec<PathBuf> = edit_session::active_temp_file()
+        .into_iter()
+        .chain(collect::active_dir())
+        .map(|path| path.with_extension(""))
+        .collect();
+
+    let mut paths: Vec<PathBuf> = fs::read_dir(std::env::temp_dir())
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.file_name().to_string_lossy().starts_with(PREFIX))
+                .map(|entry| entry.path())
+                // Also keeps the size report/exit marker next to a file in use
+                .filter(|path| !in_use.contains(&path.with_extension("")))
+                .collect()
+        })
+        .unwrap_or_default();
+    paths.sort();
+
+    let size = paths.iter().map(|path| disk_size(path)).sum();
+    Leftovers { paths, size }
+}
+
+/// Delete the given temp files, returning how many were removed and the bytes freed
+pub fn remove(leftovers: &Leftovers) -> (usize, u64) {
+    let mut removed = 0;
+    let mut freed = 0;
+    for path in &leftovers.paths {
+        let size = disk_size(path);
+        let result = if path.is_dir() {
+            fs::remove_dir_all(path)
+        } else {
+            fs::remove_file(path)
+        };
+        match result {
+            Ok(()) => {
+                removed += 1;
+                freed += size;
+            }
+            Err(e) => log::warn!("Failed to remove {:?}: {}", path, e),
+        }
+    }
+    log::info!("Removed {} temp files ({} bytes)", removed, freed);
+    (removed, freed)
+}
+
+/// Check that a path is one of the app's temp files: directly in the temp
+/// directory, with the app's prefix
+pub fn is_own(path: &Path) -> bool {
+    let in_temp_dir = path.parent().is_some_and(|dir| dir == std::env::temp_dir());
+    let prefixed = path
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with(PREFIX));
+    in_temp_dir && prefixed
+}
+
+/// Size of a file, or of everything in a directory
+fn disk_size(path: &Path) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| disk_size(&entry.path()))
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// Human-readable size, e.g. "12.3 KB"
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 3] = ["KB", "MB", "GB"];
+    if bytes < 1000 {
+        return format!("{} bytes", bytes);
+    }
+    let mut size = bytes as f64 / 1000.0;
+    let mut unit = 0;
+    while size >= 1000.0 && unit < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_prefixed_files_in_the_temp_dir_are_own() {
+        let temp_dir = std::env::temp_dir();
+        assert!(is_own(&temp_dir.join(format!("{}abc.txt", PREFIX))));
+        assert!(!is_own(&temp_dir.join("notes.txt")));
+        assert!(!is_own(&temp_dir.join("dir").join(format!("{}abc.txt", PREFIX))));
+        assert!(!is_own(Path::new("/Users/me/notes.md")));
+    }
+}
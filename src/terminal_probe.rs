@@ -0,0 +1,75 @@
+//! Whether terminals can be waited on, found out by probing them
+//!
+//! With `terminal.probe_waiting`, the configured terminal is probed once in
+//! the background at startup (see `Terminal::probe_waits`) and the result is
+//! cached in a small state file, so builds and configs that detach (or don't)
+//! get the right wait strategy. Without a result, the built-in list is used.
+
+use crate::config::Config;
+use crate::terminal::{LaunchOptions, Terminal};
+use anyhow::{Context, Result};
+use directories::ProjectDirs;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+
+/// Path of the file caching probe results
+fn cache_path() -> Option<PathBuf> {
+    ProjectDirs::from("com", "helix-anywhere", "helix-anywhere")
+        .map(|dirs| dirs.data_dir().join("terminal_waits.toml"))
+}
+
+/// Probe results, by the terminal's config name
+fn load() -> BTreeMap<String, bool> {
+    cache_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| toml::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save(results: &BTreeMap<String, bool>) -> Result<()> {
+    let path = cache_path().context("Could not determine data directory")?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create data directory: {:?}", dir))?;
+    }
+    let content = toml::to_string(results).context("Failed to serialize probe results")?;
+    fs::write(&path, content)
+        .with_context(|| format!("Failed to write probe results: {:?}", path))
+}
+
+/// Check if the edit has to be detected by polling instead of waiting on the terminal
+pub fn needs_polling(config: &Config, terminal: Terminal) -> bool {
+    if config.terminal.probe_waiting {
+        if let Some(waits) = load().get(terminal.config_name()) {
+            return !waits;
+        }
+    }
+    terminal.needs_polling()
+}
+
+/// Probe the configured terminal in the background, unless it's been probed before
+pub fn start(config: &Config) {
+    let Some(terminal) = Terminal::from_name(&config.terminal.name) else {
+        return;
+    };
+    if !terminal.is_installed() || load().contains_key(terminal.config_name()) {
+        return;
+    }
+
+    let options = LaunchOptions::from_config(config);
+    thread::spawn(move || {
+        log::info!("Probing whether {} can be waited on", terminal.display_name());
+        match terminal.probe_waits(&options) {
+            Ok(waits) => {
+                let mut results = load();
+                results.insert(terminal.config_name().to_string(), waits);
+                if let Err(e) = save(&results) {
+                    log::warn!("Failed to save probe result: {}", e);
+                }
+            }
+            Err(e) => log::warn!("Failed to probe {}: {}", terminal.display_name(), e),
+        }
+    });
+}
@@ -0,0 +1,314 @@
+//! URL scheme handler
+//!
+//! Lets other tools trigger an edit with `helix-anywhere://edit?text=...`.
+//! The text is opened in the configured editor without touching the current
+//! selection. The result is passed to the `x-success` callback URL when one is
+//! given (as its `text` query parameter) and the text was changed, and put on
+//! the clipboard otherwise.
+//!
+//! With `pasteboard=<name>`, the text is read from (when `text` isn't given)
+//! and the result written to that named pasteboard instead of the general one.
+//! Since any web page can open these URLs, system pasteboards and callbacks to
+//! anything but another app's URL scheme are refused.
+
+use crate::clipboard::{self, ClipboardAccess, NamedPasteboard, SystemClipboard};
+use crate::config::Config;
+use crate::edit_session;
+use cocoa::base::{id, nil};
+use objc::declare::ClassDecl;
+use objc::runtime::{Class, Object, Sel};
+use objc::{class, msg_send, sel, sel_impl};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+
+/// URL scheme registered in Info.plist (see `osx_url_schemes` in Cargo.toml)
+pub const SCHEME: &str = "helix-anywhere";
+
+/// Schemes that aren't app callbacks and are never opened with the result
+const REFUSED_CALLBACK_SCHEMES: &[&str] = &[
+    "file", "http", "https", "ftp", "data", "javascript", "mailto", "sms", "tel", "facetime", "vnc",
+    "smb", "afp", "ssh", "x-apple-helpviewer", SCHEME,
+];
+
+// Apple Event codes (from AE/AERegistry.h)
+const K_INTERNET_EVENT_CLASS: u32 = 0x4755_524C; // 'GURL'
+const K_AE_GET_URL: u32 = 0x4755_524C; // 'GURL'
+const KEY_DIRECT_OBJECT: u32 = 0x2D2D_2D2D; // '----'
+
+// Store config globally for the event handler
+static mut URL_CONFIG: Option<Arc<Mutex<Config>>> = None;
+
+/// A parsed `helix-anywhere://edit` request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EditRequest {
+    /// Text to open in the editor
+    pub text: String,
+    /// URL to open with the result, as in x-callback-url
+    pub success_url: Option<String>,
+    /// Named pasteboard to use instead of the general one
+    pub pasteboard: Option<String>,
+}
+
+impl EditRequest {
+    /// Parse a `helix-anywhere://edit?text=...&x-success=...` URL
+    pub fn parse(url: &str) -> Option<Self> {
+        let rest = url.strip_prefix(SCHEME)?.strip_prefix("://")?;
+        let (action, query) = rest.split_once('?').unwrap_or((rest, ""));
+        if action.trim_end_matches('/') != "edit" {
+            return None;
+        }
+
+        let mut text = None;
+        let mut success_url = None;
+        let mut pasteboard = None;
+        for pair in query.split('&').filter(|p| !p.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            match key {
+                "text" => text = Some(percent_decode(value)),
+                "x-success" => success_url = Some(percent_decode(value)),
+                "pasteboard" => pasteboard = Some(percent_decode(value)),
+                other => log::warn!("Ignoring unknown URL parameter: {}", other),
+            }
+        }
+
+        Some(Self {
+            text: text.unwrap_or_default(),
+            success_url,
+            pasteboard,
+        })
+    }
+
+    /// Why the request can't be run, if it's one a web page could abuse
+    pub fn refusal(&self) -> Option<String> {
+        if let Some(ref name) = self.pasteboard {
+            if name.is_empty() || clipboard::is_system_pasteboard(name) {
+                return Some(format!("Refusing to use pasteboard '{}'", name));
+            }
+        }
+        if let Some(ref callback) = self.success_url {
+            if !is_callback_url(callback) {
+                return Some(format!("Refusing x-success URL: {}", callback));
+            }
+        }
+        None
+    }
+}
+
+/// Whether a URL is a callback into another app's URL scheme
+pub fn is_callback_url(url: &str) -> bool {
+    let scheme = match url.split_once(':') {
+        Some((scheme, _)) => scheme,
+        None => return false,
+    };
+    let valid = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c));
+    valid && !REFUSED_CALLBACK_SCHEMES.iter().any(|refused| scheme.eq_ignore_ascii_case(refused))
+}
+
+/// Decode a percent-encoded URL component (`+` is treated as a space)
+pub fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                    Some(b) => {
+                        out.push(b);
+                        i += 3;
+                        continue;
+                    }
+                    None => out.push(b'%'),
+                }
+            }
+            b'+' => out.push(b' '),
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Percent-encode a string for use as a URL query value
+pub fn percent_encode(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for b in input.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(b as char)
+            }
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+/// Register the Apple Event handler for the URL scheme
+///
+/// Must be called before the app event loop starts so that a URL which
+/// launched the app is delivered to us.
+pub fn register(config: Arc<Mutex<Config>>) {
+    unsafe {
+        URL_CONFIG = Some(config);
+
+        register_url_handler_class();
+        let handler_class = Class::get("UrlHandler").unwrap();
+        let handler: id = msg_send![handler_class, new];
+
+        let manager: id = msg_send![class!(NSAppleEventManager), sharedAppleEventManager];
+        let _: () = msg_send![manager,
+            setEventHandler: handler
+            andSelector: sel!(handleGetURLEvent:withReplyEvent:)
+            forEventClass: K_INTERNET_EVENT_CLASS
+            andEventID: K_AE_GET_URL];
+    }
+    log::info!("Registered {}:// URL handler", SCHEME);
+}
+
+/// Register the Objective-C class receiving URL Apple Events
+fn register_url_handler_class() {
+    if Class::get("UrlHandler").is_some() {
+        return; // Already registered
+    }
+
+    let mut decl = ClassDecl::new("UrlHandler", class!(NSObject)).unwrap();
+
+    extern "C" fn handle_get_url(_this: &Object, _cmd: Sel, event: id, _reply: id) {
+        unsafe {
+            let descriptor: id = msg_send![event, paramDescriptorForKeyword: KEY_DIRECT_OBJECT];
+            if descriptor == nil {
+                return;
+            }
+            let url: id = msg_send![descriptor, stringValue];
+            if url == nil {
+                return;
+            }
+            let url: *const i8 = msg_send![url, UTF8String];
+            if url.is_null() {
+                return;
+            }
+            let url = std::ffi::CStr::from_ptr(url).to_string_lossy().to_string();
+            handle_url(&url);
+        }
+    }
+
+    unsafe {
+        decl.add_method(
+            sel!(handleGetURLEvent:withReplyEvent:),
+            handle_get_url as extern "C" fn(&Object, Sel, id, id),
+        );
+    }
+
+    decl.register();
+}
+
+/// Handle an incoming URL (runs the edit on a background thread)
+fn handle_url(url: &str) {
+    let request = match EditRequest::parse(url) {
+        Some(r) => r,
+        None => {
+            log::warn!("Unsupported URL: {}", url);
+            return;
+        }
+    };
+    if let Some(reason) = request.refusal() {
+        log::warn!("{}", reason);
+        return;
+    }
+
+    // Clone config data so we don't hold the lock during the edit
+    let config = unsafe {
+        match URL_CONFIG {
+            Some(ref config) => config.lock().unwrap().clone(),
+            None => return,
+        }
+    };
+
+    log::info!("Edit requested via URL ({} characters)", request.text.len());
+    std::thread::spawn(move || {
+        if let Err(e) = run_url_edit(&config, &request) {
+            log::error!("URL edit failed: {}", e);
+        }
+    });
+}
+
+/// Edit the requested text and deliver the result
+fn run_url_edit(config: &Config, request: &EditRequest) -> anyhow::Result<()> {
+    let clipboard: Box<dyn ClipboardAccess> = match request.pasteboard {
+        Some(ref name) => Box::new(NamedPasteboard { name: name.clone() }),
+        None => Box::new(SystemClipboard),
+    };
+
+    // Without a text parameter, edit what's on the named pasteboard
+    let text = match request.pasteboard {
+        Some(_) if request.text.is_empty() => clipboard.get_text()?,
+        _ => request.text.clone(),
+    };
+    let edited = edit_session::edit_text(config, &text, None)?;
+
+    let edited = match edited {
+        Some(edited) => edited,
+        None => {
+            // Only text the user changed is handed out, so a link can't read a pasteboard
+            log::info!("Text left unchanged, nothing to deliver");
+            return Ok(());
+        }
+    };
+    match &request.success_url {
+        Some(callback) => {
+            Command::new("open").arg(success_callback(callback, &edited)).spawn()?;
+        }
+        None => {
+            clipboard.set_text(&edited)?;
+            log::info!("Edited text placed on the clipboard");
+        }
+    }
+    Ok(())
+}
+
+/// The x-success URL with the edited text appended as its `text` parameter
+fn success_callback(callback: &str, text: &str) -> String {
+    let separator = if callback.contains('?') { '&' } else { '?' };
+    format!("{}{}text={}", callback, separator, percent_encode(text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit_urls_are_parsed() {
+        let request = EditRequest::parse("helix-anywhere://edit?text=a%20b+c&x-success=myapp%3A%2F%2Fdone").unwrap();
+        assert_eq!(request.text, "a b c");
+        assert_eq!(request.success_url.as_deref(), Some("myapp://done"));
+        assert_eq!(request.pasteboard, None);
+        assert!(EditRequest::parse("helix-anywhere://open?text=a").is_none());
+        assert!(EditRequest::parse("other://edit?text=a").is_none());
+    }
+
+    #[test]
+    fn only_app_callbacks_are_opened() {
+        assert!(is_callback_url("myapp://done"));
+        assert!(is_callback_url("raycast://extensions/x?y=1"));
+        assert!(!is_callback_url("file:///etc/passwd"));
+        assert!(!is_callback_url("FILE:///tmp/a"));
+        assert!(!is_callback_url("https://example.com/collect"));
+        assert!(!is_callback_url("helix-anywhere://edit"));
+        assert!(!is_callback_url("/Applications/Calculator.app"));
+        assert!(!is_callback_url("1app://done"));
+        assert_eq!(success_callback("myapp://done?id=1", "a&b"), "myapp://done?id=1&text=a%26b");
+    }
+
+    #[test]
+    fn system_pasteboards_and_other_callbacks_are_refused() {
+        let refused = |url: &str| EditRequest::parse(url).unwrap().refusal().is_some();
+        assert!(refused("helix-anywhere://edit?pasteboard=Apple%20CFPasteboard%20general"));
+        assert!(refused("helix-anywhere://edit?pasteboard=com.apple.find"));
+        assert!(refused("helix-anywhere://edit?pasteboard="));
+        assert!(refused("helix-anywhere://edit?text=a&x-success=file%3A%2F%2F%2Ftmp"));
+        assert!(!refused("helix-anywhere://edit?pasteboard=com.example.myscript&x-success=myapp%3A%2F%2Fdone"));
+        assert!(!refused("helix-anywhere://edit?text=a"));
+    }
+}